@@ -1,21 +1,45 @@
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::Path;
 
-use crate::cache::init_cache_manager_with_expire;
+use crate::cache::{get_cache_manager, init_cache_manager_with_expire};
 use crate::core::config::world::{init_world_list, world_list};
-use crate::error::Result;
+use crate::error::{GetterError, Result};
+use crate::websdk::cloud_rules::data::config_list::ConfigList;
 use crate::websdk::repo::api;
+use crate::websdk::repo::provider;
 
-use crate::utils::json::json_to_string;
+use crate::utils::json::{json_to_string, lenient_string_to_json};
+
+#[cfg(feature = "shell")]
+pub use crate::utils::exec::{ExecPolicy, ExecPolicyError};
 
 #[allow(dead_code)]
 pub async fn init(data_dir: &Path, cache_dir: &Path, global_expire_time: u64) -> Result<()> {
+    init_with_expire_bounds(data_dir, cache_dir, global_expire_time, None, None).await
+}
+
+/// `init`, with bounds for the expire times servers send in Cache-Control
+/// or Expires. Without `min_expire_time` they never undercut
+/// `global_expire_time`.
+pub async fn init_with_expire_bounds(
+    data_dir: &Path,
+    cache_dir: &Path,
+    global_expire_time: u64,
+    min_expire_time: Option<u64>,
+    max_expire_time: Option<u64>,
+) -> Result<()> {
     // world list
     let world_list_path = data_dir.join(world_list::WORLD_CONFIG_LIST_NAME);
     init_world_list(&world_list_path).await?;
     // cache
     let local_cache_path = cache_dir.join("local_cache");
     init_cache_manager_with_expire(local_cache_path.as_path(), global_expire_time).await;
+    get_cache_manager()
+        .await
+        .lock()
+        .await
+        .set_expire_time_bounds(min_expire_time, max_expire_time);
     Ok(())
 }
 
@@ -49,3 +73,182 @@ pub async fn get_releases<'a>(
         .await
         .map(|data| json_to_string(&data).unwrap())
 }
+
+/// Normalizes a locally installed version with the app's version rules.
+#[allow(dead_code)]
+pub fn normalize_version<'a>(
+    app_data: &BTreeMap<&'a str, &'a str>,
+    hub_data: &BTreeMap<&'a str, &'a str>,
+    version: &str,
+) -> Result<String> {
+    api::normalize_version(app_data, hub_data, version)
+        .map_err(|e| GetterError::new("Version", "invalid version rules", Box::new(e)))
+}
+
+/// The asset of a release, as returned by `get_releases`, to download by
+/// the app's `asset_filter` and `preferred_abi`. The error lists the
+/// release's assets when none fits.
+#[allow(dead_code)]
+pub fn select_asset<'a>(
+    app_data: &BTreeMap<&'a str, &'a str>,
+    hub_data: &BTreeMap<&'a str, &'a str>,
+    release: &str,
+) -> Result<String> {
+    let release = lenient_string_to_json(release)
+        .map_err(|e| GetterError::new("Asset", "invalid release", Box::new(e)))?;
+    let asset = api::select_asset(app_data, hub_data, &release)
+        .map_err(|e| GetterError::new("Asset", &e.to_string(), e))?;
+    json_to_string(&asset).map_err(|e| GetterError::new("Asset", "encode failed", Box::new(e)))
+}
+
+/// Apps on the hub matching `query`, as a JSON list of results that each
+/// carry the app_data to track the app with.
+#[allow(dead_code)]
+pub async fn search_apps<'a>(
+    uuid: &str,
+    hub_data: &BTreeMap<&'a str, &'a str>,
+    query: &str,
+) -> Result<String> {
+    let results = api::search_apps(uuid, hub_data, query)
+        .await
+        .map_err(|e| GetterError::new("Search", &e.to_string(), e))?;
+    json_to_string(&results).map_err(|e| GetterError::new("Search", "encode failed", Box::new(e)))
+}
+
+/// app_data keys the hub requires that the app lacks. Such apps are never
+/// checked, so list them to the user. None if the hub is unknown.
+#[allow(dead_code)]
+pub fn get_missing_app_keys<'a>(
+    uuid: &str,
+    app_data: &BTreeMap<&'a str, &'a str>,
+    hub_data: &BTreeMap<&'a str, &'a str>,
+) -> Option<Vec<String>> {
+    api::get_missing_app_keys(uuid, app_data, hub_data)
+        .map(|keys| keys.iter().map(|key| key.to_string()).collect())
+}
+
+/// Why the app's `${env:NAME}`/`${file:/path}` references can't be
+/// resolved; such apps are never checked either.
+#[allow(dead_code)]
+pub fn get_unresolved_refs<'a>(
+    app_data: &BTreeMap<&'a str, &'a str>,
+    hub_data: &BTreeMap<&'a str, &'a str>,
+) -> Vec<String> {
+    api::get_unresolved_refs(app_data, hub_data)
+}
+
+#[allow(dead_code)]
+pub async fn clear_cache_for_hub(uuid: &str) -> Result<()> {
+    api::clear_cache_for_hub(uuid)
+        .await
+        .map_err(|e| GetterError::new("Cache", "clear cache failed", Box::new(e)))
+}
+
+#[allow(dead_code)]
+pub async fn clear_cache_for_app<'a>(
+    uuid: &str,
+    app_data: &BTreeMap<&'a str, &'a str>,
+    hub_data: &BTreeMap<&'a str, &'a str>,
+) {
+    api::clear_cache_for_app(uuid, app_data, hub_data).await
+}
+
+/// Deletes expired cache entries, which init also does. Returns the number
+/// of files removed.
+#[allow(dead_code)]
+pub async fn clean_cache() -> Result<usize> {
+    get_cache_manager()
+        .await
+        .lock()
+        .await
+        .remove_expired()
+        .await
+        .map_err(|e| GetterError::new("Cache", "clean cache failed", Box::new(e)))
+}
+
+/// Replaces the policy every command run by the shell provider is checked
+/// against; the default allows any command.
+#[cfg(feature = "shell")]
+pub fn set_exec_policy(policy: ExecPolicy) {
+    crate::utils::exec::set_exec_policy(policy)
+}
+
+/// Lets providers use tokens from the environment (e.g. `GITHUB_TOKEN`) and
+/// netrc when hub_data has none. Off by default; they are never sent through
+/// a configured reverse proxy.
+#[allow(dead_code)]
+pub fn set_credentials_auto_discover(enabled: bool) {
+    provider::base_provider::set_credentials_auto_discover(enabled)
+}
+
+/// Checks an UpgradeAll cloud config, returning one message per issue found.
+#[allow(dead_code)]
+pub fn validate_cloud_config(json: &str) -> Result<Vec<String>> {
+    let config_list: ConfigList = lenient_string_to_json(json)
+        .map_err(|e| GetterError::new("CloudConfig", "parse failed", Box::new(e)))?;
+    Ok(config_list
+        .validate()
+        .iter()
+        .map(|issue| issue.to_string())
+        .collect())
+}
+
+/// What a getter build is, for bug reports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    /// Short commit hash, or "unknown" outside a git checkout.
+    pub git_hash: String,
+    /// Unix time of the build, or `SOURCE_DATE_EPOCH` when set.
+    pub build_time: u64,
+    pub target: String,
+    pub features: Vec<String>,
+    pub providers: Vec<ProviderInfo>,
+    /// Hubs whose provider panicked on construction, with the message.
+    pub skipped_providers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderInfo {
+    pub hub_uuid: String,
+    pub parse_version: u32,
+}
+
+pub fn build_info() -> BuildInfo {
+    let features = [
+        (
+            "rustls-platform-verifier",
+            cfg!(feature = "rustls-platform-verifier"),
+        ),
+        (
+            "rustls-platform-verifier-android",
+            cfg!(feature = "rustls-platform-verifier-android"),
+        ),
+        ("webpki-roots", cfg!(feature = "webpki-roots")),
+        ("native-tokio", cfg!(feature = "native-tokio")),
+        ("shell", cfg!(feature = "shell")),
+        ("test-util", cfg!(feature = "test-util")),
+    ];
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: env!("GETTER_GIT_HASH").to_string(),
+        build_time: env!("GETTER_BUILD_TIME").parse().unwrap_or_default(),
+        target: env!("GETTER_TARGET").to_string(),
+        features: features
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(feature, _)| feature.to_string())
+            .collect(),
+        providers: provider::get_provider_list()
+            .into_iter()
+            .map(|(hub_uuid, parse_version)| ProviderInfo {
+                hub_uuid: hub_uuid.to_string(),
+                parse_version,
+            })
+            .collect(),
+        skipped_providers: provider::get_skipped_providers()
+            .into_iter()
+            .map(|(hub_uuid, error)| format!("{}: {}", hub_uuid, error))
+            .collect(),
+    }
+}
@@ -1,4 +1,5 @@
 mod local;
+mod lru;
 pub mod manager;
 
 use once_cell::sync::Lazy;
@@ -13,20 +14,23 @@ static INSTANCE_CONTAINER: Lazy<InstanceContainer<CacheManager>> =
     Lazy::new(|| InstanceContainer::new(CacheManager::new()));
 
 pub async fn init_cache_manager(local_cache_dir: &Path) {
-    get_cache_manager()
-        .await
-        .lock()
-        .await
-        .set_local_cache_dir(local_cache_dir);
+    let cache_manager = get_cache_manager().await;
+    let mut cache_manager = cache_manager.lock().await;
+    cache_manager.set_local_cache_dir(local_cache_dir);
+    // A failed cleanup only leaves stale files behind; the cache still works
+    let _ = cache_manager.migrate_layout().await;
+    let _ = cache_manager.remove_expired().await;
 }
 
 pub async fn init_cache_manager_with_expire(local_cache_path: &Path, expire_time: u64) {
-    get_cache_manager()
-        .await
-        .lock()
-        .await
+    let cache_manager = get_cache_manager().await;
+    let mut cache_manager = cache_manager.lock().await;
+    cache_manager
         .set_local_cache_dir(local_cache_path)
         .set_global_expire_time(expire_time);
+    // A failed cleanup only leaves stale files behind; the cache still works
+    let _ = cache_manager.migrate_layout().await;
+    let _ = cache_manager.remove_expired().await;
 }
 
 pub async fn get_cache_manager<'a>() -> Arc<Mutex<CacheManager>> {
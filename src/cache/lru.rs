@@ -0,0 +1,84 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// Sizes of the cached entries, in the order they were last used.
+#[derive(Debug, Default)]
+pub struct LruIndex {
+    // key -> (last use, size)
+    entries: HashMap<String, (u64, u64)>,
+    order: BTreeMap<u64, String>,
+    total_bytes: u64,
+    next_use: u64,
+}
+
+impl LruIndex {
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Adds or resizes `key`, as the most recently used entry.
+    pub fn insert(&mut self, key: &str, size: u64) {
+        self.remove(key);
+        let used = self.next_use();
+        self.entries.insert(key.to_string(), (used, size));
+        self.order.insert(used, key.to_string());
+        self.total_bytes += size;
+    }
+
+    /// Marks `key` as the most recently used entry, if it is tracked.
+    pub fn touch(&mut self, key: &str) {
+        let used = self.next_use();
+        if let Some((last_used, _)) = self.entries.get_mut(key) {
+            let key = self.order.remove(last_used).unwrap_or_default();
+            *last_used = used;
+            self.order.insert(used, key);
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some((last_used, size)) = self.entries.remove(key) {
+            self.order.remove(&last_used);
+            self.total_bytes -= size;
+        }
+    }
+
+    /// Removes and returns the least recently used key.
+    pub fn pop_lru(&mut self) -> Option<String> {
+        let (_, key) = self.order.pop_first()?;
+        if let Some((_, size)) = self.entries.remove(&key) {
+            self.total_bytes -= size;
+        }
+        Some(key)
+    }
+
+    fn next_use(&mut self) -> u64 {
+        self.next_use += 1;
+        self.next_use
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_index() {
+        let mut index = LruIndex::default();
+        index.insert("a", 1);
+        index.insert("b", 2);
+        index.insert("c", 4);
+        index.touch("a");
+        index.insert("b", 8);
+        assert_eq!(index.entry_count(), 3);
+        assert_eq!(index.total_bytes(), 13);
+        assert_eq!(index.pop_lru().as_deref(), Some("c"));
+        index.remove("b");
+        assert_eq!(index.total_bytes(), 1);
+        assert_eq!(index.pop_lru().as_deref(), Some("a"));
+        assert_eq!(index.pop_lru(), None);
+        assert_eq!(index.total_bytes(), 0);
+    }
+}
@@ -1,9 +1,14 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
 use bytes::Bytes;
+use serde::{Deserialize, Serialize};
 
 use super::local::LocalCacheItem;
-use crate::utils::time::get_now_unix;
+use super::lru::LruIndex;
+use crate::utils::http::Validators;
+use crate::utils::json::{bytes_to_json, json_to_bytes};
+use crate::utils::time::{Clock, SystemClock};
 
 #[derive(Debug, Eq, Hash, PartialEq)]
 pub enum GroupType {
@@ -11,9 +16,45 @@ pub enum GroupType {
     Api,
 }
 
+impl GroupType {
+    pub const ALL: [GroupType; 2] = [GroupType::RepoInside, GroupType::Api];
+}
+
+// Bump when the key layout changes; older entries are dropped on init
+const LAYOUT_VERSION: &str = "1";
+const LAYOUT_VERSION_FILE: &str = "layout_version";
+
+// How long past its expiry an entry with validators is kept for `get_stale`
+const STALE_EXPIRE_TIME: u64 = 30 * 24 * 60 * 60;
+
+// Stored next to an entry that carries its own expire time or validators
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheMeta {
+    expire_time: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    validators: Option<Validators>,
+}
+
+/// Counters since the manager was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
 pub struct CacheManager {
     local_cache_dir: Option<PathBuf>,
     global_expire_time: Option<u64>,
+    min_expire_time: Option<u64>,
+    max_expire_time: Option<u64>,
+    max_entries: Option<usize>,
+    max_total_bytes: Option<u64>,
+    // Built from the cache dir on first use when limits are set, and
+    // rebuilt after removals that bypass it
+    index: Option<LruIndex>,
+    stats: CacheStats,
+    clock: Arc<dyn Clock>,
 }
 
 impl CacheManager {
@@ -21,11 +62,36 @@ impl CacheManager {
         Self {
             local_cache_dir: None,
             global_expire_time: None,
+            min_expire_time: None,
+            max_expire_time: None,
+            max_entries: None,
+            max_total_bytes: None,
+            index: None,
+            stats: CacheStats::default(),
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// A manager that evicts the least recently used entries once a save
+    /// takes it past `max_entries` entries or `max_total_bytes` of entry
+    /// data. The entry just saved is never evicted.
+    #[allow(dead_code)]
+    pub fn with_limits(max_entries: usize, max_total_bytes: u64) -> Self {
+        Self {
+            max_entries: Some(max_entries),
+            max_total_bytes: Some(max_total_bytes),
+            ..Self::new()
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
     pub fn set_local_cache_dir(&mut self, local_cache_dir: &Path) -> &mut Self {
         self.local_cache_dir = Some(local_cache_dir.to_path_buf());
+        self.index = None;
         self
     }
 
@@ -34,10 +100,54 @@ impl CacheManager {
         self
     }
 
+    /// Bounds applied to per-entry expire times passed to `save_with_expire`.
+    ///
+    /// Without an explicit minimum the global expire time is the floor, so
+    /// a short server-side max-age never expires entries earlier than the
+    /// user configured.
+    pub fn set_expire_time_bounds(
+        &mut self,
+        min_expire_time: Option<u64>,
+        max_expire_time: Option<u64>,
+    ) -> &mut Self {
+        self.min_expire_time = min_expire_time;
+        self.max_expire_time = max_expire_time;
+        self
+    }
+
+    /// Replaces the system clock that entry expiry is checked against.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = clock;
+        self
+    }
+
+    fn clamp_expire_time(&self, expire_time: u64) -> u64 {
+        let expire_time = self
+            .min_expire_time
+            .or(self.global_expire_time)
+            .map_or(expire_time, |min| expire_time.max(min));
+        self.max_expire_time
+            .map_or(expire_time, |max| expire_time.min(max))
+    }
+
     fn get_local_cache_key(group: &GroupType, key: &str) -> String {
         format!("{:?}_{}", group, key)
     }
 
+    fn get_local_meta_key(group: &GroupType, key: &str) -> String {
+        format!("{}.meta", Self::get_local_cache_key(group, key))
+    }
+
+    async fn get_meta(&self, group: &GroupType, key: &str) -> CacheMeta {
+        if let Ok(item) = self.get_local_item(&Self::get_local_meta_key(group, key)) {
+            if let Ok(data) = item.get(Bytes::from).await {
+                return bytes_to_json(&data).unwrap_or_default();
+            }
+        }
+        CacheMeta::default()
+    }
+
     async fn get_local(
         &self,
         group: &GroupType,
@@ -46,8 +156,12 @@ impl CacheManager {
     ) -> Option<Bytes> {
         let local_cache_item = self.get_local_cache_item(group, key).ok()?;
         if let Ok(time) = local_cache_item.get_cache_time().await {
+            let expire_time = match expire_time {
+                Some(expire_time) => Some(expire_time),
+                None => self.get_meta(group, key).await.expire_time,
+            };
             if let Some(expire_time) = expire_time.or(self.global_expire_time) {
-                if time + expire_time < get_now_unix() {
+                if time.saturating_add(expire_time) < self.clock.now() {
                     return None;
                 }
             }
@@ -59,12 +173,54 @@ impl CacheManager {
     }
 
     pub async fn get(
-        &self,
+        &mut self,
         group: &GroupType,
         key: &str,
         expire_time: Option<u64>,
     ) -> Option<Bytes> {
-        self.get_local(group, key, expire_time).await
+        let data = self.get_local(group, key, expire_time).await;
+        if data.is_some() {
+            self.stats.hits += 1;
+            if let Some(index) = self.get_index().await {
+                index.touch(&Self::get_local_cache_key(group, key));
+            }
+        } else {
+            self.stats.misses += 1;
+        }
+        data
+    }
+
+    /// The entry and its validators, expired or not, for revalidating it.
+    /// None if no validators were set for it.
+    pub async fn get_stale(&self, group: &GroupType, key: &str) -> Option<(Bytes, Validators)> {
+        let validators = self.get_meta(group, key).await.validators?;
+        let local_cache_item = self.get_local_cache_item(group, key).ok()?;
+        let data = local_cache_item.get(|data| data).await.ok()?;
+        Some((Bytes::from(data), validators))
+    }
+
+    /// Attaches `validators` to the saved entry; saving it again drops them.
+    pub async fn set_validators(
+        &mut self,
+        group: &GroupType,
+        key: &str,
+        validators: Validators,
+    ) -> Result<(), std::io::Error> {
+        let mut meta = self.get_meta(group, key).await;
+        meta.validators = Some(validators);
+        self.save_meta(group, key, &meta).await
+    }
+
+    async fn save_meta(
+        &self,
+        group: &GroupType,
+        key: &str,
+        meta: &CacheMeta,
+    ) -> Result<(), std::io::Error> {
+        let meta_item = self.get_local_item(&Self::get_local_meta_key(group, key))?;
+        let meta = json_to_bytes(meta)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        meta_item.save(meta, |data| data.into()).await
     }
 
     pub async fn save(
@@ -73,16 +229,309 @@ impl CacheManager {
         key: &str,
         value: Bytes,
     ) -> Result<(), std::io::Error> {
+        self.save_with_expire(group, key, value, None).await
+    }
+
+    /// Save with an entry-specific expire time, which overrides the global
+    /// one on `get` and is clamped to the configured bounds.
+    pub async fn save_with_expire(
+        &mut self,
+        group: &GroupType,
+        key: &str,
+        value: Bytes,
+        expire_time: Option<u64>,
+    ) -> Result<(), std::io::Error> {
+        let expire_time = expire_time.map(|expire_time| self.clamp_expire_time(expire_time));
+        self.save_with_fixed_expire(group, key, value, expire_time)
+            .await
+    }
+
+    /// `save_with_expire` without the clamping, for expire times the caller
+    /// chose itself rather than took from a server.
+    pub async fn save_with_fixed_expire(
+        &mut self,
+        group: &GroupType,
+        key: &str,
+        value: Bytes,
+        expire_time: Option<u64>,
+    ) -> Result<(), std::io::Error> {
+        let meta_item = self.get_local_item(&Self::get_local_meta_key(group, key))?;
+        if let Some(expire_time) = expire_time {
+            let meta = CacheMeta {
+                expire_time: Some(expire_time),
+                validators: None,
+            };
+            self.save_meta(group, key, &meta).await?;
+        } else if let Err(e) = meta_item.remove().await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e);
+            }
+        }
+        let size = value.len() as u64;
         let local_cache_item = self.get_local_cache_item(group, key)?;
-        local_cache_item.save(value, |data| data.into()).await
+        local_cache_item.save(value, |data| data.into()).await?;
+        if let Some(index) = self.get_index().await {
+            index.insert(&Self::get_local_cache_key(group, key), size);
+        }
+        self.evict().await
+    }
+
+    async fn evict(&mut self) -> Result<(), std::io::Error> {
+        while let Some(index) = self.index.as_mut() {
+            let over_limits = self
+                .max_entries
+                .is_some_and(|max| index.entry_count() > max)
+                || self
+                    .max_total_bytes
+                    .is_some_and(|max| index.total_bytes() > max);
+            // The most recent entry, just saved, stays
+            if !over_limits || index.entry_count() <= 1 {
+                break;
+            }
+            let Some(local_cache_key) = index.pop_lru() else {
+                break;
+            };
+            let local_cache_item = self.get_local_item(&local_cache_key)?;
+            match local_cache_item.remove().await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => (),
+            }
+            if let Ok(meta_item) = self.get_local_item(&format!("{}.meta", local_cache_key)) {
+                let _ = meta_item.remove().await;
+            }
+            self.stats.evictions += 1;
+        }
+        Ok(())
+    }
+
+    /// The LRU index, None without limits or a cache dir.
+    async fn get_index(&mut self) -> Option<&mut LruIndex> {
+        if self.max_entries.is_none() && self.max_total_bytes.is_none() {
+            return None;
+        }
+        if self.index.is_none() {
+            self.index = Some(self.load_index().await.ok()?);
+        }
+        self.index.as_mut()
+    }
+
+    /// Indexes the entries already in the cache dir, oldest write first.
+    async fn load_index(&self) -> Result<LruIndex, std::io::Error> {
+        let local_cache_dir = self.local_cache_dir.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "local cache dir not found")
+        })?;
+        let mut found = vec![];
+        let mut dirs = vec![local_cache_dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.ends_with(".meta")
+                    || (dir == *local_cache_dir && name == LAYOUT_VERSION_FILE)
+                {
+                    continue;
+                }
+                let Ok(relative_path) = path.strip_prefix(local_cache_dir) else {
+                    continue;
+                };
+                let local_cache_key = relative_path
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let modified = metadata.modified().ok();
+                found.push((modified, local_cache_key, metadata.len()));
+            }
+        }
+        found.sort();
+        let mut index = LruIndex::default();
+        for (_, local_cache_key, size) in found {
+            index.insert(&local_cache_key, size);
+        }
+        Ok(index)
     }
 
     #[allow(dead_code)]
     pub async fn remove(&mut self, group: &GroupType, key: &str) -> Result<(), std::io::Error> {
+        if let Some(index) = self.index.as_mut() {
+            index.remove(&Self::get_local_cache_key(group, key));
+        }
+        if let Ok(meta_item) = self.get_local_item(&Self::get_local_meta_key(group, key)) {
+            let _ = meta_item.remove().await;
+        }
         let local_cache_item = self.get_local_cache_item(group, key)?;
         local_cache_item.remove().await
     }
 
+    /// Drops everything in the cache dir if it was written with an older key
+    /// layout, since those entries would never be read or removed again.
+    pub async fn migrate_layout(&mut self) -> Result<(), std::io::Error> {
+        self.index = None;
+        let local_cache_dir = self.local_cache_dir.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "local cache dir not found")
+        })?;
+        let version_path = local_cache_dir.join(LAYOUT_VERSION_FILE);
+        if tokio::fs::read_to_string(&version_path)
+            .await
+            .ok()
+            .as_deref()
+            == Some(LAYOUT_VERSION)
+        {
+            return Ok(());
+        }
+        match tokio::fs::read_dir(local_cache_dir).await {
+            Ok(mut entries) => {
+                while let Some(entry) = entries.next_entry().await? {
+                    if entry.file_type().await?.is_dir() {
+                        tokio::fs::remove_dir_all(entry.path()).await?;
+                    } else {
+                        tokio::fs::remove_file(entry.path()).await?;
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tokio::fs::create_dir_all(local_cache_dir).await?;
+            }
+            Err(e) => return Err(e),
+        }
+        tokio::fs::write(&version_path, LAYOUT_VERSION).await
+    }
+
+    /// Remove the entries of all groups whose key starts with `prefix`.
+    ///
+    /// Keys are laid out as paths, so a prefix ending in `/` drops a whole
+    /// directory; otherwise only the last directory level is scanned. The
+    /// prefix must be a relative path of plain segments, so nothing outside
+    /// the cache dir can be reached.
+    pub async fn remove_prefix(&mut self, prefix: &str) -> Result<(), std::io::Error> {
+        self.index = None;
+        let local_cache_dir = self.local_cache_dir.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "local cache dir not found")
+        })?;
+        if prefix.contains('\\')
+            || !Path::new(prefix)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid cache key prefix: {}", prefix),
+            ));
+        }
+        for group in GroupType::ALL.iter() {
+            let path = local_cache_dir.join(Self::get_local_cache_key(group, prefix));
+            if prefix.ends_with('/') {
+                match tokio::fs::remove_dir_all(&path).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                    _ => continue,
+                }
+            }
+            let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                continue;
+            };
+            let name = name.to_string_lossy();
+            let mut entries = match tokio::fs::read_dir(parent).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                if entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(name.as_ref())
+                {
+                    if entry.file_type().await?.is_dir() {
+                        tokio::fs::remove_dir_all(entry.path()).await?;
+                    } else {
+                        tokio::fs::remove_file(entry.path()).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes expired entries, and `.meta` files whose entry is gone.
+    /// Entries with validators stay revalidatable through `get_stale` for
+    /// another 30 days; entries without any expire time are kept. Returns
+    /// the number of files removed.
+    pub async fn remove_expired(&mut self) -> Result<usize, std::io::Error> {
+        self.index = None;
+        let local_cache_dir = self.local_cache_dir.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "local cache dir not found")
+        })?;
+        let now = self.clock.now();
+        let mut removed = 0;
+        let mut dirs = vec![local_cache_dir.clone()];
+        let mut visited = vec![];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                if dir == *local_cache_dir && name == LAYOUT_VERSION_FILE {
+                    continue;
+                }
+                if let Some(entry_name) = name.strip_suffix(".meta") {
+                    // Already gone if its entry was removed earlier in the scan
+                    if !tokio::fs::try_exists(dir.join(entry_name)).await?
+                        && tokio::fs::remove_file(&path).await.is_ok()
+                    {
+                        removed += 1;
+                    }
+                    continue;
+                }
+                let meta_path = dir.join(format!("{}.meta", name));
+                let meta = match tokio::fs::read(&meta_path).await {
+                    Ok(data) => bytes_to_json::<CacheMeta>(&Bytes::from(data)).unwrap_or_default(),
+                    Err(_) => CacheMeta::default(),
+                };
+                let Some(expire_time) = meta.expire_time.or(self.global_expire_time) else {
+                    continue;
+                };
+                let expire_time = match meta.validators {
+                    Some(_) => expire_time.saturating_add(STALE_EXPIRE_TIME),
+                    None => expire_time,
+                };
+                let time = LocalCacheItem::new(&dir, &name).get_cache_time().await?;
+                if time.saturating_add(expire_time) < now {
+                    tokio::fs::remove_file(&path).await?;
+                    removed += 1;
+                    if tokio::fs::remove_file(&meta_path).await.is_ok() {
+                        removed += 1;
+                    }
+                }
+            }
+            if dir != *local_cache_dir {
+                visited.push(dir);
+            }
+        }
+        // Deepest first; non-empty directories just fail to go
+        for dir in visited.iter().rev() {
+            let _ = tokio::fs::remove_dir(dir).await;
+        }
+        Ok(removed)
+    }
+
     #[allow(dead_code)]
     pub async fn clean(&mut self) -> Result<(), std::io::Error> {
         self.clean_local().await
@@ -90,6 +539,7 @@ impl CacheManager {
 
     #[allow(dead_code)]
     async fn clean_local(&mut self) -> Result<(), std::io::Error> {
+        self.index = None;
         let local_cache_dir = self.local_cache_dir.as_ref().ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::NotFound, "local cache dir not found")
         })?;
@@ -101,17 +551,21 @@ impl CacheManager {
         group: &GroupType,
         key: &str,
     ) -> Result<LocalCacheItem, std::io::Error> {
-        let local_cache_key = Self::get_local_cache_key(group, key);
+        self.get_local_item(&Self::get_local_cache_key(group, key))
+    }
+
+    fn get_local_item(&self, local_cache_key: &str) -> Result<LocalCacheItem, std::io::Error> {
         let local_cache_dir = self.local_cache_dir.as_ref().ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::NotFound, "local cache dir not found")
         })?;
-        Ok(LocalCacheItem::new(local_cache_dir, &local_cache_key))
+        Ok(LocalCacheItem::new(local_cache_dir, local_cache_key))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::time::ManualClock;
 
     #[tokio::test]
     async fn test_cache_manager() {
@@ -182,13 +636,14 @@ mod tests {
         let group = GroupType::RepoInside;
         let key = "test_key_expire";
         let value = Bytes::from("test_value_expire");
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
         let _ = cache_manager.remove(&group, key).await;
         cache_manager
             .save(&group, key, value.clone())
             .await
             .expect("save failed");
-        // sleep 1 second
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        clock.advance(1);
         let data = cache_manager.get(&group, key, Some(0)).await;
         assert_eq!(data, None);
         let data = cache_manager
@@ -210,13 +665,14 @@ mod tests {
         let group = GroupType::RepoInside;
         let key = "test_key_expire";
         let value = Bytes::from("test_value_expire");
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
         let _ = cache_manager.remove(&group, key).await;
         cache_manager
             .save(&group, key, value.clone())
             .await
             .expect("save failed");
-        // sleep 1 second
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        clock.advance(1);
         let data = cache_manager.get(&group, key, Some(0)).await;
         assert_eq!(data, None);
         let data = cache_manager
@@ -240,13 +696,14 @@ mod tests {
         let group = GroupType::RepoInside;
         let key = "test_key_expire";
         let value = Bytes::from("test_value_expire");
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
         let _ = cache_manager.remove(&group, key).await;
         cache_manager
             .save(&group, key, value.clone())
             .await
             .expect("save failed");
-        // sleep 1 second
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        clock.advance(2);
         let data = cache_manager.get(&group, key, Some(0)).await;
         assert_eq!(data, None);
         let data = cache_manager.get(&group, key, None).await;
@@ -262,4 +719,409 @@ mod tests {
             .expect("remove failed");
         cache_manager.clean().await.expect("clean failed");
     }
+
+    #[tokio::test]
+    async fn test_cache_manager_entry_expire() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
+        cache_manager
+            .set_local_cache_dir(temp_dir.path())
+            .set_global_expire_time(100)
+            .set_expire_time_bounds(Some(0), None);
+        let group = GroupType::RepoInside;
+        let value = Bytes::from("test_value_expire");
+        cache_manager
+            .save_with_expire(&group, "short", value.clone(), Some(1))
+            .await
+            .expect("save failed");
+        cache_manager
+            .save(&group, "global", value.clone())
+            .await
+            .expect("save failed");
+        clock.advance(2);
+        assert_eq!(cache_manager.get(&group, "short", None).await, None);
+        assert_eq!(
+            cache_manager.get(&group, "global", None).await,
+            Some(value.clone())
+        );
+        // An explicit expire time still wins over the entry's own
+        assert_eq!(
+            cache_manager.get(&group, "short", Some(100)).await,
+            Some(value.clone())
+        );
+
+        // Saving without an expire time drops the previous one
+        cache_manager
+            .save(&group, "short", value.clone())
+            .await
+            .expect("save failed");
+        assert_eq!(cache_manager.get(&group, "short", None).await, Some(value));
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_stale() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
+        cache_manager
+            .set_local_cache_dir(temp_dir.path())
+            .set_expire_time_bounds(Some(0), None);
+        let group = GroupType::RepoInside;
+        let value = Bytes::from("test_value_stale");
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        };
+        cache_manager
+            .save_with_expire(&group, "key", value.clone(), Some(1))
+            .await
+            .expect("save failed");
+        assert_eq!(cache_manager.get_stale(&group, "key").await, None);
+        cache_manager
+            .set_validators(&group, "key", validators.clone())
+            .await
+            .expect("set validators failed");
+        clock.advance(2);
+        assert_eq!(cache_manager.get(&group, "key", None).await, None);
+        assert_eq!(
+            cache_manager.get_stale(&group, "key").await,
+            Some((value.clone(), validators))
+        );
+
+        // Saving the entry again drops its validators
+        cache_manager
+            .save(&group, "key", value)
+            .await
+            .expect("save failed");
+        assert_eq!(cache_manager.get_stale(&group, "key").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_expire_bounds() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
+        cache_manager
+            .set_local_cache_dir(temp_dir.path())
+            .set_expire_time_bounds(Some(60), Some(3600));
+        assert_eq!(cache_manager.clamp_expire_time(0), 60);
+        assert_eq!(cache_manager.clamp_expire_time(600), 600);
+        assert_eq!(cache_manager.clamp_expire_time(u64::MAX), 3600);
+
+        cache_manager.set_expire_time_bounds(None, Some(1));
+        let group = GroupType::RepoInside;
+        let key = "test_key_absurd";
+        cache_manager
+            .save_with_expire(&group, key, Bytes::from("value"), Some(u64::MAX))
+            .await
+            .expect("save failed");
+        clock.advance(2);
+        assert_eq!(cache_manager.get(&group, key, None).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_max_age_floor() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
+        cache_manager
+            .set_local_cache_dir(temp_dir.path())
+            .set_global_expire_time(3600);
+        let group = GroupType::RepoInside;
+        let value = Bytes::from("value");
+        cache_manager
+            .save_with_expire(&group, "max_age", value.clone(), Some(60))
+            .await
+            .expect("save failed");
+        clock.advance(61);
+        // A max-age=60 response doesn't undercut the global expire time
+        assert_eq!(
+            cache_manager.get(&group, "max_age", None).await,
+            Some(value.clone())
+        );
+
+        // A fixed expire time does
+        cache_manager
+            .save_with_fixed_expire(&group, "recheck", value.clone(), Some(60))
+            .await
+            .expect("save failed");
+        assert_eq!(cache_manager.get(&group, "recheck", None).await, None);
+
+        // Unless a lower floor is configured
+        cache_manager.set_expire_time_bounds(Some(0), None);
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
+        cache_manager
+            .save_with_expire(&group, "max_age", value.clone(), Some(60))
+            .await
+            .expect("save failed");
+        assert_eq!(
+            cache_manager.get(&group, "max_age", None).await,
+            Some(value)
+        );
+        clock.advance(61);
+        assert_eq!(cache_manager.get(&group, "max_age", None).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_remove_expired() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
+        cache_manager
+            .set_local_cache_dir(temp_dir.path())
+            .set_global_expire_time(3600)
+            .set_expire_time_bounds(Some(0), None);
+        cache_manager.migrate_layout().await.unwrap();
+        let group = GroupType::RepoInside;
+        let value = Bytes::from("value");
+        cache_manager
+            .save_with_expire(&group, "hub/old/short", value.clone(), Some(1))
+            .await
+            .unwrap();
+        cache_manager
+            .save(&group, "hub/kept", value.clone())
+            .await
+            .unwrap();
+        let orphan = temp_dir.path().join("RepoInside_hub/gone.meta");
+        std::fs::write(&orphan, "{}").unwrap();
+
+        clock.advance(2);
+        assert_eq!(cache_manager.remove_expired().await.unwrap(), 3);
+        assert!(!orphan.exists());
+        assert!(!temp_dir.path().join("RepoInside_hub/old").exists());
+        assert!(temp_dir.path().join(LAYOUT_VERSION_FILE).exists());
+        assert_eq!(
+            cache_manager.get(&group, "hub/kept", None).await,
+            Some(value)
+        );
+        assert_eq!(cache_manager.remove_expired().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_remove_expired_keeps_stale() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        let clock = Arc::new(ManualClock::starting_now());
+        cache_manager.set_clock(clock.clone());
+        cache_manager
+            .set_local_cache_dir(temp_dir.path())
+            .set_expire_time_bounds(Some(0), None);
+        let group = GroupType::RepoInside;
+        let value = Bytes::from("value");
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        };
+        cache_manager
+            .save_with_expire(&group, "hub/page", value.clone(), Some(1))
+            .await
+            .unwrap();
+        cache_manager
+            .set_validators(&group, "hub/page", validators.clone())
+            .await
+            .unwrap();
+
+        clock.advance(2);
+        assert_eq!(cache_manager.remove_expired().await.unwrap(), 0);
+        assert_eq!(cache_manager.get(&group, "hub/page", None).await, None);
+        assert_eq!(
+            cache_manager.get_stale(&group, "hub/page").await,
+            Some((value, validators))
+        );
+
+        clock.advance(STALE_EXPIRE_TIME);
+        assert_eq!(cache_manager.remove_expired().await.unwrap(), 2);
+        assert_eq!(cache_manager.get_stale(&group, "hub/page").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_remove_prefix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        cache_manager.set_local_cache_dir(temp_dir.path());
+        let value = Bytes::from("value");
+        for key in ["hub_a/app_1", "hub_a/app_2", "hub_b/app_1"] {
+            for group in GroupType::ALL.iter() {
+                cache_manager
+                    .save_with_expire(group, key, value.clone(), Some(100))
+                    .await
+                    .expect("save failed");
+            }
+        }
+
+        // The layout survives a reopen
+        let mut cache_manager = CacheManager::new();
+        cache_manager.set_local_cache_dir(temp_dir.path());
+        cache_manager.remove_prefix("hub_a/app_1").await.unwrap();
+        for group in GroupType::ALL.iter() {
+            assert_eq!(cache_manager.get(group, "hub_a/app_1", None).await, None);
+            assert!(cache_manager
+                .get(group, "hub_a/app_2", None)
+                .await
+                .is_some());
+        }
+        cache_manager.remove_prefix("hub_a/").await.unwrap();
+        for group in GroupType::ALL.iter() {
+            assert_eq!(cache_manager.get(group, "hub_a/app_2", None).await, None);
+            assert!(cache_manager
+                .get(group, "hub_b/app_1", None)
+                .await
+                .is_some());
+        }
+        // Missing prefixes are not an error
+        cache_manager.remove_prefix("hub_c/").await.unwrap();
+        cache_manager.remove_prefix("hub_c/app").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_remove_prefix_outside() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path().join("cache");
+        let victim_dir = temp_dir.path().join("victim");
+        std::fs::create_dir_all(&victim_dir).unwrap();
+        let mut cache_manager = CacheManager::new();
+        cache_manager.set_local_cache_dir(&cache_dir);
+        for prefix in [
+            "hub/../../victim/",
+            "../victim/",
+            "/victim/",
+            "hub\\..\\victim",
+        ] {
+            let result = cache_manager.remove_prefix(prefix).await;
+            assert_eq!(
+                result.unwrap_err().kind(),
+                std::io::ErrorKind::InvalidInput,
+                "{}",
+                prefix
+            );
+        }
+        assert!(victim_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_migrate_layout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path().join("cache");
+        // Entries of the old, unprefixed layout
+        std::fs::create_dir_all(cache_dir.join("RepoInside_https:/api.github.com")).unwrap();
+        std::fs::write(cache_dir.join("RepoInside_https:/api.github.com/x"), "x").unwrap();
+        std::fs::write(cache_dir.join("Api_1234"), "x").unwrap();
+
+        let mut cache_manager = CacheManager::new();
+        cache_manager.set_local_cache_dir(&cache_dir);
+        cache_manager.migrate_layout().await.unwrap();
+        assert!(!cache_dir.join("RepoInside_https:").exists());
+        assert!(!cache_dir.join("Api_1234").exists());
+
+        // Entries of the current layout survive later inits
+        let group = GroupType::Api;
+        let value = Bytes::from("value");
+        cache_manager
+            .save(&group, "hub/key", value.clone())
+            .await
+            .unwrap();
+        cache_manager.migrate_layout().await.unwrap();
+        assert_eq!(
+            cache_manager.get(&group, "hub/key", None).await,
+            Some(value)
+        );
+
+        // A missing cache dir is created
+        let mut cache_manager = CacheManager::new();
+        cache_manager.set_local_cache_dir(&temp_dir.path().join("new"));
+        cache_manager.migrate_layout().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_limits() {
+        let cache_dir = Path::new("./test_cache_manager_limits");
+        let mut cache_manager = CacheManager::with_limits(3, u64::MAX);
+        cache_manager.set_local_cache_dir(cache_dir);
+        let _ = cache_manager.clean().await;
+        let group = GroupType::RepoInside;
+        for key in ["k0", "k1", "k2"] {
+            cache_manager
+                .save(&group, key, Bytes::from(key))
+                .await
+                .expect("save failed");
+        }
+        // k1 is now the least recently used
+        assert!(cache_manager.get(&group, "k0", None).await.is_some());
+        for key in ["k3", "k4"] {
+            cache_manager
+                .save(&group, key, Bytes::from(key))
+                .await
+                .expect("save failed");
+        }
+        for key in ["k1", "k2"] {
+            assert_eq!(cache_manager.get(&group, key, None).await, None);
+            assert!(!cache_dir.join(format!("RepoInside_{}", key)).exists());
+        }
+        for key in ["k0", "k3", "k4"] {
+            assert_eq!(
+                cache_manager.get(&group, key, None).await,
+                Some(Bytes::from(key))
+            );
+        }
+        assert_eq!(
+            cache_manager.stats(),
+            CacheStats {
+                hits: 4,
+                misses: 2,
+                evictions: 2,
+            }
+        );
+        cache_manager.clean().await.expect("clean failed");
+    }
+
+    #[tokio::test]
+    async fn test_cache_manager_size_limit() {
+        let cache_dir = Path::new("./test_cache_manager_size_limit");
+        let mut cache_manager = CacheManager::with_limits(10, 10);
+        cache_manager.set_local_cache_dir(cache_dir);
+        let _ = cache_manager.clean().await;
+        let group = GroupType::Api;
+        for key in ["a", "b", "c"] {
+            cache_manager
+                .save_with_expire(&group, key, Bytes::from("1234"), Some(60))
+                .await
+                .expect("save failed");
+        }
+        assert_eq!(cache_manager.get(&group, "a", None).await, None);
+        assert!(!cache_dir.join("Api_a.meta").exists());
+        // Too big for the limit on its own, but kept over everything else
+        let big = Bytes::from("0123456789abcdef");
+        cache_manager
+            .save(&group, "nested/big", big.clone())
+            .await
+            .expect("save failed");
+        for key in ["b", "c"] {
+            assert_eq!(cache_manager.get(&group, key, None).await, None);
+        }
+        assert_eq!(cache_manager.stats().evictions, 3);
+
+        // Entries from before a restart are indexed too
+        let mut cache_manager = CacheManager::with_limits(2, u64::MAX);
+        cache_manager.set_local_cache_dir(cache_dir);
+        assert_eq!(
+            cache_manager.get(&group, "nested/big", None).await,
+            Some(big)
+        );
+        for key in ["x", "y"] {
+            cache_manager
+                .save(&group, key, Bytes::from(key))
+                .await
+                .expect("save failed");
+        }
+        assert_eq!(cache_manager.get(&group, "nested/big", None).await, None);
+        assert_eq!(cache_manager.stats().evictions, 1);
+        cache_manager.clean().await.expect("clean failed");
+    }
 }
@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use crate::error::{GetterError, Result};
-use crate::utils::json::{json_to_string, string_to_json};
+use crate::utils::json::{json_to_string, lenient_string_to_json};
 use crate::websdk::cloud_rules::cloud_rules_wrapper::CloudRules;
 use crate::websdk::cloud_rules::data::app_item::AppItem;
 use crate::websdk::cloud_rules::data::hub_item::HubItem;
@@ -25,22 +25,26 @@ impl WorldConfigWrapper {
         })
     }
 
-    pub fn get_app_rule(&self, app_name: &str) -> Option<AppItem> {
-        if let Ok(content) = self.local_repo.load(app_name) {
-            if let Ok(app_item) = string_to_json(&content) {
-                return Some(app_item);
-            }
-        }
-        None
+    pub fn get_app_rule(&self, app_name: &str) -> Result<AppItem> {
+        let content = self.local_repo.load(app_name)?;
+        lenient_string_to_json(&content).map_err(|e| {
+            GetterError::new(
+                "WorldConfigWrapper",
+                &format!("get_app_rule {}: {}", app_name, e),
+                Box::new(e),
+            )
+        })
     }
 
-    pub fn get_hub_rule(&self, hub_name: &str) -> Option<HubItem> {
-        if let Ok(content) = self.local_repo.load(hub_name) {
-            if let Ok(hub_item) = string_to_json(&content) {
-                return Some(hub_item);
-            }
-        }
-        None
+    pub fn get_hub_rule(&self, hub_name: &str) -> Result<HubItem> {
+        let content = self.local_repo.load(hub_name)?;
+        lenient_string_to_json(&content).map_err(|e| {
+            GetterError::new(
+                "WorldConfigWrapper",
+                &format!("get_hub_rule {}: {}", hub_name, e),
+                Box::new(e),
+            )
+        })
     }
 
     pub fn download_app_rule(&self, app_name: &str, cloud_rules: &mut CloudRules) -> Result<()> {
@@ -69,3 +73,26 @@ impl WorldConfigWrapper {
         &self.world_list.rule_list.hub_list
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_rule_error_position() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let local_repo_path = temp_dir.path().to_str().unwrap();
+        let wrapper =
+            WorldConfigWrapper::new(&temp_dir.path().join("world.json"), local_repo_path).unwrap();
+        wrapper
+            .local_repo
+            .save("broken", "{\n  \"a\": 1\n  \"b\": 2\n}")
+            .unwrap();
+
+        let error = wrapper.get_app_rule("broken").unwrap_err();
+        assert!(error.message.contains("line 3 column 3"), "{}", error);
+        let error = wrapper.get_hub_rule("broken").unwrap_err();
+        assert!(error.message.contains("near `\"b\": 2`"), "{}", error);
+        assert!(wrapper.get_app_rule("missing").is_err());
+    }
+}
@@ -1,8 +1,9 @@
-use std::fs::{create_dir_all, File};
-use std::io::BufReader;
+use std::fs::{create_dir_all, read_to_string, File};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use crate::error::{GetterError, Result};
+use crate::utils::json::lenient_string_to_json;
 
 use super::super::data::rule_list::RuleList;
 
@@ -22,10 +23,9 @@ impl WorldList {
     }
 
     pub fn load(&mut self, config_path: &Path) -> Result<&mut Self> {
-        let rule_list = if let Ok(file) = File::open(config_path) {
-            let reader = BufReader::new(file);
-            serde_json::from_reader(reader)
-                .map_err(|e| GetterError::new("WorldList", "load", Box::new(e)))?
+        let rule_list = if let Ok(content) = read_to_string(config_path) {
+            lenient_string_to_json(&content)
+                .map_err(|e| GetterError::new("WorldList", &format!("load: {}", e), Box::new(e)))?
         } else {
             RuleList::new()
         };
@@ -34,6 +34,9 @@ impl WorldList {
         Ok(self)
     }
 
+    /// Writes the list as indented JSON with a trailing newline. Entries keep
+    /// their order, so saving an unchanged list rewrites the same bytes and a
+    /// change shows up as a diff of the touched lines only.
     pub fn save(&self) -> Result<()> {
         let path = self
             .config_path
@@ -43,9 +46,11 @@ impl WorldList {
             .parent()
             .ok_or_else(|| GetterError::new_nobase("WorldList", "save: get parent dir failed"))?;
         let _ = create_dir_all(parent);
-        let file =
-            File::create(path).map_err(|e| GetterError::new("WorldList", "save", Box::new(e)))?;
-        serde_json::to_writer(file, &self.rule_list)
+        let mut content = serde_json::to_string_pretty(&self.rule_list)
+            .map_err(|e| GetterError::new("WorldList", "save", Box::new(e)))?;
+        content.push('\n');
+        File::create(path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
             .map_err(|e| GetterError::new("WorldList", "save", Box::new(e)))?;
         Ok(())
     }
@@ -97,4 +102,101 @@ mod tests {
         let _ = WorldList::new().load(&path);
         assert!(path.try_exists().is_ok_and(|x| !x));
     }
+
+    #[test]
+    fn test_world_list_hand_edited() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join(WORLD_CONFIG_LIST_NAME);
+        fs::write(
+            &config_path,
+            "\u{feff}{\n  // tracked apps\n  \"app_list\": [\"UpgradeAll\",],\n  \"hub_list\": [\"GitHub\"],\n}\n",
+        )
+        .unwrap();
+        let mut world_list = WorldList::new();
+        world_list.load(&config_path).unwrap();
+        assert_eq!(world_list.rule_list.app_list, vec!["UpgradeAll"]);
+        assert_eq!(world_list.rule_list.hub_list, vec!["GitHub"]);
+
+        // Saved output is strict JSON
+        world_list.save().unwrap();
+        let content = fs::read_to_string(&config_path).unwrap();
+        serde_json::from_str::<RuleList>(&content).unwrap();
+        assert!(!content.contains("//"));
+    }
+
+    #[test]
+    fn test_world_list_broken() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join(WORLD_CONFIG_LIST_NAME);
+        fs::write(
+            &config_path,
+            "{\n  \"app_list\": [],\n  \"hub_list\": [\"GitHub\" \"GitLab\"]\n}\n",
+        )
+        .unwrap();
+        let err = WorldList::new().load(&config_path).err().unwrap();
+        assert!(err.message.contains("line 3"));
+        assert!(err.message.contains("\"hub_list\""));
+    }
+
+    #[test]
+    fn test_world_list_stable_save() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join(WORLD_CONFIG_LIST_NAME);
+        fs::write(
+            &config_path,
+            r#"{"app_list": ["UpgradeAll", "F-Droid", "Termux"], "hub_list": ["GitHub"]}"#,
+        )
+        .unwrap();
+        let mut world_list = WorldList::new();
+        world_list.load(&config_path).unwrap();
+        world_list.save().unwrap();
+        let first = fs::read_to_string(&config_path).unwrap();
+        assert!(first.ends_with("]\n}\n"));
+
+        // Reloading and saving again is byte-identical
+        let mut world_list = WorldList::new();
+        world_list.load(&config_path).unwrap();
+        world_list.save().unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), first);
+
+        // A mutation only touches its own line
+        world_list.rule_list.hub_list.push("GitLab".to_string());
+        world_list.save().unwrap();
+        let second = fs::read_to_string(&config_path).unwrap();
+        let first_lines = first.lines().collect::<Vec<_>>();
+        let second_lines = second.lines().collect::<Vec<_>>();
+        let changed = second_lines
+            .iter()
+            .filter(|line| !first_lines.contains(line))
+            .collect::<Vec<_>>();
+        assert_eq!(changed, vec![&"    \"GitHub\",", &"    \"GitLab\""]);
+        assert_eq!(second_lines.len(), first_lines.len() + 1);
+    }
+
+    #[test]
+    fn test_world_list_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join(WORLD_CONFIG_LIST_NAME);
+        let names = [
+            "UpgradeAll",
+            "",
+            "with \"quote\"",
+            "日本語",
+            "a\\b",
+            "GitHub",
+        ];
+        for n in 0..names.len() {
+            let mut world_list = WorldList::new();
+            world_list.load(&config_path).unwrap();
+            world_list.rule_list.app_list = names[..n].iter().map(|s| s.to_string()).collect();
+            world_list.rule_list.hub_list =
+                names[n..].iter().rev().map(|s| s.to_string()).collect();
+            world_list.save().unwrap();
+
+            let mut loaded = WorldList::new();
+            loaded.load(&config_path).unwrap();
+            assert_eq!(loaded.rule_list.app_list, world_list.rule_list.app_list);
+            assert_eq!(loaded.rule_list.hub_list, world_list.rule_list.hub_list);
+        }
+    }
 }
@@ -1,20 +1,119 @@
-use crate::websdk::repo::data::release::ReleaseData;
+use crate::api::BuildInfo;
+use crate::websdk::repo::api::{AppCheckResult, AppQuery, CheckOptions};
+use crate::websdk::repo::data::release::{AssetData, ReleaseData};
+use crate::websdk::repo::data::search::SearchResultData;
 
 use super::data::*;
 use jsonrpsee::core::client::ClientT;
 use jsonrpsee::core::client::Error;
+use jsonrpsee::core::traits::ToRpcParams;
 use jsonrpsee::http_client::HttpClient;
 use jsonrpsee::http_client::HttpClientBuilder;
+use jsonrpsee::rpc_params;
+use serde::de::DeserializeOwned;
+use serde_json::value::RawValue;
 use std::collections::BTreeMap;
+use std::time::Duration;
+
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Configures a `Client`. Without a timeout, requests give up after the
+/// HTTP client's default of 60 seconds.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    timeout: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            retries: 0,
+            backoff: DEFAULT_BACKOFF,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// Requests taking longer fail with `Error::RequestTimeout`, which is
+    /// never retried.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// How many more times a call that only reads is sent after a
+    /// transport error, such as a dropped connection.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// The wait before the first retry, doubled before each one after it.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn build(self, url: impl AsRef<str>) -> Result<Client, Error> {
+        let mut builder = HttpClientBuilder::default();
+        if let Some(timeout) = self.timeout {
+            builder = builder.request_timeout(timeout);
+        }
+        Ok(Client {
+            client: builder.build(url)?,
+            retries: self.retries,
+            backoff: self.backoff,
+        })
+    }
+}
+
+// Params serialized once, to be sent again on a retry
+struct RawParams(Option<Box<RawValue>>);
+
+impl ToRpcParams for RawParams {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+        Ok(self.0)
+    }
+}
 
 pub struct Client {
     client: HttpClient,
+    retries: u32,
+    backoff: Duration,
 }
 
 impl Client {
     pub fn new(url: impl AsRef<str>) -> Result<Self, Error> {
-        let client = HttpClientBuilder::default().build(url)?;
-        Ok(Self { client })
+        Self::builder().build(url)
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// `request` for calls that are safe to repeat, retried on transport
+    /// errors as configured.
+    async fn request_idempotent<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl ToRpcParams,
+    ) -> Result<R, Error> {
+        let params = params.to_rpc_params()?;
+        let mut backoff = self.backoff;
+        let mut retries = self.retries;
+        loop {
+            match self.client.request(method, RawParams(params.clone())).await {
+                Err(Error::Transport(_)) if retries > 0 => {
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    retries -= 1;
+                }
+                result => return result,
+            }
+        }
     }
 
     pub async fn check_app_available(
@@ -22,13 +121,25 @@ impl Client {
         hub_uuid: &str,
         app_data: BTreeMap<&str, &str>,
         hub_data: BTreeMap<&str, &str>,
+    ) -> Result<bool, Error> {
+        self.check_app_available_with_options(hub_uuid, app_data, hub_data, CheckOptions::default())
+            .await
+    }
+
+    pub async fn check_app_available_with_options(
+        &self,
+        hub_uuid: &str,
+        app_data: BTreeMap<&str, &str>,
+        hub_data: BTreeMap<&str, &str>,
+        options: CheckOptions,
     ) -> Result<bool, Error> {
         let data = RpcAppRequest {
             hub_uuid,
             app_data,
             hub_data,
+            options,
         };
-        self.client.request("check_app_available", data).await
+        self.request_idempotent("check_app_available", data).await
     }
 
     pub async fn get_latest_release(
@@ -36,13 +147,25 @@ impl Client {
         hub_uuid: &str,
         app_data: BTreeMap<&str, &str>,
         hub_data: BTreeMap<&str, &str>,
+    ) -> Result<ReleaseData, Error> {
+        self.get_latest_release_with_options(hub_uuid, app_data, hub_data, CheckOptions::default())
+            .await
+    }
+
+    pub async fn get_latest_release_with_options(
+        &self,
+        hub_uuid: &str,
+        app_data: BTreeMap<&str, &str>,
+        hub_data: BTreeMap<&str, &str>,
+        options: CheckOptions,
     ) -> Result<ReleaseData, Error> {
         let data = RpcAppRequest {
             hub_uuid,
             app_data,
             hub_data,
+            options,
         };
-        self.client.request("get_latest_release", data).await
+        self.request_idempotent("get_latest_release", data).await
     }
 
     pub async fn get_releases(
@@ -51,11 +174,223 @@ impl Client {
         app_data: BTreeMap<&str, &str>,
         hub_data: BTreeMap<&str, &str>,
     ) -> Result<Vec<ReleaseData>, Error> {
+        self.get_releases_with_options(hub_uuid, app_data, hub_data, CheckOptions::default())
+            .await
+    }
+
+    pub async fn get_releases_with_options(
+        &self,
+        hub_uuid: &str,
+        app_data: BTreeMap<&str, &str>,
+        hub_data: BTreeMap<&str, &str>,
+        options: CheckOptions,
+    ) -> Result<Vec<ReleaseData>, Error> {
+        let data = RpcAppRequest {
+            hub_uuid,
+            app_data,
+            hub_data,
+            options,
+        };
+        self.request_idempotent("get_releases", data).await
+    }
+
+    /// Apps on the hub matching `query`, each with the app_data to track it.
+    pub async fn search_apps(
+        &self,
+        hub_uuid: &str,
+        hub_data: BTreeMap<&str, &str>,
+        query: &str,
+    ) -> Result<Vec<SearchResultData>, Error> {
+        let data = RpcSearchRequest {
+            hub_uuid,
+            hub_data,
+            query,
+        };
+        self.request_idempotent("search_apps", data).await
+    }
+
+    pub async fn clear_cache_for_hub(&self, hub_uuid: &str) -> Result<bool, Error> {
+        self.client
+            .request("clear_cache_for_hub", RpcHubRequest { hub_uuid })
+            .await
+    }
+
+    pub async fn clear_cache_for_app(
+        &self,
+        hub_uuid: &str,
+        app_data: BTreeMap<&str, &str>,
+        hub_data: BTreeMap<&str, &str>,
+    ) -> Result<bool, Error> {
         let data = RpcAppRequest {
             hub_uuid,
             app_data,
             hub_data,
+            options: CheckOptions::default(),
         };
-        self.client.request("get_releases", data).await
+        self.client.request("clear_cache_for_app", data).await
+    }
+
+    pub async fn build_info(&self) -> Result<BuildInfo, Error> {
+        self.request_idempotent("build_info", rpc_params![]).await
+    }
+
+    pub async fn clean_cache(&self) -> Result<usize, Error> {
+        self.client.request("clean_cache", rpc_params![]).await
+    }
+
+    pub async fn get_missing_app_keys(
+        &self,
+        hub_uuid: &str,
+        app_data: BTreeMap<&str, &str>,
+        hub_data: BTreeMap<&str, &str>,
+    ) -> Result<Vec<String>, Error> {
+        let data = RpcAppRequest {
+            hub_uuid,
+            app_data,
+            hub_data,
+            options: CheckOptions::default(),
+        };
+        self.request_idempotent("get_missing_app_keys", data).await
+    }
+
+    pub async fn get_unresolved_refs(
+        &self,
+        hub_uuid: &str,
+        app_data: BTreeMap<&str, &str>,
+        hub_data: BTreeMap<&str, &str>,
+    ) -> Result<Vec<String>, Error> {
+        let data = RpcAppRequest {
+            hub_uuid,
+            app_data,
+            hub_data,
+            options: CheckOptions::default(),
+        };
+        self.request_idempotent("get_unresolved_refs", data).await
+    }
+
+    pub async fn normalize_version(
+        &self,
+        app_data: BTreeMap<&str, &str>,
+        hub_data: BTreeMap<&str, &str>,
+        version: &str,
+    ) -> Result<String, Error> {
+        let data = RpcNormalizeVersionRequest {
+            app_data,
+            hub_data,
+            version,
+        };
+        self.request_idempotent("normalize_version", data).await
+    }
+
+    /// Latest releases of `apps`, in order, checked concurrently on the
+    /// server.
+    pub async fn get_latest_releases(
+        &self,
+        apps: Vec<AppQuery>,
+        max_concurrency: Option<usize>,
+    ) -> Result<Vec<AppCheckResult>, Error> {
+        let data = RpcBatchRequest {
+            apps,
+            max_concurrency,
+            options: CheckOptions::default(),
+        };
+        self.request_idempotent("get_latest_releases", data).await
+    }
+
+    pub async fn select_asset(
+        &self,
+        app_data: BTreeMap<&str, &str>,
+        hub_data: BTreeMap<&str, &str>,
+        release: ReleaseData,
+    ) -> Result<AssetData, Error> {
+        let data = RpcSelectAssetRequest {
+            app_data,
+            hub_data,
+            release,
+        };
+        self.request_idempotent("select_asset", data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    // The id of the JSON-RPC request read off `socket`
+    async fn read_request_id(socket: &mut TcpStream) -> serde_json::Value {
+        let mut data = vec![];
+        let mut buf = [0; 1024];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            assert!(n > 0, "connection closed mid-request");
+            data.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&data);
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or_default();
+            if body.len() >= length {
+                let request: serde_json::Value = serde_json::from_str(&body[..length]).unwrap();
+                return request["id"].clone();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_dropped_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            // The first attempt's connection is closed unanswered
+            let (socket, _) = listener.accept().await.unwrap();
+            drop(socket);
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let id = read_request_id(&mut socket).await;
+            let body = serde_json::json!({"jsonrpc": "2.0", "result": true, "id": id}).to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let app_data = BTreeMap::from([("owner", "DUpdateSystem"), ("repo", "UpgradeAll")]);
+        let client = Client::builder()
+            .retries(2)
+            .backoff(Duration::from_millis(10))
+            .build(url)
+            .unwrap();
+        assert!(client
+            .check_app_available("hub", app_data, BTreeMap::new())
+            .await
+            .unwrap());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        // Connections queue up in the backlog, never answered
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .retries(3)
+            .build(url)
+            .unwrap();
+        let start = Instant::now();
+        let result = client.build_info().await;
+        assert!(matches!(result, Err(Error::RequestTimeout)));
+        assert!(start.elapsed() < Duration::from_secs(2));
     }
 }
@@ -3,11 +3,18 @@ use serde::{Deserialize, Serialize};
 use serde_json::value::to_raw_value;
 use std::collections::BTreeMap;
 
+use crate::websdk::repo::api::{AppQuery, CheckOptions};
+use crate::websdk::repo::data::release::ReleaseData;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RpcInitRequest<'a> {
     pub data_path: &'a str,
     pub cache_path: &'a str,
     pub global_expire_time: u64,
+    #[serde(default)]
+    pub min_expire_time: Option<u64>,
+    #[serde(default)]
+    pub max_expire_time: Option<u64>,
 }
 
 impl ToRpcParams for RpcInitRequest<'_> {
@@ -21,6 +28,8 @@ pub struct RpcAppRequest<'a> {
     pub hub_uuid: &'a str,
     pub app_data: BTreeMap<&'a str, &'a str>,
     pub hub_data: BTreeMap<&'a str, &'a str>,
+    #[serde(default)]
+    pub options: CheckOptions,
 }
 
 impl ToRpcParams for RpcAppRequest<'_> {
@@ -29,6 +38,75 @@ impl ToRpcParams for RpcAppRequest<'_> {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcNormalizeVersionRequest<'a> {
+    pub app_data: BTreeMap<&'a str, &'a str>,
+    pub hub_data: BTreeMap<&'a str, &'a str>,
+    pub version: &'a str,
+}
+
+impl ToRpcParams for RpcNormalizeVersionRequest<'_> {
+    fn to_rpc_params(self) -> Result<Option<Box<serde_json::value::RawValue>>, serde_json::Error> {
+        to_raw_value(&self).map(Some)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcSelectAssetRequest<'a> {
+    #[serde(borrow)]
+    pub app_data: BTreeMap<&'a str, &'a str>,
+    #[serde(borrow)]
+    pub hub_data: BTreeMap<&'a str, &'a str>,
+    pub release: ReleaseData,
+}
+
+impl ToRpcParams for RpcSelectAssetRequest<'_> {
+    fn to_rpc_params(self) -> Result<Option<Box<serde_json::value::RawValue>>, serde_json::Error> {
+        to_raw_value(&self).map(Some)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcBatchRequest {
+    pub apps: Vec<AppQuery>,
+    /// Checks in flight at once; `DEFAULT_BATCH_CONCURRENCY` if unset
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    #[serde(default)]
+    pub options: CheckOptions,
+}
+
+impl ToRpcParams for RpcBatchRequest {
+    fn to_rpc_params(self) -> Result<Option<Box<serde_json::value::RawValue>>, serde_json::Error> {
+        to_raw_value(&self).map(Some)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcSearchRequest<'a> {
+    pub hub_uuid: &'a str,
+    #[serde(borrow, default)]
+    pub hub_data: BTreeMap<&'a str, &'a str>,
+    pub query: &'a str,
+}
+
+impl ToRpcParams for RpcSearchRequest<'_> {
+    fn to_rpc_params(self) -> Result<Option<Box<serde_json::value::RawValue>>, serde_json::Error> {
+        to_raw_value(&self).map(Some)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcHubRequest<'a> {
+    pub hub_uuid: &'a str,
+}
+
+impl ToRpcParams for RpcHubRequest<'_> {
+    fn to_rpc_params(self) -> Result<Option<Box<serde_json::value::RawValue>>, serde_json::Error> {
+        to_raw_value(&self).map(Some)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RpcCloudConfigRequest<'a> {
     pub api_url: &'a str,
@@ -2,20 +2,81 @@ use super::data::*;
 use crate::api as api_root;
 use crate::websdk::cloud_rules::cloud_rules_manager::CloudRules;
 use crate::websdk::repo::api;
-use jsonrpsee::server::{RpcModule, Server, ServerHandle};
+use jsonrpsee::server::{
+    RegisterMethodError, RpcModule, Server, ServerBuilder, ServerHandle, TowerServiceBuilder,
+};
 use jsonrpsee::types::{ErrorCode, ErrorObjectOwned};
 use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tower::layer::util::Identity;
+
+/// Transport limits of the JSON-RPC server.
+///
+/// All of them are enforced by the HTTP transport before the request body is
+/// parsed; requests without `Content-Type: application/json` are rejected
+/// with 415.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Larger request bodies are rejected with 413.
+    pub max_request_body_size: u32,
+    /// Requests beyond this many in flight are rejected with 429.
+    pub max_concurrent_requests: u32,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            max_request_body_size: 2 * 1024 * 1024,
+            max_concurrent_requests: 256,
+        }
+    }
+}
 
 pub async fn run_server(
     addr: &str,
     is_running: Arc<AtomicBool>,
+) -> Result<(String, ServerHandle), Box<dyn std::error::Error>> {
+    run_server_with_config(addr, is_running, &ServerConfig::default()).await
+}
+
+pub async fn run_server_with_config(
+    addr: &str,
+    is_running: Arc<AtomicBool>,
+    config: &ServerConfig,
 ) -> Result<(String, ServerHandle), Box<dyn std::error::Error>> {
     let addr = if addr.is_empty() { "127.0.0.1:0" } else { addr };
-    let server = Server::builder().build(addr.parse::<SocketAddr>()?).await?;
+    let server = server_builder(config)
+        .build(addr.parse::<SocketAddr>()?)
+        .await?;
+    let module = build_rpc_module(is_running)?;
+    let addr = server.local_addr()?;
+    let handle = server.start(module);
+    tokio::spawn(handle.clone().stopped());
+    Ok((format!("http://{}", addr), handle))
+}
+
+fn server_builder(config: &ServerConfig) -> ServerBuilder<Identity, Identity> {
+    Server::builder()
+        .max_request_body_size(config.max_request_body_size)
+        .max_connections(config.max_concurrent_requests)
+}
+
+/// HTTP transport for `build_rpc_module` with the limits of `config`, for
+/// hosts serving the methods from their own server. Build the tower service
+/// per connection from clones of it; they share the concurrency limit.
+pub fn build_service_builder(config: &ServerConfig) -> TowerServiceBuilder<Identity, Identity> {
+    server_builder(config).to_service_builder()
+}
+
+/// Builds the getter JSON-RPC methods without a transport, so a host can
+/// serve them from its own HTTP server, e.g. under a path prefix, through
+/// `build_service_builder`.
+///
+/// `shutdown` clears `is_running`; stopping the transport is up to the host.
+pub fn build_rpc_module(is_running: Arc<AtomicBool>) -> Result<RpcModule<()>, RegisterMethodError> {
     let mut module = RpcModule::new(());
     // Register the shutdown method
     let run_flag = is_running.clone();
@@ -26,28 +87,41 @@ pub async fn run_server(
         }
     })?;
     module.register_method("ping", |_, _, _| "pong")?;
+    module.register_method("build_info", |_, _, _| {
+        Ok::<api_root::BuildInfo, ErrorObjectOwned>(api_root::build_info())
+    })?;
     module.register_async_method("init", |params, _, _| async move {
         let request = params.parse::<RpcInitRequest>()?;
         let data_dir = Path::new(request.data_path);
         let cache_dir = Path::new(request.cache_path);
-        api_root::init(data_dir, cache_dir, request.global_expire_time)
-            .await
-            .map(|_| true)
-            .map_err(|e| {
-                ErrorObjectOwned::owned(
-                    ErrorCode::InternalError.code(),
-                    "Internal error",
-                    Some(e.to_string()),
-                )
-            })
+        api_root::init_with_expire_bounds(
+            data_dir,
+            cache_dir,
+            request.global_expire_time,
+            request.min_expire_time,
+            request.max_expire_time,
+        )
+        .await
+        .map(|_| true)
+        .map_err(|e| {
+            ErrorObjectOwned::owned(
+                ErrorCode::InternalError.code(),
+                "Internal error",
+                Some(e.to_string()),
+            )
+        })
     })?;
     module.register_async_method(
         "check_app_available",
         |params, _context, _extensions| async move {
             let request = params.parse::<RpcAppRequest>()?;
-            if let Some(result) =
-                api::check_app_available(request.hub_uuid, &request.app_data, &request.hub_data)
-                    .await
+            if let Some(result) = api::check_app_available_with_options(
+                request.hub_uuid,
+                &request.app_data,
+                &request.hub_data,
+                &request.options,
+            )
+            .await
             {
                 Ok(result)
             } else {
@@ -63,9 +137,13 @@ pub async fn run_server(
         "get_latest_release",
         |params, _context, _extensions| async move {
             if let Ok(request) = params.parse::<RpcAppRequest>() {
-                if let Some(result) =
-                    api::get_latest_release(request.hub_uuid, &request.app_data, &request.hub_data)
-                        .await
+                if let Some(result) = api::get_latest_release_with_options(
+                    request.hub_uuid,
+                    &request.app_data,
+                    &request.hub_data,
+                    &request.options,
+                )
+                .await
                 {
                     Ok(result)
                 } else {
@@ -86,8 +164,13 @@ pub async fn run_server(
     )?;
     module.register_async_method("get_releases", |params, _context, _extensions| async move {
         if let Ok(request) = params.parse::<RpcAppRequest>() {
-            if let Some(result) =
-                api::get_releases(request.hub_uuid, &request.app_data, &request.hub_data).await
+            if let Some(result) = api::get_releases_with_options(
+                request.hub_uuid,
+                &request.app_data,
+                &request.hub_data,
+                &request.options,
+            )
+            .await
             {
                 Ok(result)
             } else {
@@ -105,6 +188,90 @@ pub async fn run_server(
             ))
         }
     })?;
+    module.register_async_method(
+        "get_latest_releases",
+        |params, _context, _extensions| async move {
+            let request = params.parse::<RpcBatchRequest>()?;
+            let concurrency = request
+                .max_concurrency
+                .unwrap_or(api::DEFAULT_BATCH_CONCURRENCY);
+            Ok::<_, ErrorObjectOwned>(
+                api::get_latest_releases(request.apps, concurrency, request.options).await,
+            )
+        },
+    )?;
+    module.register_method("normalize_version", |params, _context, _extensions| {
+        let request = params.parse::<RpcNormalizeVersionRequest>()?;
+        api::normalize_version(&request.app_data, &request.hub_data, request.version).map_err(|e| {
+            ErrorObjectOwned::owned(
+                ErrorCode::InvalidParams.code(),
+                "Invalid version rules",
+                Some(e.to_string()),
+            )
+        })
+    })?;
+    module.register_method("select_asset", |params, _context, _extensions| {
+        let request = params.parse::<RpcSelectAssetRequest>()?;
+        api::select_asset(&request.app_data, &request.hub_data, &request.release).map_err(|e| {
+            ErrorObjectOwned::owned(
+                ErrorCode::InvalidParams.code(),
+                "No asset selected",
+                Some(e.to_string()),
+            )
+        })
+    })?;
+    module.register_async_method("search_apps", |params, _context, _extensions| async move {
+        let request = params.parse::<RpcSearchRequest>()?;
+        api::search_apps(request.hub_uuid, &request.hub_data, request.query)
+            .await
+            .map_err(|e| {
+                ErrorObjectOwned::owned(
+                    ErrorCode::InternalError.code(),
+                    "Search failed",
+                    Some(e.to_string()),
+                )
+            })
+    })?;
+    module.register_async_method(
+        "clear_cache_for_hub",
+        |params, _context, _extensions| async move {
+            let request = params.parse::<RpcHubRequest>()?;
+            api::clear_cache_for_hub(request.hub_uuid)
+                .await
+                .map(|_| true)
+                .map_err(|e| {
+                    ErrorObjectOwned::owned(
+                        ErrorCode::InternalError.code(),
+                        "Clear cache failed",
+                        Some(e.to_string()),
+                    )
+                })
+        },
+    )?;
+    module.register_async_method(
+        "clear_cache_for_app",
+        |params, _context, _extensions| async move {
+            let request = params.parse::<RpcAppRequest>()?;
+            api::clear_cache_for_app(request.hub_uuid, &request.app_data, &request.hub_data).await;
+            Ok::<bool, ErrorObjectOwned>(true)
+        },
+    )?;
+
+    module.register_method("get_missing_app_keys", |params, _context, _extensions| {
+        let request = params.parse::<RpcAppRequest>()?;
+        api::get_missing_app_keys(request.hub_uuid, &request.app_data, &request.hub_data)
+            .ok_or_else(|| {
+                ErrorObjectOwned::borrowed(ErrorCode::InvalidParams.code(), "Hub not found", None)
+            })
+    })?;
+
+    module.register_method("get_unresolved_refs", |params, _context, _extensions| {
+        let request = params.parse::<RpcAppRequest>()?;
+        Ok::<Vec<String>, ErrorObjectOwned>(api::get_unresolved_refs(
+            &request.app_data,
+            &request.hub_data,
+        ))
+    })?;
 
     module.register_async_method(
         "get_cloud_config",
@@ -128,10 +295,7 @@ pub async fn run_server(
             }
         },
     )?;
-    let addr = server.local_addr()?;
-    let handle = server.start(module);
-    tokio::spawn(handle.clone().stopped());
-    Ok((format!("http://{}", addr), handle))
+    Ok(module)
 }
 
 #[allow(dead_code)]
@@ -160,15 +324,24 @@ mod tests {
     use crate::rpc::client::Client;
     use crate::websdk::repo::provider::github;
     use crate::websdk::{
-        cloud_rules::data::config_list::ConfigList, repo::data::release::ReleaseData,
+        cloud_rules::data::config_list::ConfigList,
+        repo::data::release::{AssetData, ReleaseData},
     };
 
     use super::*;
+    use bytes::Bytes;
+    use http_body_util::Full;
+    use hyper_util::{
+        client::legacy::Client as HyperClient,
+        rt::{TokioExecutor, TokioIo},
+    };
+    use jsonrpsee::server::{stop_channel, HttpBody, HttpResponse};
     use jsonrpsee::{core::client::ClientT, http_client::HttpClientBuilder, rpc_params};
     use mockito::Server;
     use std::collections::BTreeMap;
     use std::fs;
     use tokio::time::timeout;
+    use tower::Service;
 
     #[tokio::test]
     async fn test_server_start() {
@@ -202,6 +375,193 @@ mod tests {
         handle.stop().unwrap();
     }
 
+    #[tokio::test]
+    async fn test_build_info() {
+        let (url, handle) = run_server("", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        let client = Client::new(url).unwrap();
+        let build_info = client.build_info().await.unwrap();
+        assert_eq!(build_info, api_root::build_info());
+        assert_eq!(build_info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!build_info.git_hash.is_empty());
+        assert!(
+            build_info.git_hash == "unknown"
+                || build_info.git_hash.chars().all(|c| c.is_ascii_hexdigit())
+        );
+        assert!(build_info.build_time > 0);
+        assert!(!build_info.target.is_empty());
+        assert!(build_info
+            .providers
+            .iter()
+            .any(|provider| provider.hub_uuid == "fd9b2602-62c5-4d55-bd1e-0d6537714ca0"));
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_normalize_version() {
+        let (url, handle) = run_server("", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        let client = Client::new(url).unwrap();
+        let app_data = BTreeMap::from([("version_strip_prefixes", "release-")]);
+        let version = client
+            .normalize_version(app_data, BTreeMap::new(), "release-1.2.0")
+            .await
+            .unwrap();
+        assert_eq!(version, "1.2.0");
+
+        let app_data = BTreeMap::from([("version_extract", "(")]);
+        assert!(client
+            .normalize_version(app_data, BTreeMap::new(), "1.2.0")
+            .await
+            .is_err());
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_releases() {
+        let (url, handle) = run_server("", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        let client = Client::new(url).unwrap();
+        let apps = vec![
+            api::AppQuery {
+                hub_uuid: "unknown-hub".to_string(),
+                ..Default::default()
+            },
+            api::AppQuery {
+                hub_uuid: "fd9b2602-62c5-4d55-bd1e-0d6537714ca0".to_string(),
+                app_data: BTreeMap::from([("owner".to_string(), "DUpdateSystem".to_string())]),
+                ..Default::default()
+            },
+        ];
+        let results = client.get_latest_releases(apps, None).await.unwrap();
+        let errors = results
+            .iter()
+            .map(|result| result.error.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                Some("hub unknown-hub not found"),
+                Some("app_data lacks repo")
+            ]
+        );
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_select_asset() {
+        let (url, handle) = run_server("", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        let client = Client::new(url).unwrap();
+        let asset = |file_name: &str| AssetData {
+            file_name: file_name.to_string(),
+            file_type: "apk".to_string(),
+            download_url: format!("https://example.com/{}", file_name),
+            size: None,
+            extra: None,
+        };
+        let release = ReleaseData {
+            version_number: "1.0.0".to_string(),
+            changelog: "".to_string(),
+            assets: vec![asset("app-armeabi-v7a.apk"), asset("app-arm64-v8a.apk")],
+            extra: None,
+        };
+        let app_data = BTreeMap::from([("preferred_abi", "arm64-v8a")]);
+        let selected = client
+            .select_asset(app_data, BTreeMap::new(), release.clone())
+            .await
+            .unwrap();
+        assert_eq!(selected, asset("app-arm64-v8a.apk"));
+
+        let app_data = BTreeMap::from([("asset_filter", r"\.aab$")]);
+        assert!(client
+            .select_asset(app_data, BTreeMap::new(), release)
+            .await
+            .is_err());
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_apps() {
+        let body =
+            fs::read_to_string("tests/files/web/github_api_search_repositories.json").unwrap();
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/search/repositories?q=syncthing")
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+        let (url, handle) = run_server("", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        let client = Client::new(url).unwrap();
+        let proxy_url = format!("{} -> {}", github::GITHUB_API_URL, server.url());
+        let hub_data = BTreeMap::from([("reverse_proxy", proxy_url.as_str())]);
+        let results = client
+            .search_apps(
+                "fd9b2602-62c5-4d55-bd1e-0d6537714ca0",
+                hub_data,
+                "syncthing",
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].name, "syncthing/syncthing-android");
+        assert_eq!(results[0].app_data["repo"], "syncthing-android");
+        assert!(client
+            .search_apps("unknown-hub", BTreeMap::new(), "syncthing")
+            .await
+            .is_err());
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_app_keys() {
+        let (url, handle) = run_server("", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        let client = Client::new(url).unwrap();
+        let app_data = BTreeMap::from([("owner", "DUpdateSystem")]);
+        let keys = client
+            .get_missing_app_keys(
+                "fd9b2602-62c5-4d55-bd1e-0d6537714ca0",
+                app_data.clone(),
+                BTreeMap::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(keys, vec!["repo"]);
+        assert!(client
+            .get_missing_app_keys("unknown-hub", app_data, BTreeMap::new())
+            .await
+            .is_err());
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_unresolved_refs() {
+        let (url, handle) = run_server("", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        let client = Client::new(url).unwrap();
+        let hub_data = BTreeMap::from([("token", "${env:GETTER_TEST_RPC_TOKEN_UNSET}")]);
+        let errors = client
+            .get_unresolved_refs(
+                "fd9b2602-62c5-4d55-bd1e-0d6537714ca0",
+                BTreeMap::new(),
+                hub_data,
+            )
+            .await
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("GETTER_TEST_RPC_TOKEN_UNSET"));
+        handle.stop().unwrap();
+    }
+
     #[tokio::test]
     async fn test_init() {
         let mut server = Server::new_async().await;
@@ -222,6 +582,8 @@ mod tests {
             data_path: &format!("{}/data", temp_dir_path),
             cache_path: &format!("{}/cache", temp_dir_path),
             global_expire_time: 3600,
+            min_expire_time: None,
+            max_expire_time: Some(86400),
         };
         println!("{:?}", params);
         let response: Result<bool, _> = client.request("init", params).await;
@@ -249,6 +611,7 @@ mod tests {
             hub_uuid: "fd9b2602-62c5-4d55-bd1e-0d6537714ca0",
             app_data: id_map,
             hub_data,
+            options: Default::default(),
         };
         println!("{:?}", params);
         let client = Client::new(url).unwrap();
@@ -264,7 +627,7 @@ mod tests {
         let body = fs::read_to_string("tests/files/web/github_api_release.json").unwrap();
         let mut server = Server::new_async().await;
         let _m = server
-            .mock("GET", "/repos/DUpdateSystem/UpgradeAll/releases")
+            .mock("GET", "/repos/dupdatesystem/upgradeall/releases")
             .with_status(200)
             .with_body(body)
             .create();
@@ -281,6 +644,7 @@ mod tests {
             hub_uuid: "fd9b2602-62c5-4d55-bd1e-0d6537714ca0",
             app_data: id_map,
             hub_data,
+            options: Default::default(),
         };
         println!("{:?}", params);
         let client = Client::new(url).unwrap();
@@ -297,7 +661,7 @@ mod tests {
         let body = fs::read_to_string("tests/files/web/github_api_release.json").unwrap();
         let mut server = Server::new_async().await;
         let _m = server
-            .mock("GET", "/repos/DUpdateSystem/UpgradeAll/releases")
+            .mock("GET", "/repos/dupdatesystem/upgradeall/releases")
             .with_status(200)
             .with_body(body)
             .create();
@@ -314,6 +678,7 @@ mod tests {
             hub_uuid: "fd9b2602-62c5-4d55-bd1e-0d6537714ca0",
             app_data: id_map,
             hub_data,
+            options: Default::default(),
         };
         println!("{:?}", params);
         let client = Client::new(url).unwrap();
@@ -387,4 +752,155 @@ mod tests {
         assert!(!config.hub_config_list.is_empty());
         handle.stop().unwrap();
     }
+
+    async fn post_raw(url: &str, content_type: &str, body: String) -> u16 {
+        let client = HyperClient::builder(TokioExecutor::new()).build_http();
+        let req = hyper::Request::builder()
+            .method("POST")
+            .uri(url)
+            .header("Content-Type", content_type)
+            .body(Full::new(Bytes::from(body)))
+            .unwrap();
+        client.request(req).await.unwrap().status().as_u16()
+    }
+
+    #[tokio::test]
+    async fn test_request_body_limit() {
+        let is_running = Arc::new(AtomicBool::new(true));
+        let config = ServerConfig {
+            max_request_body_size: 1024,
+            ..Default::default()
+        };
+        let (url, handle) = run_server_with_config("", is_running.clone(), &config)
+            .await
+            .unwrap();
+        let padding = "a".repeat(2048);
+        let body = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"shutdown","params":["{}"]}}"#,
+            padding
+        );
+        assert_eq!(post_raw(&url, "application/json", body).await, 413);
+        // The shutdown handler never ran
+        assert!(is_running.load(Ordering::SeqCst));
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_content_type() {
+        let (url, handle) = run_server("", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"ping","params":[]}"#;
+        assert_eq!(post_raw(&url, "text/plain", body.to_string()).await, 415);
+        assert_eq!(
+            post_raw(&url, "application/json", body.to_string()).await,
+            200
+        );
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_request_limit() {
+        let body = fs::read_to_string("tests/files/web/cloud_config.json").unwrap();
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/cloud_config.json")
+            .with_status(200)
+            .with_chunked_body(move |w| {
+                std::thread::sleep(Duration::from_millis(500));
+                w.write_all(body.as_bytes())
+            })
+            .create_async()
+            .await;
+
+        let config = ServerConfig {
+            max_concurrent_requests: 2,
+            ..Default::default()
+        };
+        let (url, handle) = run_server_with_config("", Arc::new(AtomicBool::new(true)), &config)
+            .await
+            .unwrap();
+        let body = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"get_cloud_config","params":{{"api_url":"{}/cloud_config.json"}}}}"#,
+            server.url()
+        );
+        let mut requests = tokio::task::JoinSet::new();
+        for _ in 0..6 {
+            let (url, body) = (url.clone(), body.clone());
+            requests.spawn(async move { post_raw(&url, "application/json", body).await });
+        }
+        let statuses = requests.join_all().await;
+        assert_eq!(statuses.iter().filter(|status| **status == 200).count(), 2);
+        assert_eq!(statuses.iter().filter(|status| **status == 429).count(), 4);
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_build_rpc_module_mounted() {
+        let is_running = Arc::new(AtomicBool::new(true));
+        let methods = build_rpc_module(is_running.clone()).unwrap();
+        let (stop_handle, server_handle) = stop_channel();
+        let config = ServerConfig {
+            max_request_body_size: 1024,
+            ..Default::default()
+        };
+        let service_builder = build_service_builder(&config);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((sock, _)) = listener.accept().await {
+                let rpc_service = service_builder
+                    .clone()
+                    .build(methods.clone(), stop_handle.clone());
+                let host_service = hyper::service::service_fn(move |req| {
+                    let mut rpc_service = rpc_service.clone();
+                    async move {
+                        if req.uri().path().starts_with("/api/getter") {
+                            rpc_service.call(req.map(HttpBody::new)).await
+                        } else {
+                            Ok(HttpResponse::builder()
+                                .status(404)
+                                .body(HttpBody::default())
+                                .unwrap())
+                        }
+                    }
+                });
+                tokio::spawn(
+                    hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(sock), host_service),
+                );
+            }
+        });
+
+        let client = HttpClientBuilder::default()
+            .build(format!("http://{}/api/getter", addr))
+            .unwrap();
+        let response: Result<String, _> = client.request("ping", rpc_params![]).await;
+        assert_eq!(response.unwrap(), "pong");
+        let response: Result<(), _> = client.request("shutdown", rpc_params![]).await;
+        assert!(response.is_ok());
+        assert!(!is_running.load(Ordering::SeqCst));
+
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"ping","params":[]}"#;
+        let status = post_raw(
+            &format!("http://{}/other", addr),
+            "application/json",
+            body.to_string(),
+        )
+        .await;
+        assert_eq!(status, 404);
+        // The transport limits hold for the mounted module too
+        let body = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"ping","params":["{}"]}}"#,
+            "a".repeat(2048)
+        );
+        let status = post_raw(
+            &format!("http://{}/api/getter", addr),
+            "application/json",
+            body,
+        )
+        .await;
+        assert_eq!(status, 413);
+        server_handle.stop().unwrap();
+    }
 }
@@ -1,6 +1,10 @@
 pub mod convert;
+#[cfg(feature = "shell")]
+pub mod exec;
 pub mod http;
 pub mod instance;
 pub mod json;
+pub mod netrc;
 pub mod time;
+pub mod value_ref;
 pub mod versioning;
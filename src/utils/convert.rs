@@ -19,3 +19,51 @@ pub fn convert_btreemap(original: &BTreeMap<String, String>) -> BTreeMap<&str, &
     }
     new_map
 }
+
+pub fn borrow_btreemap_values<'a>(
+    original: &'a BTreeMap<&str, String>,
+) -> BTreeMap<&'a str, &'a str> {
+    original
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect()
+}
+
+/// Decodes `%XX` escapes. None if the result isn't UTF-8; malformed escapes
+/// are kept as they are.
+pub fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Encodes every byte but the unreserved characters of RFC 3986 as `%XX`,
+/// for a value put in a URL query.
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
@@ -0,0 +1,307 @@
+use once_cell::sync::Lazy;
+use std::env;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+
+/// What spawned commands may do. Every command getter runs goes through
+/// [`run`] with the policy in force.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecPolicy {
+    /// Kill-switch: when false, every command fails with `ExecutionDisabled`
+    pub enabled: bool,
+    /// Program names or absolute paths that may be spawned; None allows any
+    pub allowed_executables: Option<Vec<String>>,
+    /// Environment variables passed through; None passes the whole environment
+    pub env_allowlist: Option<Vec<String>>,
+    /// Working directory of the commands; None keeps the current one
+    pub working_dir: Option<PathBuf>,
+    /// Bytes kept of stdout and of stderr each; the rest is discarded
+    pub max_output_bytes: Option<usize>,
+}
+
+impl Default for ExecPolicy {
+    fn default() -> Self {
+        ExecPolicy {
+            enabled: true,
+            allowed_executables: None,
+            env_allowlist: None,
+            working_dir: None,
+            max_output_bytes: None,
+        }
+    }
+}
+
+impl ExecPolicy {
+    fn check(&self, program: &str) -> Result<(), ExecPolicyError> {
+        if !self.enabled {
+            return Err(ExecPolicyError::ExecutionDisabled);
+        }
+        if let Some(allowed) = &self.allowed_executables {
+            let resolved = find_executable(program);
+            if !allowed
+                .iter()
+                .any(|entry| entry == program || resolved.as_deref() == Some(Path::new(entry)))
+            {
+                return Err(ExecPolicyError::ExecutableNotAllowed {
+                    program: program.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+static EXEC_POLICY: Lazy<RwLock<Arc<ExecPolicy>>> =
+    Lazy::new(|| RwLock::new(Arc::new(ExecPolicy::default())));
+
+pub fn set_exec_policy(policy: ExecPolicy) {
+    *EXEC_POLICY.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(policy);
+}
+
+pub fn get_exec_policy() -> Arc<ExecPolicy> {
+    EXEC_POLICY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// A command refused by the [`ExecPolicy`], naming the rule that refused it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecPolicyError {
+    ExecutionDisabled,
+    ExecutableNotAllowed { program: String },
+}
+
+impl fmt::Display for ExecPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecPolicyError::ExecutionDisabled => {
+                write!(f, "ExecPolicyError: command execution is disabled")
+            }
+            ExecPolicyError::ExecutableNotAllowed { program } => write!(
+                f,
+                "ExecPolicyError: {} is not in allowed_executables",
+                program
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExecPolicyError {}
+
+#[derive(Debug)]
+pub enum ExecError {
+    Policy(ExecPolicyError),
+    Spawn(io::Error),
+    Timeout(Duration),
+    Wait(io::Error),
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Policy(e) => e.fmt(f),
+            ExecError::Spawn(e) => write!(f, "spawn failed: {}", e),
+            ExecError::Timeout(timeout) => write!(f, "timeout after {}s", timeout.as_secs()),
+            ExecError::Wait(e) => write!(f, "wait failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+#[derive(Debug)]
+pub struct ExecOutput {
+    pub success: bool,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Whether stdout or stderr was cut at `max_output_bytes`
+    pub truncated: bool,
+}
+
+/// `program` as found in `PATH`, if it isn't a path already.
+fn find_executable(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Some(PathBuf::from(program));
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+async fn read_capped(
+    mut reader: impl AsyncRead + Unpin,
+    cap: Option<usize>,
+) -> io::Result<(Vec<u8>, bool)> {
+    let mut buf = Vec::new();
+    let Some(cap) = cap else {
+        reader.read_to_end(&mut buf).await?;
+        return Ok((buf, false));
+    };
+    (&mut reader).take(cap as u64).read_to_end(&mut buf).await?;
+    // Keep draining, or a chatty child blocks on the full pipe
+    let rest = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok((buf, rest > 0))
+}
+
+/// Runs `program` with `args` under `policy`, killing it after `timeout`.
+pub async fn run(
+    policy: &ExecPolicy,
+    program: &str,
+    args: &[&str],
+    timeout: Duration,
+) -> Result<ExecOutput, ExecError> {
+    policy.check(program).map_err(ExecError::Policy)?;
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    if let Some(allowlist) = &policy.env_allowlist {
+        cmd.env_clear();
+        for name in allowlist {
+            if let Some(value) = env::var_os(name) {
+                cmd.env(name, value);
+            }
+        }
+    }
+    if let Some(working_dir) = &policy.working_dir {
+        cmd.current_dir(working_dir);
+    }
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(ExecError::Spawn)?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let cap = policy.max_output_bytes;
+    // The child is killed when it's dropped on timeout
+    let ((stdout, stdout_truncated), (stderr, stderr_truncated), status) =
+        tokio::time::timeout(timeout, async {
+            tokio::try_join!(
+                read_capped(stdout, cap),
+                read_capped(stderr, cap),
+                child.wait()
+            )
+        })
+        .await
+        .map_err(|_| ExecError::Timeout(timeout))?
+        .map_err(ExecError::Wait)?;
+    Ok(ExecOutput {
+        success: status.success(),
+        stdout,
+        stderr,
+        truncated: stdout_truncated || stderr_truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[tokio::test]
+    async fn test_run_allowed_executables() {
+        let policy = ExecPolicy {
+            allowed_executables: Some(vec!["/nonexistent/sh".to_string()]),
+            ..Default::default()
+        };
+        let result = run(&policy, "sh", &["-c", "true"], TIMEOUT).await;
+        assert!(matches!(
+            result,
+            Err(ExecError::Policy(ExecPolicyError::ExecutableNotAllowed { program })) if program == "sh"
+        ));
+
+        let sh = find_executable("sh").unwrap().display().to_string();
+        for entry in ["sh".to_string(), sh] {
+            let policy = ExecPolicy {
+                allowed_executables: Some(vec![entry]),
+                ..Default::default()
+            };
+            assert!(
+                run(&policy, "sh", &["-c", "true"], TIMEOUT)
+                    .await
+                    .unwrap()
+                    .success
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_env_allowlist() {
+        env::set_var("GETTER_TEST_EXEC_KEPT", "kept");
+        env::set_var("GETTER_TEST_EXEC_STRIPPED", "stripped");
+        let policy = ExecPolicy {
+            env_allowlist: Some(vec![
+                "PATH".to_string(),
+                "GETTER_TEST_EXEC_KEPT".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let output = run(&policy, "sh", &["-c", "env"], TIMEOUT).await.unwrap();
+        let env = String::from_utf8(output.stdout).unwrap();
+        assert!(env.contains("GETTER_TEST_EXEC_KEPT=kept"));
+        assert!(!env.contains("GETTER_TEST_EXEC_STRIPPED"));
+    }
+
+    #[tokio::test]
+    async fn test_run_working_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let policy = ExecPolicy {
+            working_dir: Some(temp_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let output = run(&policy, "sh", &["-c", "pwd -P"], TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap().trim(),
+            temp_dir
+                .path()
+                .canonicalize()
+                .unwrap()
+                .display()
+                .to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_max_output_bytes() {
+        let policy = ExecPolicy {
+            max_output_bytes: Some(10),
+            ..Default::default()
+        };
+        let script = "head -c 100000 /dev/zero | tr '\\0' a; echo err >&2";
+        let output = run(&policy, "sh", &["-c", script], TIMEOUT).await.unwrap();
+        assert!(output.success);
+        assert_eq!(output.stdout, b"aaaaaaaaaa");
+        assert_eq!(output.stderr, b"err\n");
+        assert!(output.truncated);
+
+        let output = run(&ExecPolicy::default(), "sh", &["-c", script], TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(output.stdout.len(), 100000);
+        assert!(!output.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_run_disabled() {
+        let policy = ExecPolicy {
+            enabled: false,
+            ..Default::default()
+        };
+        let result = run(&policy, "sh", &["-c", "true"], TIMEOUT).await;
+        assert!(matches!(
+            result,
+            Err(ExecError::Policy(ExecPolicyError::ExecutionDisabled))
+        ));
+    }
+}
@@ -11,15 +11,133 @@ use once_cell::sync::Lazy;
 use rustls::ClientConfig;
 #[cfg(feature = "rustls-platform-verifier")]
 use rustls_platform_verifier::BuilderVerifierExt;
-use std::{collections::HashMap, fmt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, SystemTime},
+};
 
 // Custom http response Error
 #[derive(Debug)]
 pub struct ResponseData {
     pub status: u16,
+    // Header names are lowercase
+    pub headers: HashMap<String, String>,
     pub body: Option<Bytes>,
 }
 
+/// Caching directive derived from the `Cache-Control`/`Expires` response headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    NoStore,
+    MaxAge(u64),
+    /// An expire time getter picks itself for data expected to change soon.
+    /// Unlike a server's max-age, it isn't clamped to the configured bounds.
+    Recheck(u64),
+}
+
+/// The policy that expires first; no-store wins over everything.
+pub fn stricter_cache_policy(
+    a: Option<CachePolicy>,
+    b: Option<CachePolicy>,
+) -> Option<CachePolicy> {
+    match (a, b) {
+        (Some(CachePolicy::NoStore), _) | (_, Some(CachePolicy::NoStore)) => {
+            Some(CachePolicy::NoStore)
+        }
+        (Some(CachePolicy::MaxAge(a)), Some(CachePolicy::MaxAge(b))) => {
+            Some(CachePolicy::MaxAge(a.min(b)))
+        }
+        (Some(CachePolicy::Recheck(a)), Some(CachePolicy::Recheck(b)))
+        | (Some(CachePolicy::Recheck(a)), Some(CachePolicy::MaxAge(b)))
+        | (Some(CachePolicy::MaxAge(b)), Some(CachePolicy::Recheck(a))) => {
+            Some(CachePolicy::Recheck(a.min(b)))
+        }
+        (a, b) => a.or(b),
+    }
+}
+
+/// What a response can be revalidated with on a later conditional request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Adds `If-None-Match`/`If-Modified-Since` to `header_map`.
+    pub fn add_conditional_headers(&self, header_map: &mut HashMap<String, String>) {
+        if let Some(etag) = &self.etag {
+            header_map.insert("If-None-Match".to_string(), etag.clone());
+        }
+        if let Some(last_modified) = &self.last_modified {
+            header_map.insert("If-Modified-Since".to_string(), last_modified.clone());
+        }
+    }
+}
+
+impl ResponseData {
+    /// Returns `None` when the response has neither an ETag nor a
+    /// Last-Modified header.
+    pub fn validators(&self) -> Option<Validators> {
+        let validators = Validators {
+            etag: self.headers.get("etag").cloned(),
+            last_modified: self.headers.get("last-modified").cloned(),
+        };
+        if validators.etag.is_none() && validators.last_modified.is_none() {
+            return None;
+        }
+        Some(validators)
+    }
+
+    /// Whether a conditional request found the validated body still current.
+    pub fn is_not_modified(&self) -> bool {
+        self.status == StatusCode::NOT_MODIFIED.as_u16()
+    }
+
+    /// Returns `None` when the server gave no usable caching hint.
+    pub fn cache_policy(&self) -> Option<CachePolicy> {
+        if let Some(cache_control) = self.headers.get("cache-control") {
+            let mut max_age = None;
+            for directive in cache_control.split(',') {
+                let directive = directive.trim().to_ascii_lowercase();
+                if directive == "no-store" {
+                    return Some(CachePolicy::NoStore);
+                } else if directive == "no-cache" {
+                    max_age = Some(0);
+                } else if let Some(value) = directive.strip_prefix("max-age=") {
+                    if let Ok(value) = value.trim_matches('"').parse::<u64>() {
+                        max_age = Some(max_age.map_or(value, |age: u64| age.min(value)));
+                    }
+                }
+            }
+            if let Some(max_age) = max_age {
+                return Some(CachePolicy::MaxAge(max_age));
+            }
+        }
+        // An invalid date (e.g. "0") means already expired
+        self.headers.get("expires").map(|expires| {
+            let max_age = httpdate::parse_http_date(expires)
+                .ok()
+                .and_then(|time| time.duration_since(SystemTime::now()).ok())
+                .unwrap_or(Duration::ZERO);
+            CachePolicy::MaxAge(max_age.as_secs())
+        })
+    }
+}
+
+fn get_headers<T>(res: &hyper::Response<T>) -> HashMap<String, String> {
+    res.headers()
+        .iter()
+        .filter_map(|(key, value)| {
+            Some((key.as_str().to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect()
+}
+
 impl fmt::Display for ResponseData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -45,6 +163,23 @@ pub async fn get(
     }
 }
 
+/// `get` that the server may answer with an empty 304 when the body
+/// `validators` came with is still current.
+pub async fn get_conditional(
+    url: Uri,
+    header_map: &HashMap<String, String>,
+    validators: Option<&Validators>,
+) -> Result<ResponseData, Box<dyn std::error::Error + Send + Sync>> {
+    match validators {
+        Some(validators) => {
+            let mut header_map = header_map.clone();
+            validators.add_conditional_headers(&mut header_map);
+            get(url, &header_map).await
+        }
+        None => get(url, header_map).await,
+    }
+}
+
 pub async fn head(
     url: Uri,
     header_map: &HashMap<String, String>,
@@ -56,38 +191,54 @@ pub async fn head(
     }
 }
 
+/// Sends an actual HEAD request. `head` fetches with GET and drops the
+/// body instead, which also works with servers that mishandle HEAD.
+pub async fn head_request(
+    url: Uri,
+    header_map: &HashMap<String, String>,
+) -> Result<ResponseData, Box<dyn std::error::Error + Send + Sync>> {
+    if url.scheme_str() == Some("https") {
+        _https_get(url, header_map, true, "HEAD").await
+    } else {
+        _http_get(url, header_map, true, "HEAD").await
+    }
+}
+
 pub async fn http_get(
     url: Uri,
     header_map: &HashMap<String, String>,
 ) -> Result<ResponseData, Box<dyn std::error::Error + Send + Sync>> {
-    _http_get(url, header_map, false).await
+    _http_get(url, header_map, false, "GET").await
 }
 
 pub async fn http_head(
     url: Uri,
     header_map: &HashMap<String, String>,
 ) -> Result<ResponseData, Box<dyn std::error::Error + Send + Sync>> {
-    _http_get(url, header_map, true).await
+    _http_get(url, header_map, true, "GET").await
 }
 
 async fn _http_get(
     url: Uri,
     header_map: &HashMap<String, String>,
     only_status: bool,
+    method: &str,
 ) -> Result<ResponseData, Box<dyn std::error::Error + Send + Sync>> {
     let http = HttpConnector::new();
     let client = Client::builder(TokioExecutor::new()).build(http);
 
-    let mut req = hyper::Request::builder().method("GET").uri(url.clone());
+    let mut req = hyper::Request::builder().method(method).uri(url.clone());
     for (key, value) in header_map {
         req = req.header(key, value);
     }
     let req = req.body(Empty::<Bytes>::new())?;
     let mut res = client.request(req).await?;
     let status = res.status();
+    let headers = get_headers(&res);
     if only_status {
         Ok(ResponseData {
             status: status.as_u16(),
+            headers,
             body: None,
         })
     } else {
@@ -100,6 +251,7 @@ async fn _http_get(
         }
         Ok(ResponseData {
             status: status.as_u16(),
+            headers,
             body: Some(body.freeze()),
         })
     }
@@ -109,14 +261,14 @@ pub async fn https_get(
     url: Uri,
     header_map: &HashMap<String, String>,
 ) -> Result<ResponseData, Box<dyn std::error::Error + Send + Sync>> {
-    _https_get(url, header_map, false).await
+    _https_get(url, header_map, false, "GET").await
 }
 
 pub async fn https_head(
     url: Uri,
     header_map: &HashMap<String, String>,
 ) -> Result<ResponseData, Box<dyn std::error::Error + Send + Sync>> {
-    _https_get(url, header_map, true).await
+    _https_get(url, header_map, true, "GET").await
 }
 
 // Global https provider with lazy initialization
@@ -195,10 +347,11 @@ async fn _https_get(
     url: Uri,
     header_map: &HashMap<String, String>,
     only_status: bool,
+    method: &str,
 ) -> Result<ResponseData, Box<dyn std::error::Error + Send + Sync>> {
     let https = https_config()?;
     let client = Client::builder(TokioExecutor::new()).build(https);
-    let mut req = hyper::Request::builder().method("GET").uri(url.clone());
+    let mut req = hyper::Request::builder().method(method).uri(url.clone());
     for (key, value) in header_map {
         req = req.header(key, value);
     }
@@ -206,9 +359,11 @@ async fn _https_get(
 
     let mut res = client.request(req).await?;
     let status = res.status();
+    let headers = get_headers(&res);
     if only_status {
         Ok(ResponseData {
             status: status.as_u16(),
+            headers,
             body: None,
         })
     } else {
@@ -221,6 +376,7 @@ async fn _https_get(
         }
         Ok(ResponseData {
             status: status.as_u16(),
+            headers,
             body: Some(body.freeze()),
         })
     }
@@ -238,6 +394,58 @@ pub fn http_status_is_ok(status: u16) -> bool {
 mod tests {
     use super::*;
 
+    fn response_with_headers(headers: &[(&str, &str)]) -> ResponseData {
+        ResponseData {
+            status: 200,
+            headers: headers
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            body: None,
+        }
+    }
+
+    #[test]
+    fn test_cache_policy() {
+        let rsp = response_with_headers(&[("cache-control", "public, max-age=60")]);
+        assert_eq!(rsp.cache_policy(), Some(CachePolicy::MaxAge(60)));
+        let rsp = response_with_headers(&[("cache-control", "no-cache")]);
+        assert_eq!(rsp.cache_policy(), Some(CachePolicy::MaxAge(0)));
+        let rsp = response_with_headers(&[("cache-control", "max-age=60, No-Store")]);
+        assert_eq!(rsp.cache_policy(), Some(CachePolicy::NoStore));
+        let rsp = response_with_headers(&[("cache-control", "private")]);
+        assert_eq!(rsp.cache_policy(), None);
+        let rsp = response_with_headers(&[]);
+        assert_eq!(rsp.cache_policy(), None);
+    }
+
+    #[test]
+    fn test_cache_policy_expires() {
+        let expires = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+        let rsp = response_with_headers(&[("expires", &expires)]);
+        match rsp.cache_policy() {
+            Some(CachePolicy::MaxAge(max_age)) => assert!((118..=120).contains(&max_age)),
+            policy => panic!("unexpected policy: {:?}", policy),
+        }
+        let rsp = response_with_headers(&[("expires", "0")]);
+        assert_eq!(rsp.cache_policy(), Some(CachePolicy::MaxAge(0)));
+        // max-age takes precedence over Expires
+        let rsp = response_with_headers(&[("cache-control", "max-age=5"), ("expires", &expires)]);
+        assert_eq!(rsp.cache_policy(), Some(CachePolicy::MaxAge(5)));
+    }
+
+    #[test]
+    fn test_validators() {
+        let last_modified = "Wed, 21 Oct 2015 07:28:00 GMT";
+        let rsp = response_with_headers(&[("etag", "W/\"abc\""), ("last-modified", last_modified)]);
+        let validators = rsp.validators().unwrap();
+        let mut header_map = HashMap::new();
+        validators.add_conditional_headers(&mut header_map);
+        assert_eq!(header_map["If-None-Match"], "W/\"abc\"");
+        assert_eq!(header_map["If-Modified-Since"], last_modified);
+        assert_eq!(response_with_headers(&[]).validators(), None);
+    }
+
     #[tokio::test]
     async fn test_https_get() {
         let url = "https://example.com".parse().unwrap();
@@ -1,5 +1,5 @@
 use bytes::Bytes;
-use serde::de::Deserialize;
+use serde::de::{Deserialize, DeserializeOwned};
 use serde::ser::Serialize;
 
 pub fn json_to_bytes<T>(json: &T) -> Result<Bytes, serde_json::Error>
@@ -29,3 +29,149 @@ where
 {
     serde_json::from_str(string)
 }
+
+/// Error of a lenient parse, pointing at the offending line.
+#[derive(Debug)]
+pub struct JsonLoadError {
+    pub line: usize,
+    pub column: usize,
+    pub snippet: String,
+    error: serde_json::Error,
+}
+
+impl std::fmt::Display for JsonLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // serde_json appends its own position, which is reported separately
+        let message = self.error.to_string();
+        let message = message
+            .rsplit_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message);
+        write!(
+            f,
+            "line {} column {}: {} near `{}`",
+            self.line, self.column, message, self.snippet
+        )
+    }
+}
+
+impl std::error::Error for JsonLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+// Blank out comments and trailing commas in place, so error positions still
+// match the original text
+fn strip_json_extras(string: &str) -> Vec<u8> {
+    let mut bytes = string.as_bytes().to_vec();
+    let mut i = 0;
+    let mut in_string = false;
+    let mut pending_comma: Option<usize> = None;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if in_string {
+            if c == b'\\' {
+                i += 1;
+            } else if c == b'"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
+        match (c, bytes.get(i + 1)) {
+            (b'/', Some(b'/')) => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    bytes[i] = b' ';
+                    i += 1;
+                }
+                continue;
+            }
+            (b'/', Some(b'*')) => {
+                let end = string[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+                for byte in &mut bytes[i..end] {
+                    if *byte != b'\n' {
+                        *byte = b' ';
+                    }
+                }
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        if !c.is_ascii_whitespace() {
+            if let Some(comma) = pending_comma.take() {
+                if c == b'}' || c == b']' {
+                    bytes[comma] = b' ';
+                }
+            }
+            match c {
+                b'"' => in_string = true,
+                b',' => pending_comma = Some(i),
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    bytes
+}
+
+/// Parses hand-edited JSON, accepting a UTF-8 BOM, `//` and `/* */` comments
+/// and trailing commas. Only meant for reading; writes stay strict.
+pub fn lenient_string_to_json<T>(string: &str) -> Result<T, JsonLoadError>
+where
+    T: DeserializeOwned,
+{
+    let string = string.strip_prefix('\u{feff}').unwrap_or(string);
+    serde_json::from_slice(&strip_json_extras(string)).map_err(|error| {
+        let (line, column) = (error.line(), error.column());
+        let snippet = string
+            .lines()
+            .nth(line.saturating_sub(1))
+            .unwrap_or_default()
+            .trim()
+            .chars()
+            .take(80)
+            .collect();
+        JsonLoadError {
+            line,
+            column,
+            snippet,
+            error,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_lenient_string_to_json() {
+        let json = "\u{feff}{
+            // line comment
+            \"a\": \"http://x//y\", /* block
+            comment */ \"b\": [1, 2, 3,],
+            \"c\": \"with, \\\"quote\\\" ,}\",
+        }";
+        let value: BTreeMap<String, serde_json::Value> = lenient_string_to_json(json).unwrap();
+        assert_eq!(value["a"], "http://x//y");
+        assert_eq!(value["b"], serde_json::json!([1, 2, 3]));
+        assert_eq!(value["c"], "with, \"quote\" ,}");
+    }
+
+    #[test]
+    fn test_lenient_string_to_json_error() {
+        let json = "{\n  // comment\n  \"a\": 1\n  \"b\": 2\n}";
+        let error = lenient_string_to_json::<serde_json::Value>(json).unwrap_err();
+        assert_eq!(error.line, 4);
+        assert_eq!(error.snippet, "\"b\": 2");
+        assert_eq!(error.column, 3);
+        assert_eq!(
+            error.to_string(),
+            "line 4 column 3: expected `,` or `}` near `\"b\": 2`"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::env;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+/// `$NETRC`, or `.netrc` in the home directory.
+pub fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".netrc"))
+}
+
+pub fn read_netrc() -> Option<String> {
+    read_to_string(netrc_path()?).ok()
+}
+
+/// The password of the `machine` entry for `host`, falling back to the
+/// `default` entry, as curl and git resolve it.
+pub fn find_password(content: &str, host: &str) -> Option<String> {
+    let mut tokens = content.split_whitespace();
+    let mut in_match = false;
+    let mut in_default = false;
+    let mut default_password = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => {
+                in_match = tokens.next() == Some(host);
+                in_default = false;
+            }
+            "default" => {
+                in_match = false;
+                in_default = true;
+            }
+            "password" => {
+                let password = tokens.next();
+                if in_match {
+                    return password.map(|p| p.to_string());
+                }
+                if in_default && default_password.is_none() {
+                    default_password = password.map(|p| p.to_string());
+                }
+            }
+            "login" | "account" => {
+                tokens.next();
+            }
+            // Macro bodies run to the next blank line, which whitespace
+            // splitting can't see; stop rather than misread them
+            "macdef" => break,
+            _ => (),
+        }
+    }
+    default_password
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_password() {
+        let content = "machine example.com login a password wrong\n\
+                       machine api.github.com\n  login me\n  password ghp_token\n\
+                       default login anonymous password fallback\n";
+        assert_eq!(
+            find_password(content, "api.github.com"),
+            Some("ghp_token".to_string())
+        );
+        assert_eq!(
+            find_password(content, "gitlab.com"),
+            Some("fallback".to_string())
+        );
+        assert_eq!(
+            find_password("machine example.com password p", "gitlab.com"),
+            None
+        );
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub fn get_now_unix() -> u64 {
@@ -6,3 +8,62 @@ pub fn get_now_unix() -> u64 {
         .unwrap_or_else(|_| Duration::from_secs(0))
         .as_secs()
 }
+
+/// Source of the current time, replaceable so time-dependent logic can be
+/// tested without sleeping.
+pub trait Clock: Send + Sync {
+    /// Seconds since the unix epoch.
+    fn now(&self) -> u64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        get_now_unix()
+    }
+}
+
+/// A clock that only moves when advanced.
+#[cfg(any(test, feature = "test-util"))]
+pub struct ManualClock {
+    now: AtomicU64,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl ManualClock {
+    pub fn new(now: u64) -> Self {
+        ManualClock {
+            now: AtomicU64::new(now),
+        }
+    }
+
+    /// Starts at the real current time, e.g. to compare against file times.
+    pub fn starting_now() -> Self {
+        Self::new(get_now_unix())
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.now.fetch_add(seconds, Ordering::SeqCst);
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::new(1000);
+        assert_eq!(clock.now(), 1000);
+        clock.advance(61);
+        assert_eq!(clock.now(), 1061);
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs::read_to_string;
+
+/// A `${env:NAME}` or `${file:/path}` value that can't be resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnresolvedRefError {
+    Env { key: String, name: String },
+    File { key: String, path: String },
+}
+
+impl fmt::Display for UnresolvedRefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnresolvedRefError::Env { key, name } => write!(
+                f,
+                "UnresolvedRefError: {} refers to environment variable {}, which is not set",
+                key, name
+            ),
+            UnresolvedRefError::File { key, path } => write!(
+                f,
+                "UnresolvedRefError: {} refers to file {}, which can't be read",
+                key, path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UnresolvedRefError {}
+
+/// The value a `${env:NAME}` or `${file:/path}` reference points to, with
+/// the trailing newline of a file dropped. None if `value` isn't a reference.
+pub fn resolve_ref(key: &str, value: &str) -> Option<Result<String, UnresolvedRefError>> {
+    let reference = value.strip_prefix("${")?.strip_suffix('}')?;
+    if let Some(name) = reference.strip_prefix("env:") {
+        return Some(env::var(name).map_err(|_| UnresolvedRefError::Env {
+            key: key.to_string(),
+            name: name.to_string(),
+        }));
+    }
+    reference.strip_prefix("file:").map(|path| {
+        read_to_string(path)
+            .map(|content| content.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|_| UnresolvedRefError::File {
+                key: key.to_string(),
+                path: path.to_string(),
+            })
+    })
+}
+
+/// Resolved values of the references in `data`, by key.
+pub fn resolve_refs<'a>(
+    data: &BTreeMap<&'a str, &str>,
+) -> Result<BTreeMap<&'a str, String>, UnresolvedRefError> {
+    let mut resolved = BTreeMap::new();
+    for (key, value) in data {
+        if let Some(value) = resolve_ref(key, value) {
+            resolved.insert(*key, value?);
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_resolve_ref() {
+        env::set_var("GETTER_TEST_RESOLVE_REF", "from-env");
+        assert_eq!(
+            resolve_ref("token", "${env:GETTER_TEST_RESOLVE_REF}"),
+            Some(Ok("from-env".to_string()))
+        );
+        assert_eq!(
+            resolve_ref("token", "${env:GETTER_TEST_RESOLVE_REF_UNSET}"),
+            Some(Err(UnresolvedRefError::Env {
+                key: "token".to_string(),
+                name: "GETTER_TEST_RESOLVE_REF_UNSET".to_string(),
+            }))
+        );
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("secret");
+        fs::write(&path, "from-file\n").unwrap();
+        let reference = format!("${{file:{}}}", path.display());
+        assert_eq!(
+            resolve_ref("token", &reference),
+            Some(Ok("from-file".to_string()))
+        );
+        let missing = format!("${{file:{}}}", temp_dir.path().join("none").display());
+        assert!(matches!(
+            resolve_ref("token", &missing),
+            Some(Err(UnresolvedRefError::File { .. }))
+        ));
+
+        assert_eq!(resolve_ref("token", "plain"), None);
+        assert_eq!(resolve_ref("token", "${other:x}"), None);
+        assert_eq!(resolve_ref("token", "prefix ${env:HOME}"), None);
+    }
+}
@@ -52,6 +52,67 @@ impl PartialOrd for Version {
     }
 }
 
+/// Per-app rules turning raw release tags into comparable version strings.
+///
+/// `extract` runs first (its first capture group, or the whole match, is
+/// kept), then the first matching prefix and suffix are stripped.
+#[derive(Debug, Default)]
+pub struct VersionNormalizer {
+    extract: Option<Regex>,
+    strip_prefixes: Vec<String>,
+    strip_suffixes: Vec<String>,
+}
+
+impl VersionNormalizer {
+    // Prefix/suffix lists are one entry per line
+    pub fn new(
+        extract: Option<&str>,
+        strip_prefixes: Option<&str>,
+        strip_suffixes: Option<&str>,
+    ) -> Result<Self, regex::Error> {
+        let split = |list: Option<&str>| {
+            list.unwrap_or_default()
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<String>>()
+        };
+        Ok(VersionNormalizer {
+            extract: extract.map(Regex::new).transpose()?,
+            strip_prefixes: split(strip_prefixes),
+            strip_suffixes: split(strip_suffixes),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extract.is_none() && self.strip_prefixes.is_empty() && self.strip_suffixes.is_empty()
+    }
+
+    pub fn normalize(&self, version: &str) -> String {
+        let mut version = version.trim();
+        if let Some(caps) = self.extract.as_ref().and_then(|re| re.captures(version)) {
+            if let Some(m) = caps.get(1).or_else(|| caps.get(0)) {
+                version = m.as_str();
+            }
+        }
+        if let Some(stripped) = self
+            .strip_prefixes
+            .iter()
+            .find_map(|prefix| version.strip_prefix(prefix.as_str()))
+        {
+            version = stripped;
+        }
+        if let Some(stripped) = self
+            .strip_suffixes
+            .iter()
+            .find_map(|suffix| version.strip_suffix(suffix.as_str()))
+        {
+            version = stripped;
+        }
+        version.trim().to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,4 +229,30 @@ mod tests {
         };
         assert_eq!(version.get_valid_version(), Some("1.0-alpha".to_string()));
     }
+
+    #[test]
+    fn test_version_normalizer() {
+        let normalizer =
+            VersionNormalizer::new(None, Some("release-\nv"), Some("-stable\n-beta")).unwrap();
+        assert_eq!(normalizer.normalize("release-2024.05-stable"), "2024.05");
+        assert_eq!(normalizer.normalize("v1.0.0"), "1.0.0");
+        assert!(
+            Version::new(normalizer.normalize("release-2024.05-stable"))
+                > Version::new("2024.4".to_string())
+        );
+
+        let normalizer = VersionNormalizer::new(Some(r"_v([\d.]+)_"), None, None).unwrap();
+        assert_eq!(normalizer.normalize("app_v1.2.3_build45"), "1.2.3");
+        // Not matching leaves the version alone
+        assert_eq!(normalizer.normalize("1.2.4"), "1.2.4");
+        let installed = Version::new(normalizer.normalize("1.2.2"));
+        assert!(Version::new(normalizer.normalize("app_v1.2.3_build45")) > installed);
+        assert!(
+            Version::new(normalizer.normalize("app_v1.2.10_build2"))
+                > Version::new(normalizer.normalize("app_v1.2.3_build45"))
+        );
+
+        assert!(VersionNormalizer::default().is_empty());
+        assert!(VersionNormalizer::new(Some("(unclosed"), None, None).is_err());
+    }
 }
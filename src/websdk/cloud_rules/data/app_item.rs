@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 /// AppConfig
@@ -36,6 +37,10 @@ pub struct AppItem {
 
     #[serde(rename = "info")]
     pub info: AppInfo,
+
+    /// Fields not modeled here, kept so that a round trip doesn't drop them
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -48,6 +53,9 @@ pub struct AppInfo {
 
     #[serde(rename = "extra_map")]
     pub extra_map: HashMap<String, String>, // Use HashMap to store arbitrary key/value pairs
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[cfg(test)]
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
 
 use super::app_item::AppItem;
 use super::hub_item::HubItem;
@@ -40,6 +42,94 @@ impl ConfigList {
     }
 }
 
+/// A problem found by [`ConfigList::validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigIssue {
+    /// An app (by name) without a uuid
+    MissingAppUuid(String),
+    /// A hub (by name) without a uuid
+    MissingHubUuid(String),
+    DuplicateUuid(String),
+    /// An app whose base_hub_uuid is not in the hub list
+    DanglingHubUuid {
+        app_uuid: String,
+        hub_uuid: String,
+    },
+    /// A url template that isn't an http(s) URL, or has a `%` not followed
+    /// by a key name
+    MalformedUrlTemplate {
+        hub_uuid: String,
+        template: String,
+    },
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigIssue::MissingAppUuid(name) => write!(f, "app {} has no uuid", name),
+            ConfigIssue::MissingHubUuid(name) => write!(f, "hub {} has no uuid", name),
+            ConfigIssue::DuplicateUuid(uuid) => write!(f, "uuid {} is used more than once", uuid),
+            ConfigIssue::DanglingHubUuid { app_uuid, hub_uuid } => {
+                write!(f, "app {} refers to unknown hub {}", app_uuid, hub_uuid)
+            }
+            ConfigIssue::MalformedUrlTemplate { hub_uuid, template } => {
+                write!(
+                    f,
+                    "hub {} has malformed url template {}",
+                    hub_uuid, template
+                )
+            }
+        }
+    }
+}
+
+// Templates may also use keys filled in by the client (e.g. `%language`),
+// so keys aren't checked against api_keywords
+fn is_valid_url_template(template: &str) -> bool {
+    (template.starts_with("https://") || template.starts_with("http://"))
+        && template
+            .split('%')
+            .skip(1)
+            .all(|part| part.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+impl ConfigList {
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut seen = HashSet::new();
+        for hub in &self.hub_config_list {
+            if hub.uuid.is_empty() {
+                issues.push(ConfigIssue::MissingHubUuid(hub.info.hub_name.clone()));
+            } else if !seen.insert(hub.uuid.as_str()) {
+                issues.push(ConfigIssue::DuplicateUuid(hub.uuid.clone()));
+            }
+            for template in &hub.app_url_templates {
+                if !is_valid_url_template(template) {
+                    issues.push(ConfigIssue::MalformedUrlTemplate {
+                        hub_uuid: hub.uuid.clone(),
+                        template: template.clone(),
+                    });
+                }
+            }
+        }
+        let hub_uuids = seen.clone();
+        for app in &self.app_config_list {
+            if app.uuid.is_empty() {
+                issues.push(ConfigIssue::MissingAppUuid(app.info.name.clone()));
+            } else if !seen.insert(app.uuid.as_str()) {
+                issues.push(ConfigIssue::DuplicateUuid(app.uuid.clone()));
+            }
+            if !hub_uuids.contains(app.base_hub_uuid.as_str()) {
+                issues.push(ConfigIssue::DanglingHubUuid {
+                    app_uuid: app.uuid.clone(),
+                    hub_uuid: app.base_hub_uuid.clone(),
+                });
+            }
+        }
+        issues
+    }
+}
+
 impl ConfigListViewer<'_> {
     pub fn to_owned(&self) -> ConfigList {
         ConfigList {
@@ -77,4 +167,76 @@ mod tests {
             "Xposed Module Repository"
         );
     }
+
+    #[test]
+    fn test_config_list_round_trip() {
+        let json = fs::read_to_string("tests/files/data/UpgradeAll-rules_rules.json").unwrap();
+        let mut original: serde_json::Value = serde_json::from_str(&json).unwrap();
+        original["hub_config_list"][0]["info"]["unmodeled"] = "kept".into();
+        original["app_config_list"][0]["unmodeled"] = serde_json::json!({"a": [1, 2]});
+
+        let config_list: ConfigList = serde_json::from_value(original.clone()).unwrap();
+        let saved = serde_json::to_value(&config_list).unwrap();
+
+        // Everything in the source survives; saving only fills in defaults
+        fn is_subset(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+            match (a, b) {
+                (serde_json::Value::Object(a), serde_json::Value::Object(b)) => a
+                    .iter()
+                    .all(|(k, v)| b.get(k).is_some_and(|bv| is_subset(v, bv))),
+                (serde_json::Value::Array(a), serde_json::Value::Array(b)) => {
+                    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| is_subset(a, b))
+                }
+                _ => a == b,
+            }
+        }
+        assert!(is_subset(&original, &saved));
+        let reloaded: ConfigList = serde_json::from_value(saved.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&reloaded).unwrap(), saved);
+    }
+
+    #[test]
+    fn test_validate() {
+        let json = fs::read_to_string("tests/files/data/UpgradeAll-rules_rules.json").unwrap();
+        let config_list: ConfigList = serde_json::from_str(&json).unwrap();
+        assert_eq!(config_list.validate(), vec![]);
+
+        let json = r#"
+{
+  "app_config_list": [
+    {"base_version": 2, "config_version": 1, "uuid": "", "base_hub_uuid": "hub",
+     "info": {"name": "NoUuid", "url": "", "extra_map": {}}},
+    {"base_version": 2, "config_version": 1, "uuid": "hub", "base_hub_uuid": "hub",
+     "info": {"name": "Duplicate", "url": "", "extra_map": {}}},
+    {"base_version": 2, "config_version": 1, "uuid": "app", "base_hub_uuid": "missing",
+     "info": {"name": "Dangling", "url": "", "extra_map": {}}}
+  ],
+  "hub_config_list": [
+    {"uuid": "hub", "info": {"hub_name": "Hub"}, "api_keywords": ["owner"],
+     "app_url_templates": ["https://example.com/%owner/", "https://example.com/%/", "example.com"]},
+    {"uuid": "", "info": {"hub_name": "NoUuid"}}
+  ]
+}"#;
+        let config_list: ConfigList = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            config_list.validate(),
+            vec![
+                ConfigIssue::MalformedUrlTemplate {
+                    hub_uuid: "hub".to_string(),
+                    template: "https://example.com/%/".to_string()
+                },
+                ConfigIssue::MalformedUrlTemplate {
+                    hub_uuid: "hub".to_string(),
+                    template: "example.com".to_string()
+                },
+                ConfigIssue::MissingHubUuid("NoUuid".to_string()),
+                ConfigIssue::MissingAppUuid("NoUuid".to_string()),
+                ConfigIssue::DuplicateUuid("hub".to_string()),
+                ConfigIssue::DanglingHubUuid {
+                    app_uuid: "app".to_string(),
+                    hub_uuid: "missing".to_string()
+                },
+            ]
+        );
+    }
 }
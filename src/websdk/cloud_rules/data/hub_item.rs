@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 /// HubConfig
 ///
@@ -40,6 +41,10 @@ pub struct HubItem {
 
     #[serde(rename = "target_check_api")]
     pub target_check_api: Option<String>,
+
+    /// Fields not modeled here, kept so that a round trip doesn't drop them
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -49,6 +54,9 @@ pub struct Info {
 
     #[serde(rename = "hub_icon_url", default)]
     pub hub_icon_url: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[cfg(test)]
@@ -1,15 +1,27 @@
 use async_fn_traits::AsyncFnOnce2;
+use bytes::Bytes;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
 
-use super::data::release::ReleaseData;
-use super::provider::base_provider::{AppDataMap, DataMap, FIn, FOut, FunctionType, HubDataMap};
+use super::data::release::{AssetData, ReleaseData};
+use super::data::search::SearchResultData;
+use super::provider::base_provider::{
+    AppDataMap, DataMap, FIn, FOut, FunctionType, HubDataMap, NoReleaseError, StaleCache,
+    REVERSE_PROXY,
+};
 use super::provider::outside_rpc::OutsideProvider;
 use super::provider::{self, add_provider};
 use crate::cache::get_cache_manager;
-use crate::cache::manager::GroupType;
+use crate::cache::manager::{CacheManager, GroupType};
+use crate::utils::convert::borrow_btreemap_values;
+use crate::utils::http::{stricter_cache_policy, CachePolicy, Validators};
 use crate::utils::json::{bytes_to_json, json_to_bytes};
-use std::collections::HashMap;
+use crate::utils::value_ref::resolve_refs;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 struct ErrorProviderNotFound;
@@ -26,24 +38,187 @@ impl std::error::Error for ErrorProviderNotFound {
     }
 }
 
+/// Required app_data keys the app lacks; its hub is never asked.
+#[derive(Debug, Clone)]
+struct MissingAppKeysError {
+    keys: Vec<&'static str>,
+}
+
+impl std::fmt::Display for MissingAppKeysError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "app_data lacks {}", self.keys.join(", "))
+    }
+}
+
+impl std::error::Error for MissingAppKeysError {}
+
+/// Per-call overrides, leaving the stored config untouched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckOptions {
+    /// Neither read nor write the response cache.
+    #[serde(default)]
+    pub bypass_cache: bool,
+    /// Ignore `reverse_proxy` and request the origin URLs.
+    #[serde(default)]
+    pub bypass_proxy: bool,
+}
+
+const FUNCTION_TYPES: [FunctionType; 3] = [
+    FunctionType::CheckAppAvailable,
+    FunctionType::GetLatestRelease,
+    FunctionType::GetReleases,
+];
+
+// Cache keys are grouped under the hub uuid, so a hub can be dropped at once.
+// The uuid comes from callers and becomes a directory name, so anything but
+// [A-Za-z0-9_-] is percent-encoded to keep it a single plain path segment.
+fn get_hub_cache_key(uuid: &str, key: &str) -> String {
+    let hub_segment = uuid
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect::<String>();
+    format!("{}/{}", hub_segment, key)
+}
+
+fn get_api_cache_key(
+    uuid: &str,
+    func_type: &FunctionType,
+    parse_version: u32,
+    data_map: &DataMap,
+) -> String {
+    let mut hasher = DefaultHasher::new();
+    uuid.hash(&mut hasher);
+    func_type.hash(&mut hasher);
+    parse_version.hash(&mut hasher);
+    data_map.hash(&mut hasher);
+    get_hub_cache_key(uuid, &hasher.finish().to_string())
+}
+
+/// Saves raw provider responses, honoring their caching directives and
+/// keeping their validators for revalidation once they expire. Returns the
+/// strictest directive, which the parsed result inherits.
+async fn save_cached_map(
+    cache_manager: &Mutex<CacheManager>,
+    uuid: &str,
+    cached_map: HashMap<String, Bytes>,
+    cache_policy_map: Option<HashMap<String, CachePolicy>>,
+    mut validators_map: Option<HashMap<String, Validators>>,
+) -> Option<CachePolicy> {
+    let mut cache_manager = cache_manager.lock().await;
+    let mut result_policy = None;
+    for (key, value) in cached_map {
+        let policy = cache_policy_map
+            .as_ref()
+            .and_then(|map| map.get(&key))
+            .copied();
+        result_policy = stricter_cache_policy(result_policy, policy);
+        let validators = validators_map.as_mut().and_then(|map| map.remove(&key));
+        let key = get_hub_cache_key(uuid, &key);
+        let saved = match policy {
+            Some(CachePolicy::NoStore) => continue,
+            Some(CachePolicy::MaxAge(max_age)) => {
+                cache_manager
+                    .save_with_expire(&GroupType::RepoInside, &key, value, Some(max_age))
+                    .await
+            }
+            Some(CachePolicy::Recheck(expire_time)) => {
+                cache_manager
+                    .save_with_fixed_expire(&GroupType::RepoInside, &key, value, Some(expire_time))
+                    .await
+            }
+            None => {
+                cache_manager
+                    .save(&GroupType::RepoInside, &key, value)
+                    .await
+            }
+        };
+        if let (Ok(()), Some(validators)) = (saved, validators) {
+            let _ = cache_manager
+                .set_validators(&GroupType::RepoInside, &key, validators)
+                .await;
+        }
+    }
+    result_policy
+}
+
+type DataValues<'a> = BTreeMap<&'a str, String>;
+
+/// The values providers see: `${env:…}`/`${file:…}` references resolved
+/// and repo paths canonical. References stay in the stored config and
+/// resolve per call. None if one can't be resolved.
+fn effective_data<'a>(
+    uuid: &str,
+    app_data: &AppDataMap<'a>,
+    hub_data: &HubDataMap<'a>,
+) -> Option<(DataValues<'a>, DataValues<'a>)> {
+    let resolve = |data: &BTreeMap<&'a str, &'a str>| {
+        let mut resolved = resolve_refs(data).ok()?;
+        Some(
+            data.iter()
+                .map(|(key, value)| {
+                    let value = resolved.remove(key).unwrap_or_else(|| value.to_string());
+                    (*key, value)
+                })
+                .collect::<BTreeMap<_, _>>(),
+        )
+    };
+    let mut app_values = resolve(app_data)?;
+    provider::canonicalize_app_data(uuid, &mut app_values);
+    Some((app_values, resolve(hub_data)?))
+}
+
 async fn call_func<T, F>(
     uuid: &str,
     app_data: &AppDataMap<'_>,
     hub_data: &HubDataMap<'_>,
     func_type: FunctionType,
+    options: &CheckOptions,
     provider_func: F,
-) -> Result<Option<T>, ErrorProviderNotFound>
+) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>>
 where
     T: Send + DeserializeOwned + Serialize,
     F: for<'b> AsyncFnOnce2<&'b str, &'b FIn<'b>, Output = Option<FOut<T>>>,
 {
+    // Sending an unresolved reference would only fail at the server
+    let Some((app_values, hub_values)) = effective_data(uuid, app_data, hub_data) else {
+        return Ok(None);
+    };
+    let app_data = borrow_btreemap_values(&app_values);
+    let hub_data = hub_values
+        .iter()
+        .filter(|(key, _)| !options.bypass_proxy || **key != REVERSE_PROXY)
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect::<HubDataMap>();
+    let data_map = DataMap {
+        app_data: &app_data,
+        hub_data: &hub_data,
+    };
+    // Can't succeed until the app is edited, so don't spend a request on it
+    if let Some(keys) = provider::get_missing_app_keys(uuid, &data_map) {
+        if !keys.is_empty() {
+            return Err(Box::new(MissingAppKeysError { keys }));
+        }
+    }
+    if options.bypass_cache {
+        let fin = FIn::new(data_map, None);
+        return match provider_func(uuid, &fin).await {
+            Some(fout) => fout.result.map(Some),
+            None => Err(Box::new(ErrorProviderNotFound)),
+        };
+    }
     let cache_manager = get_cache_manager().await;
-    let data_map = DataMap { app_data, hub_data };
-    let api_cache_key = data_map.get_hash();
+    let parse_version = provider::get_parse_version(uuid).unwrap_or_default();
+    let api_cache_key = get_api_cache_key(uuid, &func_type, parse_version, &data_map);
     if let Some(bytes) = cache_manager
         .lock()
         .await
-        .get(&GroupType::Api, &api_cache_key.to_string(), None)
+        .get(&GroupType::Api, &api_cache_key, None)
         .await
     {
         if let Ok(value) = bytes_to_json::<T>(&bytes) {
@@ -52,44 +227,77 @@ where
     }
     let cache_keys = provider::get_cache_request_key(uuid, &func_type, &data_map);
     let mut cache_map = HashMap::new();
+    let mut stale_map = HashMap::new();
     if let Some(keys) = cache_keys {
+        let mut cache_manager = cache_manager.lock().await;
         for key in keys {
+            let hub_cache_key = get_hub_cache_key(uuid, &key);
             if let Some(value) = cache_manager
-                .lock()
-                .await
-                .get(&GroupType::RepoInside, &key, None)
+                .get(&GroupType::RepoInside, &hub_cache_key, None)
                 .await
             {
                 cache_map.insert(key, value);
+            } else if let Some((body, validators)) = cache_manager
+                .get_stale(&GroupType::RepoInside, &hub_cache_key)
+                .await
+            {
+                stale_map.insert(key, StaleCache { body, validators });
             }
         }
     }
 
-    let fin = FIn::new(data_map, Some(cache_map));
+    let fin = FIn::new(data_map, Some(cache_map)).with_stale_map(stale_map);
     if let Some(fout) = provider_func(uuid, &fin).await {
+        // Policies may also be set on responses read from the cache, e.g.
+        // to recheck a release early; the result follows those too
+        let mut result_policy = fout
+            .cache_policy_map
+            .iter()
+            .flat_map(|map| map.values())
+            .fold(None, |a, b| stricter_cache_policy(a, Some(*b)));
         if let Some(cached_map) = fout.cached_map {
-            for (key, value) in cached_map {
-                let _ = cache_manager
-                    .lock()
-                    .await
-                    .save(&GroupType::RepoInside, &key, value)
-                    .await;
-            }
+            result_policy = stricter_cache_policy(
+                result_policy,
+                save_cached_map(
+                    &cache_manager,
+                    uuid,
+                    cached_map,
+                    fout.cache_policy_map,
+                    fout.validators_map,
+                )
+                .await,
+            );
         }
-        if let Ok(data) = fout.result {
-            if let Ok(value) = json_to_bytes(&data) {
-                let _ = cache_manager
-                    .lock()
-                    .await
-                    .save(&GroupType::Api, &api_cache_key.to_string(), value)
-                    .await;
-            }
-            Ok(Some(data))
-        } else {
-            Ok(None)
+        let data = fout.result?;
+        if let Ok(value) = json_to_bytes(&data) {
+            let mut cache_manager = cache_manager.lock().await;
+            let _ = match result_policy {
+                Some(CachePolicy::NoStore) => return Ok(Some(data)),
+                Some(CachePolicy::MaxAge(max_age)) => {
+                    cache_manager
+                        .save_with_expire(&GroupType::Api, &api_cache_key, value, Some(max_age))
+                        .await
+                }
+                Some(CachePolicy::Recheck(expire_time)) => {
+                    cache_manager
+                        .save_with_fixed_expire(
+                            &GroupType::Api,
+                            &api_cache_key,
+                            value,
+                            Some(expire_time),
+                        )
+                        .await
+                }
+                None => {
+                    cache_manager
+                        .save(&GroupType::Api, &api_cache_key, value)
+                        .await
+                }
+            };
         }
+        Ok(Some(data))
     } else {
-        Err(ErrorProviderNotFound)
+        Err(Box::new(ErrorProviderNotFound))
     }
 }
 
@@ -97,12 +305,22 @@ pub async fn check_app_available<'a>(
     uuid: &str,
     app_data: &AppDataMap<'a>,
     hub_data: &HubDataMap<'a>,
+) -> Option<bool> {
+    check_app_available_with_options(uuid, app_data, hub_data, &CheckOptions::default()).await
+}
+
+pub async fn check_app_available_with_options<'a>(
+    uuid: &str,
+    app_data: &AppDataMap<'a>,
+    hub_data: &HubDataMap<'a>,
+    options: &CheckOptions,
 ) -> Option<bool> {
     call_func(
         uuid,
         app_data,
         hub_data,
         FunctionType::CheckAppAvailable,
+        options,
         provider::check_app_available,
     )
     .await
@@ -113,12 +331,22 @@ pub async fn get_latest_release<'a>(
     uuid: &str,
     app_data: &AppDataMap<'a>,
     hub_data: &HubDataMap<'a>,
+) -> Option<ReleaseData> {
+    get_latest_release_with_options(uuid, app_data, hub_data, &CheckOptions::default()).await
+}
+
+pub async fn get_latest_release_with_options<'a>(
+    uuid: &str,
+    app_data: &AppDataMap<'a>,
+    hub_data: &HubDataMap<'a>,
+    options: &CheckOptions,
 ) -> Option<ReleaseData> {
     call_func(
         uuid,
         app_data,
         hub_data,
         FunctionType::GetLatestRelease,
+        options,
         provider::get_latest_release,
     )
     .await
@@ -129,18 +357,265 @@ pub async fn get_releases<'a>(
     uuid: &str,
     app_data: &AppDataMap<'a>,
     hub_data: &HubDataMap<'a>,
+) -> Option<Vec<ReleaseData>> {
+    get_releases_with_options(uuid, app_data, hub_data, &CheckOptions::default()).await
+}
+
+pub async fn get_releases_with_options<'a>(
+    uuid: &str,
+    app_data: &AppDataMap<'a>,
+    hub_data: &HubDataMap<'a>,
+    options: &CheckOptions,
 ) -> Option<Vec<ReleaseData>> {
     call_func(
         uuid,
         app_data,
         hub_data,
         FunctionType::GetReleases,
+        options,
         provider::get_releases,
     )
     .await
     .unwrap_or(None)
 }
 
+/// An app of a batch check. The data is owned, so each check can run as
+/// its own task.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppQuery {
+    pub hub_uuid: String,
+    #[serde(default)]
+    pub app_data: BTreeMap<String, String>,
+    #[serde(default)]
+    pub hub_data: BTreeMap<String, String>,
+}
+
+/// The latest release of an app in a batch check, or why there is none.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppCheckResult {
+    pub release: Option<ReleaseData>,
+    pub error: Option<String>,
+}
+
+impl AppCheckResult {
+    fn error(error: String) -> Self {
+        AppCheckResult {
+            release: None,
+            error: Some(error),
+        }
+    }
+}
+
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+async fn check_app(app: &AppQuery, options: &CheckOptions) -> AppCheckResult {
+    fn borrow(data: &BTreeMap<String, String>) -> BTreeMap<&str, &str> {
+        data.iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+    let (app_data, hub_data) = (borrow(&app.app_data), borrow(&app.hub_data));
+    let unresolved_refs = get_unresolved_refs(&app_data, &hub_data);
+    if !unresolved_refs.is_empty() {
+        return AppCheckResult::error(unresolved_refs.join("; "));
+    }
+    match get_missing_app_keys(&app.hub_uuid, &app_data, &hub_data) {
+        None => return AppCheckResult::error(format!("hub {} not found", app.hub_uuid)),
+        Some(keys) if !keys.is_empty() => {
+            return AppCheckResult::error(MissingAppKeysError { keys }.to_string())
+        }
+        Some(_) => (),
+    }
+    let result = call_func(
+        &app.hub_uuid,
+        &app_data,
+        &hub_data,
+        FunctionType::GetLatestRelease,
+        options,
+        provider::get_latest_release,
+    )
+    .await;
+    match result {
+        Ok(Some(release)) => AppCheckResult {
+            release: Some(release),
+            error: None,
+        },
+        Ok(None) => AppCheckResult::error("no release found".to_string()),
+        Err(e) if e.is::<NoReleaseError>() => AppCheckResult::error("no release found".to_string()),
+        Err(e) => AppCheckResult::error(e.to_string()),
+    }
+}
+
+/// Latest releases of `apps`, in their order, with at most `concurrency`
+/// checks in flight.
+pub async fn get_latest_releases(
+    apps: Vec<AppQuery>,
+    concurrency: usize,
+    options: CheckOptions,
+) -> Vec<AppCheckResult> {
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut results = vec![None; apps.len()];
+    let mut tasks = JoinSet::new();
+    for (i, app) in apps.into_iter().enumerate() {
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            (i, check_app(&app, &options).await)
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((i, result)) = joined {
+            results[i] = Some(result);
+        }
+    }
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| AppCheckResult::error("check panicked".to_string())))
+        .collect()
+}
+
+/// Required app_data keys the app lacks for its hub; None if the hub is
+/// unknown.
+pub fn get_missing_app_keys<'a>(
+    uuid: &str,
+    app_data: &AppDataMap<'a>,
+    hub_data: &HubDataMap<'a>,
+) -> Option<Vec<&'static str>> {
+    provider::get_missing_app_keys(uuid, &DataMap { app_data, hub_data })
+}
+
+/// Why the `${env:…}`/`${file:…}` references in the app's data can't be
+/// resolved. Such apps are never checked.
+pub fn get_unresolved_refs<'a>(
+    app_data: &AppDataMap<'a>,
+    hub_data: &HubDataMap<'a>,
+) -> Vec<String> {
+    [resolve_refs(app_data), resolve_refs(hub_data)]
+        .into_iter()
+        .filter_map(|resolved| resolved.err())
+        .map(|e| e.to_string())
+        .collect()
+}
+
+/// Drops every cached response and result of the hub.
+pub async fn clear_cache_for_hub(uuid: &str) -> Result<(), std::io::Error> {
+    if uuid.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "empty hub uuid",
+        ));
+    }
+    get_cache_manager()
+        .await
+        .lock()
+        .await
+        .remove_prefix(&get_hub_cache_key(uuid, ""))
+        .await
+}
+
+/// Drops the cached responses and results of one app, as keyed by its
+/// provider. Responses shared with other apps (e.g. a repo index) go too.
+pub async fn clear_cache_for_app<'a>(
+    uuid: &str,
+    app_data: &AppDataMap<'a>,
+    hub_data: &HubDataMap<'a>,
+) {
+    let Some((app_values, hub_values)) = effective_data(uuid, app_data, hub_data) else {
+        return;
+    };
+    let app_data = borrow_btreemap_values(&app_values);
+    let hub_data = borrow_btreemap_values(&hub_values);
+    let data_map = DataMap {
+        app_data: &app_data,
+        hub_data: &hub_data,
+    };
+    let parse_version = provider::get_parse_version(uuid).unwrap_or_default();
+    let cache_manager = get_cache_manager().await;
+    let mut cache_manager = cache_manager.lock().await;
+    for func_type in FUNCTION_TYPES.iter() {
+        for key in provider::get_cache_request_key(uuid, func_type, &data_map).unwrap_or_default() {
+            let key = get_hub_cache_key(uuid, &key);
+            let _ = cache_manager.remove(&GroupType::RepoInside, &key).await;
+        }
+        let api_cache_key = get_api_cache_key(uuid, func_type, parse_version, &data_map);
+        let _ = cache_manager.remove(&GroupType::Api, &api_cache_key).await;
+    }
+}
+
+/// Normalizes a locally installed version with the app's rules, so it
+/// compares against release versions in the same form.
+pub fn normalize_version(
+    app_data: &AppDataMap,
+    hub_data: &HubDataMap,
+    version: &str,
+) -> Result<String, regex::Error> {
+    let data_map = DataMap { app_data, hub_data };
+    Ok(match data_map.get_version_normalizer()? {
+        Some(normalizer) => normalizer.normalize(version),
+        None => version.to_string(),
+    })
+}
+
+/// The asset of `release` to download, by the app's `asset_filter` and
+/// `preferred_abi`.
+pub fn select_asset(
+    app_data: &AppDataMap,
+    hub_data: &HubDataMap,
+    release: &ReleaseData,
+) -> Result<AssetData, Box<dyn std::error::Error + Send + Sync>> {
+    let selector = DataMap { app_data, hub_data }.get_asset_selector()?;
+    Ok(release.select_asset(&selector)?.clone())
+}
+
+/// Apps on the hub matching `query`, each with the app_data that tracks
+/// it. Responses the provider reads, such as a repo index, share the cache
+/// of release checks; the results aren't cached.
+pub async fn search_apps(
+    uuid: &str,
+    hub_data: &HubDataMap<'_>,
+    query: &str,
+) -> Result<Vec<SearchResultData>, Box<dyn std::error::Error + Send + Sync>> {
+    let app_data = AppDataMap::new();
+    let (_, hub_values) = effective_data(uuid, &app_data, hub_data)
+        .ok_or_else(|| get_unresolved_refs(&app_data, hub_data).join("; "))?;
+    let hub_data = borrow_btreemap_values(&hub_values);
+    let data_map = DataMap {
+        app_data: &app_data,
+        hub_data: &hub_data,
+    };
+    let keys =
+        provider::get_search_cache_request_key(uuid, &data_map).ok_or(ErrorProviderNotFound)?;
+    let cache_manager = get_cache_manager().await;
+    let mut cache_map = HashMap::new();
+    {
+        let mut cache_manager = cache_manager.lock().await;
+        for key in keys {
+            let hub_cache_key = get_hub_cache_key(uuid, &key);
+            if let Some(value) = cache_manager
+                .get(&GroupType::RepoInside, &hub_cache_key, None)
+                .await
+            {
+                cache_map.insert(key, value);
+            }
+        }
+    }
+    let fin = FIn::new(data_map, Some(cache_map));
+    let fout = provider::search_apps(uuid, &fin, query)
+        .await
+        .ok_or(ErrorProviderNotFound)?;
+    if let Some(cached_map) = fout.cached_map {
+        save_cached_map(
+            &cache_manager,
+            uuid,
+            cached_map,
+            fout.cache_policy_map,
+            fout.validators_map,
+        )
+        .await;
+    }
+    fout.result
+}
+
 pub fn add_outside_provider(uuid: &str, url: &str) {
     let provider = OutsideProvider {
         uuid: uuid.to_string(),
@@ -148,3 +623,647 @@ pub fn add_outside_provider(uuid: &str, url: &str) {
     };
     add_provider(uuid, provider);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::init_cache_manager;
+    use crate::websdk::repo::provider::base_provider::{BaseProvider, BaseProviderExt, TOKEN};
+    use async_trait::async_trait;
+    use once_cell::sync::Lazy;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // The cache manager is global, so every test here shares one directory
+    static TEST_CACHE_DIR: Lazy<tempfile::TempDir> = Lazy::new(|| tempfile::tempdir().unwrap());
+
+    async fn init_test_cache() {
+        init_cache_manager(TEST_CACHE_DIR.path()).await;
+    }
+
+    struct CountProvider {
+        calls: Arc<AtomicUsize>,
+        parse_version: Arc<AtomicU32>,
+        cache_policy: Option<CachePolicy>,
+    }
+
+    impl BaseProviderExt for CountProvider {}
+
+    #[async_trait]
+    impl BaseProvider for CountProvider {
+        fn get_parse_version(&self) -> u32 {
+            self.parse_version.load(Ordering::SeqCst)
+        }
+
+        fn get_cache_request_key(
+            &self,
+            _function_type: &FunctionType,
+            data_map: &DataMap,
+        ) -> Vec<String> {
+            vec![format!("raw_{}", data_map.app_data["id"])]
+        }
+
+        async fn check_app_available(&self, _fin: &FIn) -> FOut<bool> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            FOut::new(true)
+        }
+
+        async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let raw_key = format!("raw_{}", fin.data_map.app_data["id"]);
+            FOut::new(vec![ReleaseData {
+                version_number: "1.0.0".to_string(),
+                changelog: self.replace_proxy_url(fin, "https://origin.example/releases"),
+                assets: vec![],
+                extra: None,
+            }])
+            .set_cache(&raw_key, Bytes::from("raw"))
+            .set_cache_policy(&raw_key, self.cache_policy)
+        }
+    }
+
+    fn add_count_provider(
+        uuid: &str,
+        cache_policy: Option<CachePolicy>,
+    ) -> (Arc<AtomicUsize>, Arc<AtomicU32>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let parse_version = Arc::new(AtomicU32::new(1));
+        add_provider(
+            uuid,
+            CountProvider {
+                calls: calls.clone(),
+                parse_version: parse_version.clone(),
+                cache_policy,
+            },
+        );
+        (calls, parse_version)
+    }
+
+    #[tokio::test]
+    async fn test_parse_version_invalidates_cache() {
+        init_test_cache().await;
+
+        let uuid_a = "test-parse-version-a";
+        let uuid_b = "test-parse-version-b";
+        let (calls_a, parse_version_a) = add_count_provider(uuid_a, None);
+        let (calls_b, _) = add_count_provider(uuid_b, None);
+        let app_data = BTreeMap::from([("id", "test_parse_version")]);
+        let hub_data = BTreeMap::new();
+
+        for uuid in [uuid_a, uuid_b] {
+            assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+            assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+        }
+        assert_eq!(calls_a.load(Ordering::SeqCst), 1);
+        assert_eq!(calls_b.load(Ordering::SeqCst), 1);
+
+        parse_version_a.store(2, Ordering::SeqCst);
+        for uuid in [uuid_a, uuid_b] {
+            assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+        }
+        assert_eq!(calls_a.load(Ordering::SeqCst), 2);
+        assert_eq!(calls_b.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_missing_app_keys_skip_request() {
+        let github_uuid = "fd9b2602-62c5-4d55-bd1e-0d6537714ca0";
+        let mut server = mockito::Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/dupdatesystem/upgradeall/releases")
+            .with_status(200)
+            .with_body("[]")
+            .expect(1)
+            .create_async()
+            .await;
+        let proxy_url = format!("https://api.github.com -> {}", server.url());
+        let hub_data = BTreeMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        let options = CheckOptions {
+            bypass_cache: true,
+            bypass_proxy: false,
+        };
+
+        let app_data = BTreeMap::from([("owner", "DUpdateSystem")]);
+        assert_eq!(
+            get_missing_app_keys(github_uuid, &app_data, &hub_data),
+            Some(vec!["repo"])
+        );
+        let releases = get_releases_with_options(github_uuid, &app_data, &hub_data, &options);
+        assert_eq!(releases.await, None);
+        let result = call_func(
+            github_uuid,
+            &app_data,
+            &hub_data,
+            FunctionType::GetReleases,
+            &options,
+            provider::get_releases,
+        )
+        .await;
+        assert_eq!(result.unwrap_err().to_string(), "app_data lacks repo");
+        assert_eq!(
+            get_missing_app_keys("unknown-hub", &app_data, &hub_data),
+            None
+        );
+
+        let app_data = BTreeMap::from([("owner", "DUpdateSystem"), ("repo", "UpgradeAll")]);
+        assert_eq!(
+            get_missing_app_keys(github_uuid, &app_data, &hub_data),
+            Some(vec![])
+        );
+        let releases = get_releases_with_options(github_uuid, &app_data, &hub_data, &options);
+        assert_eq!(releases.await, Some(vec![]));
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_resolve_refs() {
+        let github_uuid = "fd9b2602-62c5-4d55-bd1e-0d6537714ca0";
+        let mut server = mockito::Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/dupdatesystem/upgradeall/releases")
+            .match_header("authorization", "Bearer from-file")
+            .with_status(200)
+            .with_body("[]")
+            .expect(1)
+            .create_async()
+            .await;
+        let proxy_url = format!("https://api.github.com -> {}", server.url());
+        let options = CheckOptions {
+            bypass_cache: true,
+            bypass_proxy: false,
+        };
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("token");
+        std::fs::write(&path, "from-file\n").unwrap();
+        std::env::set_var("GETTER_TEST_REPO", "UpgradeAll");
+
+        let token = format!("${{file:{}}}", path.display());
+        let app_data = BTreeMap::from([
+            ("owner", "DUpdateSystem"),
+            ("repo", "${env:GETTER_TEST_REPO}"),
+        ]);
+        let hub_data =
+            BTreeMap::from([(REVERSE_PROXY, proxy_url.as_str()), (TOKEN, token.as_str())]);
+        assert!(get_unresolved_refs(&app_data, &hub_data).is_empty());
+        let releases = get_releases_with_options(github_uuid, &app_data, &hub_data, &options);
+        assert_eq!(releases.await, Some(vec![]));
+
+        // An unresolved reference is never sent
+        let app_data = BTreeMap::from([
+            ("owner", "DUpdateSystem"),
+            ("repo", "${env:GETTER_TEST_REPO_UNSET}"),
+        ]);
+        assert_eq!(
+            get_unresolved_refs(&app_data, &hub_data),
+            vec!["UnresolvedRefError: repo refers to environment variable GETTER_TEST_REPO_UNSET, which is not set"]
+        );
+        let releases = get_releases_with_options(github_uuid, &app_data, &hub_data, &options);
+        assert_eq!(releases.await, None);
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_api_cache_key_per_function() {
+        let data_map = DataMap {
+            app_data: &BTreeMap::from([("id", "1")]),
+            hub_data: &BTreeMap::new(),
+        };
+        assert_ne!(
+            get_api_cache_key("uuid", &FunctionType::CheckAppAvailable, 0, &data_map),
+            get_api_cache_key("uuid", &FunctionType::GetReleases, 0, &data_map)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_store_is_not_cached() {
+        init_test_cache().await;
+
+        let uuid = "test-no-store";
+        let (calls, _) = add_count_provider(uuid, Some(CachePolicy::NoStore));
+        let app_data = BTreeMap::from([("id", "test_no_store")]);
+        let hub_data = BTreeMap::new();
+        assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+        assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let raw = get_cache_manager()
+            .await
+            .lock()
+            .await
+            .get(
+                &GroupType::RepoInside,
+                &get_hub_cache_key(uuid, "raw_test_no_store"),
+                None,
+            )
+            .await;
+        assert_eq!(raw, None);
+    }
+
+    #[tokio::test]
+    async fn test_save_cached_map_policy() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        cache_manager.set_local_cache_dir(temp_dir.path());
+        let cache_manager = Mutex::new(cache_manager);
+        let cached_map = HashMap::from([
+            ("long".to_string(), Bytes::from("long")),
+            ("short".to_string(), Bytes::from("short")),
+            ("default".to_string(), Bytes::from("default")),
+        ]);
+        let cache_policy_map = HashMap::from([
+            ("long".to_string(), CachePolicy::MaxAge(600)),
+            ("short".to_string(), CachePolicy::MaxAge(60)),
+        ]);
+        let policy = save_cached_map(
+            &cache_manager,
+            "hub",
+            cached_map,
+            Some(cache_policy_map),
+            None,
+        )
+        .await;
+        assert_eq!(policy, Some(CachePolicy::MaxAge(60)));
+        for key in ["hub/long", "hub/short", "hub/default"] {
+            assert!(cache_manager
+                .lock()
+                .await
+                .get(&GroupType::RepoInside, key, None)
+                .await
+                .is_some());
+        }
+
+        let cached_map = HashMap::from([
+            ("private".to_string(), Bytes::from("private")),
+            ("public".to_string(), Bytes::from("public")),
+        ]);
+        let cache_policy_map = HashMap::from([
+            ("private".to_string(), CachePolicy::NoStore),
+            ("public".to_string(), CachePolicy::MaxAge(60)),
+        ]);
+        let policy = save_cached_map(
+            &cache_manager,
+            "hub",
+            cached_map,
+            Some(cache_policy_map),
+            None,
+        )
+        .await;
+        assert_eq!(policy, Some(CachePolicy::NoStore));
+        let mut cache_manager = cache_manager.lock().await;
+        assert!(cache_manager
+            .get(&GroupType::RepoInside, "hub/private", None)
+            .await
+            .is_none());
+        assert!(cache_manager
+            .get(&GroupType::RepoInside, "hub/public", None)
+            .await
+            .is_some());
+    }
+
+    struct SlowProvider {
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl BaseProviderExt for SlowProvider {}
+
+    #[async_trait]
+    impl BaseProvider for SlowProvider {
+        fn required_app_keys(&self) -> &'static [&'static str] {
+            &["id"]
+        }
+
+        fn get_cache_request_key(
+            &self,
+            _function_type: &FunctionType,
+            _data_map: &DataMap,
+        ) -> Vec<String> {
+            vec![]
+        }
+
+        async fn check_app_available(&self, _fin: &FIn) -> FOut<bool> {
+            FOut::new(true)
+        }
+
+        async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            let id = fin.data_map.app_data["id"];
+            match id {
+                "failing" => {
+                    return FOut::new_empty()
+                        .set_error(Box::new(std::io::Error::other("rate limited")))
+                }
+                "empty" => return FOut::new(vec![]),
+                _ => (),
+            }
+            FOut::new(vec![ReleaseData {
+                version_number: id.to_string(),
+                changelog: "".to_string(),
+                assets: vec![],
+                extra: None,
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_releases() {
+        let uuid = "test-get-latest-releases";
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        add_provider(
+            uuid,
+            SlowProvider {
+                in_flight: Arc::new(AtomicUsize::new(0)),
+                max_in_flight: max_in_flight.clone(),
+            },
+        );
+        let query = |hub_uuid: &str, app_data: &[(&str, &str)]| AppQuery {
+            hub_uuid: hub_uuid.to_string(),
+            app_data: app_data
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            hub_data: BTreeMap::new(),
+        };
+        let mut apps = (0..5)
+            .map(|i| query(uuid, &[("id", &format!("1.{}.0", i))]))
+            .collect::<Vec<_>>();
+        apps.push(query(uuid, &[("id", "failing")]));
+        apps.push(query(uuid, &[("id", "empty")]));
+        apps.push(query(uuid, &[]));
+        apps.push(query(
+            "test-get-latest-releases-unknown",
+            &[("id", "1.0.0")],
+        ));
+        let options = CheckOptions {
+            bypass_cache: true,
+            ..Default::default()
+        };
+
+        let results = get_latest_releases(apps, 2, options).await;
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+        let versions = results
+            .iter()
+            .map(|result| result.release.as_ref().map(|r| r.version_number.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            versions[..5],
+            [
+                Some("1.0.0"),
+                Some("1.1.0"),
+                Some("1.2.0"),
+                Some("1.3.0"),
+                Some("1.4.0")
+            ]
+        );
+        let errors = results[5..]
+            .iter()
+            .map(|result| (result.release.is_none(), result.error.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                (true, Some("rate limited")),
+                (true, Some("no release found")),
+                (true, Some("app_data lacks id")),
+                (true, Some("hub test-get-latest-releases-unknown not found")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bypass_cache() {
+        init_test_cache().await;
+
+        let uuid = "test-bypass-cache";
+        let (calls, _) = add_count_provider(uuid, None);
+        let app_data = BTreeMap::from([("id", "test_bypass_cache")]);
+        let hub_data = BTreeMap::new();
+        let options = CheckOptions {
+            bypass_cache: true,
+            ..Default::default()
+        };
+        assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+        assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(
+            get_releases_with_options(uuid, &app_data, &hub_data, &options)
+                .await
+                .is_some()
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_repo_path_spellings_share_cache() {
+        init_test_cache().await;
+
+        let github_uuid = "fd9b2602-62c5-4d55-bd1e-0d6537714ca0";
+        let mut server = mockito::Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/spellingtest/upgradeall/releases")
+            .with_status(200)
+            .with_body("[]")
+            .expect(1)
+            .create_async()
+            .await;
+        let proxy_url = format!("https://api.github.com -> {}", server.url());
+        let hub_data = BTreeMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        for (owner, repo) in [
+            ("SpellingTest", "UpgradeAll"),
+            (" spellingtest ", "UpgradeAll.git/"),
+            ("SpellingTest", "upgradeall%2F"),
+        ] {
+            let app_data = BTreeMap::from([("owner", owner), ("repo", repo)]);
+            assert_eq!(
+                get_releases(github_uuid, &app_data, &hub_data).await,
+                Some(vec![])
+            );
+        }
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_raw_responses_shared_across_functions() {
+        init_test_cache().await;
+
+        let github_uuid = "fd9b2602-62c5-4d55-bd1e-0d6537714ca0";
+        let body = std::fs::read_to_string("tests/files/web/github_api_release.json").unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/rawcachetest/upgradeall/releases")
+            .with_status(200)
+            .with_body(body)
+            .expect(1)
+            .create_async()
+            .await;
+        let proxy_url = format!("https://api.github.com -> {}", server.url());
+        let hub_data = BTreeMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        let app_data = BTreeMap::from([("owner", "RawCacheTest"), ("repo", "UpgradeAll")]);
+        // Each function caches its own result, but the release list behind
+        // them is fetched once under the provider's cache request key
+        let releases = get_releases(github_uuid, &app_data, &hub_data)
+            .await
+            .unwrap();
+        let latest = get_latest_release(github_uuid, &app_data, &hub_data)
+            .await
+            .unwrap();
+        assert_eq!(latest, releases[0]);
+        assert_eq!(
+            get_releases(github_uuid, &app_data, &hub_data).await,
+            Some(releases)
+        );
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_search_apps() {
+        init_test_cache().await;
+
+        let fdroid_uuid = "6a6d590b-1809-41bf-8ce3-7e3f6c8da945";
+        let index = "<fdroid><application id=\"org.example.notes\"><id>org.example.notes</id>\
+            <name>Notes</name><summary>Take notes</summary><package><version>1.2</version>\
+            <versioncode>12</versioncode><apkname>org.example.notes_12.apk</apkname>\
+            </package></application></fdroid>";
+        let mut server = mockito::Server::new_async().await;
+        let m = server
+            .mock("GET", "/repo/index.xml")
+            .with_status(200)
+            .with_body(index)
+            .expect(1)
+            .create_async()
+            .await;
+        let repo_url = server.url();
+        let hub_data = BTreeMap::from([("repo_url", repo_url.as_str())]);
+        let results = search_apps(fdroid_uuid, &hub_data, "note").await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "Notes");
+        // The index the search fetched is the one release checks read
+        let app_data = crate::utils::convert::convert_btreemap(&results[0].app_data);
+        let releases = get_releases(fdroid_uuid, &app_data, &hub_data)
+            .await
+            .unwrap();
+        assert_eq!(releases[0].version_number, "1.2");
+        m.assert_async().await;
+
+        let lsposed_uuid = "401e6259-2eab-46f0-8e8a-d2bfafedf5bf";
+        let error = search_apps(lsposed_uuid, &hub_data, "note")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("SearchNotSupportedError"));
+        assert!(search_apps("unknown-hub", &hub_data, "note").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bypass_proxy() {
+        init_test_cache().await;
+
+        let uuid = "test-bypass-proxy";
+        add_count_provider(uuid, None);
+        let app_data = BTreeMap::from([("id", "test_bypass_proxy")]);
+        let hub_data = BTreeMap::from([(
+            REVERSE_PROXY,
+            "https://origin.example -> https://proxy.example",
+        )]);
+        let release = get_latest_release(uuid, &app_data, &hub_data)
+            .await
+            .unwrap();
+        assert_eq!(release.changelog, "https://proxy.example/releases");
+        let options = CheckOptions {
+            bypass_proxy: true,
+            ..Default::default()
+        };
+        let release = get_latest_release_with_options(uuid, &app_data, &hub_data, &options)
+            .await
+            .unwrap();
+        assert_eq!(release.changelog, "https://origin.example/releases");
+    }
+
+    #[tokio::test]
+    async fn test_clear_cache_for_hub() {
+        init_test_cache().await;
+
+        let uuid_a = "test-clear-hub-a";
+        let uuid_b = "test-clear-hub-b";
+        let (calls_a, _) = add_count_provider(uuid_a, None);
+        let (calls_b, _) = add_count_provider(uuid_b, None);
+        let app_data = BTreeMap::from([("id", "test_clear_hub")]);
+        let hub_data = BTreeMap::new();
+        for uuid in [uuid_a, uuid_b] {
+            assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+        }
+
+        clear_cache_for_hub(uuid_a).await.unwrap();
+        for uuid in [uuid_a, uuid_b] {
+            assert!(get_releases(uuid, &app_data, &hub_data).await.is_some());
+        }
+        assert_eq!(calls_a.load(Ordering::SeqCst), 2);
+        assert_eq!(calls_b.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_clear_cache_for_app() {
+        init_test_cache().await;
+
+        let uuid = "test-clear-app";
+        let (calls, _) = add_count_provider(uuid, None);
+        let app_data_1 = BTreeMap::from([("id", "test_clear_app_1")]);
+        let app_data_2 = BTreeMap::from([("id", "test_clear_app_2")]);
+        let hub_data = BTreeMap::new();
+        for app_data in [&app_data_1, &app_data_2] {
+            assert!(get_releases(uuid, app_data, &hub_data).await.is_some());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        clear_cache_for_app(uuid, &app_data_1, &hub_data).await;
+        let raw = get_cache_manager()
+            .await
+            .lock()
+            .await
+            .get(
+                &GroupType::RepoInside,
+                &get_hub_cache_key(uuid, "raw_test_clear_app_1"),
+                None,
+            )
+            .await;
+        assert_eq!(raw, None);
+        for app_data in [&app_data_1, &app_data_2] {
+            assert!(get_releases(uuid, app_data, &hub_data).await.is_some());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_clear_cache_for_hub_stays_in_cache_dir() {
+        init_test_cache().await;
+
+        let victim = tempfile::tempdir().unwrap();
+        let victim_file = victim.path().join("keep");
+        std::fs::write(&victim_file, "keep").unwrap();
+        let relative = pathdiff(victim.path(), TEST_CACHE_DIR.path());
+        for uuid in [
+            format!("../../{}", relative),
+            format!("x/../../../{}", relative),
+        ] {
+            clear_cache_for_hub(&uuid).await.unwrap();
+        }
+        assert!(victim_file.exists());
+        assert!(clear_cache_for_hub("").await.is_err());
+
+        assert_eq!(get_hub_cache_key("../a/b", "key"), "%2E%2E%2Fa%2Fb/key");
+        assert_eq!(
+            get_hub_cache_key("fd9b2602-62c5-4d55-bd1e-0d6537714ca0", "key"),
+            "fd9b2602-62c5-4d55-bd1e-0d6537714ca0/key"
+        );
+    }
+
+    // `path` relative to `base`, both absolute
+    fn pathdiff(path: &std::path::Path, base: &std::path::Path) -> String {
+        let depth = base.components().count() - 1;
+        let mut relative = "../".repeat(depth);
+        relative.push_str(path.to_str().unwrap().trim_start_matches('/'));
+        relative
+    }
+}
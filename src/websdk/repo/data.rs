@@ -1 +1,2 @@
 pub mod release;
+pub mod search;
@@ -1,5 +1,67 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+
+use crate::utils::versioning::VersionNormalizer;
+
+pub const RAW_VERSION_KEY: &str = "raw_version";
+
+/// Well-known `extra` keys that git forge providers fill in when the
+/// release carries them, so clients can show the tag and link to the page.
+pub const TAG_NAME_KEY: &str = "tag_name";
+pub const RELEASE_URL_KEY: &str = "html_url";
+pub const TARGET_COMMITISH_KEY: &str = "target_commitish";
+pub const COMMIT_SHA_KEY: &str = "commit_sha";
+
+/// `extra` key of how many assets the asset filter left out of a release.
+pub const FILTERED_ASSETS_KEY: &str = "filtered_assets";
+
+/// `AssetData::extra` key of how often the asset was downloaded.
+pub const DOWNLOAD_COUNT_KEY: &str = "download_count";
+
+/// Picks the one asset of a release to download.
+#[derive(Debug, Clone, Default)]
+pub struct AssetSelector {
+    /// Only assets whose file name matches are picked
+    pub file_name: Option<Regex>,
+    /// Preferred among the matches when their file name names this ABI
+    pub preferred_abi: Option<String>,
+}
+
+/// No asset of a release passes the [`AssetSelector`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoMatchingAssetError {
+    pub version_number: String,
+    /// File names of the release's assets
+    pub available: Vec<String>,
+}
+
+impl fmt::Display for NoMatchingAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "NoMatchingAssetError: no asset of {} matches, available: ",
+            self.version_number
+        )?;
+        if self.available.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", self.available.join(", "))
+        }
+    }
+}
+
+impl std::error::Error for NoMatchingAssetError {}
+
+/// Whether `file_name` names `abi` as a whole word, so that e.g. "x86"
+/// doesn't match "x86_64".
+fn names_abi(file_name: &str, abi: &str) -> bool {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    file_name.match_indices(abi).any(|(i, _)| {
+        !file_name[..i].ends_with(is_word) && !file_name[i + abi.len()..].starts_with(is_word)
+    })
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ReleaseData {
@@ -9,9 +71,193 @@ pub struct ReleaseData {
     pub extra: Option<HashMap<String, String>>,
 }
 
+impl ReleaseData {
+    /// Rewrites `version_number`, keeping the original in `extra["raw_version"]`.
+    pub fn normalize_version(&mut self, normalizer: &VersionNormalizer) {
+        let version_number = normalizer.normalize(&self.version_number);
+        if version_number != self.version_number {
+            let raw_version = std::mem::replace(&mut self.version_number, version_number);
+            self.extra
+                .get_or_insert_with(HashMap::new)
+                .insert(RAW_VERSION_KEY.to_string(), raw_version);
+        }
+    }
+
+    /// Drops the assets `keeps` rejects, counting them in
+    /// `extra["filtered_assets"]`.
+    pub fn filter_assets(&mut self, keeps: impl Fn(&AssetData) -> bool) {
+        let count = self.assets.len();
+        self.assets.retain(|asset| keeps(asset));
+        let filtered = count - self.assets.len();
+        if filtered > 0 {
+            self.extra
+                .get_or_insert_with(HashMap::new)
+                .insert(FILTERED_ASSETS_KEY.to_string(), filtered.to_string());
+        }
+    }
+
+    /// The first asset, in asset order, that `selector` matches, unless a
+    /// later match names the preferred ABI.
+    pub fn select_asset(
+        &self,
+        selector: &AssetSelector,
+    ) -> Result<&AssetData, NoMatchingAssetError> {
+        let mut matches = self.assets.iter().filter(|asset| {
+            selector
+                .file_name
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(&asset.file_name))
+        });
+        let first = matches.clone().next();
+        let preferred = selector
+            .preferred_abi
+            .as_ref()
+            .and_then(|abi| matches.find(|asset| names_abi(&asset.file_name, abi)));
+        preferred.or(first).ok_or_else(|| NoMatchingAssetError {
+            version_number: self.version_number.clone(),
+            available: self
+                .assets
+                .iter()
+                .map(|asset| asset.file_name.clone())
+                .collect(),
+        })
+    }
+}
+
+/// Collapses releases with the same version number into the first one,
+/// which gains any assets of the later ones it doesn't have by name.
+pub fn dedup_releases(releases: Vec<ReleaseData>) -> Vec<ReleaseData> {
+    let mut result: Vec<ReleaseData> = Vec::with_capacity(releases.len());
+    for release in releases {
+        if let Some(kept) = result
+            .iter_mut()
+            .find(|kept| kept.version_number == release.version_number)
+        {
+            for asset in release.assets {
+                if !kept.assets.iter().any(|a| a.file_name == asset.file_name) {
+                    kept.assets.push(asset);
+                }
+            }
+        } else {
+            result.push(release);
+        }
+    }
+    result
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AssetData {
     pub file_name: String,
     pub file_type: String,
     pub download_url: String,
+    /// Size in bytes, when the provider lists it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Provider-specific details, e.g. `download_count`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<HashMap<String, String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version_number: &str, assets: &[&str]) -> ReleaseData {
+        ReleaseData {
+            version_number: version_number.to_string(),
+            changelog: "".to_string(),
+            assets: assets
+                .iter()
+                .map(|file_name| AssetData {
+                    file_name: file_name.to_string(),
+                    file_type: "".to_string(),
+                    download_url: "".to_string(),
+                    size: None,
+                    extra: None,
+                })
+                .collect(),
+            extra: None,
+        }
+    }
+
+    #[test]
+    fn test_dedup_releases() {
+        let releases = dedup_releases(vec![
+            release("1.0.0", &["a.apk"]),
+            release("0.9.0", &["a.apk"]),
+            release("1.0.0", &["a.apk", "b.apk"]),
+            release("1.0.0", &["c.apk"]),
+        ]);
+        assert_eq!(
+            releases,
+            vec![
+                release("1.0.0", &["a.apk", "b.apk", "c.apk"]),
+                release("0.9.0", &["a.apk"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_asset() {
+        let release = release(
+            "1.0.0",
+            &[
+                "app-x86_64-release.apk",
+                "app-x86-release.apk",
+                "app-arm64-v8a-release.apk",
+                "app-universal-release.apk",
+                "checksums.txt",
+            ],
+        );
+        let select = |file_name: Option<&str>, preferred_abi: Option<&str>| {
+            let selector = AssetSelector {
+                file_name: file_name.map(|regex| Regex::new(regex).unwrap()),
+                preferred_abi: preferred_abi.map(str::to_string),
+            };
+            release
+                .select_asset(&selector)
+                .map(|asset| asset.file_name.as_str())
+        };
+        assert_eq!(select(None, None), Ok("app-x86_64-release.apk"));
+        assert_eq!(
+            select(Some(r"universal.*\.apk$"), None),
+            Ok("app-universal-release.apk")
+        );
+        assert_eq!(select(None, Some("x86")), Ok("app-x86-release.apk"));
+        assert_eq!(
+            select(Some(r"\.apk$"), Some("arm64-v8a")),
+            Ok("app-arm64-v8a-release.apk")
+        );
+        // No asset for the ABI: the first match still goes
+        assert_eq!(
+            select(Some(r"\.apk$"), Some("armeabi-v7a")),
+            Ok("app-x86_64-release.apk")
+        );
+
+        let error = select(Some(r"\.aab$"), None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "NoMatchingAssetError: no asset of 1.0.0 matches, available: \
+             app-x86_64-release.apk, app-x86-release.apk, app-arm64-v8a-release.apk, \
+             app-universal-release.apk, checksums.txt"
+        );
+    }
+
+    #[test]
+    fn test_asset_optional_fields() {
+        // Cached results and clients from before size and extra still parse
+        let json = r#"{"file_name":"a.apk","file_type":"apk","download_url":"https://a/a.apk"}"#;
+        let asset = serde_json::from_str::<AssetData>(json).unwrap();
+        assert_eq!(asset.size, None);
+        assert_eq!(asset.extra, None);
+        assert_eq!(serde_json::to_string(&asset).unwrap(), json);
+
+        let asset = AssetData {
+            size: Some(4096),
+            ..asset
+        };
+        assert!(serde_json::to_string(&asset)
+            .unwrap()
+            .contains(r#""size":4096"#));
+    }
 }
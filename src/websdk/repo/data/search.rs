@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// An app found on a hub by a search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResultData {
+    pub name: String,
+    pub description: String,
+    /// The app_data that tracks the app on the hub searched
+    pub app_data: BTreeMap<String, String>,
+}
@@ -1,56 +1,229 @@
 pub mod base_provider;
+#[cfg(any(test, feature = "test-util"))]
+pub mod conformance;
+pub mod direct_url;
 pub mod fdroid;
+pub mod fdroid_repo;
 pub mod github;
 pub mod gitlab;
+pub mod google_play;
 pub mod lsposed_repo;
 pub mod outside_rpc;
+#[cfg(feature = "shell")]
+pub mod shell;
 
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, PoisonError, RwLock};
 
-use self::base_provider::{BaseProvider, DataMap, FIn, FOut, FunctionType};
+use self::base_provider::{
+    BaseProvider, DataMap, FIn, FOut, FunctionType, AWAITING_ASSETS_EXPIRE_TIME, SOURCE_ONLY,
+};
+use self::direct_url::DirectUrlProvider;
 use self::fdroid::FDroidProvider;
+use self::fdroid_repo::FDroidRepoProvider;
 use self::github::GitHubProvider;
 use self::gitlab::GitLabProvider;
+use self::google_play::GooglePlayProvider;
 use self::lsposed_repo::LsposedRepoProvider;
-use super::data::release::ReleaseData;
+#[cfg(feature = "shell")]
+use self::shell::ShellProvider;
+use super::data::release::{dedup_releases, ReleaseData};
+use super::data::search::SearchResultData;
+use crate::utils::convert::percent_decode;
+use crate::utils::http::{stricter_cache_policy, CachePolicy};
 
 type ProviderMap = HashMap<&'static str, Arc<dyn BaseProvider + Send + Sync>>;
 
+type ProviderConstructor = fn() -> Arc<dyn BaseProvider + Send + Sync>;
+
+fn builtin_providers() -> Vec<(&'static str, ProviderConstructor)> {
+    #[allow(unused_mut)]
+    let mut providers: Vec<(&'static str, ProviderConstructor)> = vec![
+        ("fd9b2602-62c5-4d55-bd1e-0d6537714ca0", || {
+            Arc::new(GitHubProvider::new())
+        }),
+        ("6a6d590b-1809-41bf-8ce3-7e3f6c8da945", || {
+            Arc::new(FDroidProvider::new())
+        }),
+        ("a84e2fbe-1478-4db5-80ae-75d00454c7eb", || {
+            Arc::new(GitLabProvider::new())
+        }),
+        ("401e6259-2eab-46f0-8e8a-d2bfafedf5bf", || {
+            Arc::new(LsposedRepoProvider::new())
+        }),
+        (direct_url::DIRECT_URL_HUB_UUID, || {
+            Arc::new(DirectUrlProvider::new())
+        }),
+        (fdroid_repo::FDROID_REPO_HUB_UUID, || {
+            Arc::new(FDroidRepoProvider::new())
+        }),
+        (google_play::GOOGLE_PLAY_HUB_UUID, || {
+            Arc::new(GooglePlayProvider::new())
+        }),
+    ];
+    #[cfg(feature = "shell")]
+    providers.push((shell::SHELL_HUB_UUID, || Arc::new(ShellProvider::new())));
+    providers
+}
+
+// Both locks recover from poisoning: a panic elsewhere while one was held
+// leaves the map itself intact, so it shouldn't take every later lookup down
 static PROVIDER_MAP: Lazy<Arc<RwLock<ProviderMap>>> = Lazy::new(|| {
-    Arc::new(RwLock::new(HashMap::from([
-        (
-            "fd9b2602-62c5-4d55-bd1e-0d6537714ca0",
-            Arc::new(GitHubProvider::new()) as Arc<dyn BaseProvider + Send + Sync>,
-        ),
-        (
-            "6a6d590b-1809-41bf-8ce3-7e3f6c8da945",
-            Arc::new(FDroidProvider::new()) as Arc<dyn BaseProvider + Send + Sync>,
-        ),
-        (
-            "a84e2fbe-1478-4db5-80ae-75d00454c7eb",
-            Arc::new(GitLabProvider::new()) as Arc<dyn BaseProvider + Send + Sync>,
-        ),
-        (
-            "401e6259-2eab-46f0-8e8a-d2bfafedf5bf",
-            Arc::new(LsposedRepoProvider::new()) as Arc<dyn BaseProvider + Send + Sync>,
-        ),
-    ])))
+    let mut map = ProviderMap::new();
+    for (uuid, new) in builtin_providers() {
+        if let Some(provider) = construct_provider(uuid, new) {
+            map.insert(uuid, provider);
+        }
+    }
+    Arc::new(RwLock::new(map))
 });
 
+static SKIPPED_PROVIDERS: Lazy<RwLock<Vec<(String, String)>>> = Lazy::new(|| RwLock::new(vec![]));
+
+/// Runs a provider constructor, recording the hub as skipped if it panics.
+fn construct_provider<P>(uuid: &str, new: impl FnOnce() -> P) -> Option<P> {
+    match panic::catch_unwind(AssertUnwindSafe(new)) {
+        Ok(provider) => Some(provider),
+        Err(e) => {
+            let error = e
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| e.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "constructor panicked".to_string());
+            SKIPPED_PROVIDERS
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((uuid.to_string(), error));
+            None
+        }
+    }
+}
+
 fn get_provider(uuid: &str) -> Option<Arc<dyn BaseProvider + Send + Sync>> {
-    let map = PROVIDER_MAP.read().unwrap();
+    let map = PROVIDER_MAP.read().unwrap_or_else(PoisonError::into_inner);
     map.get(uuid).cloned()
 }
 
 pub fn add_provider(uuid: &str, provider: impl BaseProvider + Send + Sync + 'static) {
-    let mut map = PROVIDER_MAP.write().unwrap();
+    add_provider_with(uuid, move || provider);
+}
+
+/// Registers the provider `new` builds. If it panics, the hub is left out
+/// and listed by `get_skipped_providers`; returns whether it was added.
+#[allow(dead_code)]
+pub fn add_provider_with<P>(uuid: &str, new: impl FnOnce() -> P) -> bool
+where
+    P: BaseProvider + Send + Sync + 'static,
+{
+    let Some(provider) = construct_provider(uuid, new) else {
+        return false;
+    };
+    let mut map = PROVIDER_MAP.write().unwrap_or_else(PoisonError::into_inner);
     let uuid: &'static str = Box::leak(Box::new(uuid.to_string()));
     map.insert(
         uuid,
         Arc::new(provider) as Arc<dyn BaseProvider + Send + Sync>,
     );
+    true
+}
+
+/// Hubs whose provider failed to construct, with the panic message.
+pub fn get_skipped_providers() -> Vec<(String, String)> {
+    SKIPPED_PROVIDERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Default expiry of releases whose assets may still be uploading.
+const AWAITING_ASSETS_DEFAULT_EXPIRE_TIME: u64 = 15 * 60;
+
+/// Recheck the release responses early when the latest release has no
+/// assets yet: projects often publish a release before uploading its
+/// binaries. Apps marked `source_only` never get any and are left alone.
+fn recheck_awaiting_assets<T>(
+    provider: &(dyn BaseProvider + Send + Sync),
+    data_map: &DataMap,
+    latest_has_assets: bool,
+    mut fout: FOut<T>,
+) -> FOut<T> {
+    if latest_has_assets || data_map.app_data.get(SOURCE_ONLY) == Some(&"true") {
+        return fout;
+    }
+    let expire_time = data_map
+        .app_data
+        .get(AWAITING_ASSETS_EXPIRE_TIME)
+        .or_else(|| data_map.hub_data.get(AWAITING_ASSETS_EXPIRE_TIME))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(AWAITING_ASSETS_DEFAULT_EXPIRE_TIME);
+    for key in provider.get_cache_request_key(&FunctionType::GetReleases, data_map) {
+        let policy = fout
+            .cache_policy_map
+            .as_ref()
+            .and_then(|map| map.get(&key).copied());
+        fout = fout.set_cache_policy(
+            &key,
+            stricter_cache_policy(policy, Some(CachePolicy::Recheck(expire_time))),
+        );
+    }
+    fout
+}
+
+/// Registered providers by hub uuid, with their parse versions.
+pub fn get_provider_list() -> Vec<(&'static str, u32)> {
+    let map = PROVIDER_MAP.read().unwrap_or_else(PoisonError::into_inner);
+    let mut providers = map
+        .iter()
+        .map(|(uuid, provider)| (*uuid, provider.get_parse_version()))
+        .collect::<Vec<_>>();
+    providers.sort();
+    providers
+}
+
+pub fn get_parse_version(uuid: &str) -> Option<u32> {
+    get_provider(uuid).map(|provider| provider.get_parse_version())
+}
+
+/// Required app_data keys of the hub's provider that `data_map` lacks.
+pub fn get_missing_app_keys(uuid: &str, data_map: &DataMap) -> Option<Vec<&'static str>> {
+    get_provider(uuid).map(|provider| {
+        provider
+            .required_app_keys()
+            .iter()
+            .filter(|key| data_map.get_app_value(key).is_err())
+            .copied()
+            .collect()
+    })
+}
+
+/// Rewrites the provider's repo path values in `app_values` to their
+/// canonical form.
+pub fn canonicalize_app_data(uuid: &str, app_values: &mut BTreeMap<&str, String>) {
+    let Some(provider) = get_provider(uuid) else {
+        return;
+    };
+    for key in provider.repo_path_keys() {
+        let Some(value) = app_values.get_mut(key) else {
+            continue;
+        };
+        let decoded = percent_decode(value.trim());
+        let mut canonical = decoded.as_deref().unwrap_or(value.trim()).trim();
+        loop {
+            let stripped = canonical.trim_end_matches('/');
+            let stripped = stripped.strip_suffix(".git").unwrap_or(stripped);
+            if stripped == canonical {
+                break;
+            }
+            canonical = stripped;
+        }
+        *value = if provider.repo_path_case_insensitive() {
+            canonical.to_lowercase()
+        } else {
+            canonical.to_string()
+        };
+    }
 }
 
 pub fn get_cache_request_key(
@@ -61,6 +234,18 @@ pub fn get_cache_request_key(
     get_provider(uuid).map(|provider| provider.get_cache_request_key(function_type, data_map))
 }
 
+pub fn get_search_cache_request_key(uuid: &str, data_map: &DataMap) -> Option<Vec<String>> {
+    get_provider(uuid).map(|provider| provider.get_search_cache_request_key(data_map))
+}
+
+pub async fn search_apps<'a>(
+    uuid: &str,
+    fin: &FIn<'a>,
+    query: &str,
+) -> Option<FOut<Vec<SearchResultData>>> {
+    Some(get_provider(uuid)?.search_apps(fin, query).await)
+}
+
 pub async fn check_app_available<'a>(uuid: &str, fin: &FIn<'a>) -> Option<FOut<bool>> {
     if let Some(provider) = get_provider(uuid) {
         Some(provider.check_app_available(fin).await)
@@ -70,17 +255,435 @@ pub async fn check_app_available<'a>(uuid: &str, fin: &FIn<'a>) -> Option<FOut<b
 }
 
 pub async fn get_latest_release<'a>(uuid: &str, fin: &FIn<'a>) -> Option<FOut<ReleaseData>> {
-    if let Some(provider) = get_provider(uuid) {
-        Some(provider.get_latest_release(fin).await)
-    } else {
-        None
+    let provider = get_provider(uuid)?;
+    let normalizer = match fin.data_map.get_version_normalizer() {
+        Ok(normalizer) => normalizer,
+        Err(e) => return Some(FOut::new_empty().set_error(Box::new(e))),
+    };
+    let asset_filter = match fin.data_map.get_asset_filter() {
+        Ok(asset_filter) => asset_filter,
+        Err(e) => return Some(FOut::new_empty().set_error(Box::new(e))),
+    };
+    let mut fout = provider.get_latest_release(fin).await;
+    if let Ok(release) = &mut fout.result {
+        if let Some(normalizer) = normalizer {
+            release.normalize_version(&normalizer);
+        }
+        if let Some(asset_filter) = &asset_filter {
+            release.filter_assets(|asset| asset_filter.keeps(&asset.file_name));
+        }
     }
+    let has_assets = !matches!(&fout.result, Ok(release) if release.assets.is_empty());
+    Some(recheck_awaiting_assets(
+        provider.as_ref(),
+        &fin.data_map,
+        has_assets,
+        fout,
+    ))
 }
 
 pub async fn get_releases<'a>(uuid: &str, fin: &FIn<'a>) -> Option<FOut<Vec<ReleaseData>>> {
-    if let Some(provider) = get_provider(uuid) {
-        Some(provider.get_releases(fin).await)
-    } else {
-        None
+    let provider = get_provider(uuid)?;
+    let normalizer = match fin.data_map.get_version_normalizer() {
+        Ok(normalizer) => normalizer,
+        Err(e) => return Some(FOut::new_empty().set_error(Box::new(e))),
+    };
+    let asset_filter = match fin.data_map.get_asset_filter() {
+        Ok(asset_filter) => asset_filter,
+        Err(e) => return Some(FOut::new_empty().set_error(Box::new(e))),
+    };
+    let mut fout = provider.get_releases(fin).await;
+    if let Ok(releases) = &mut fout.result {
+        for release in releases.iter_mut() {
+            if let Some(normalizer) = &normalizer {
+                release.normalize_version(normalizer);
+            }
+            if let Some(asset_filter) = &asset_filter {
+                release.filter_assets(|asset| asset_filter.keeps(&asset.file_name));
+            }
+        }
+        if provider.merges_duplicate_versions() {
+            *releases = dedup_releases(std::mem::take(releases));
+        }
+    }
+    let has_assets = !matches!(
+        &fout.result,
+        Ok(releases) if releases.first().is_some_and(|release| release.assets.is_empty())
+    );
+    Some(recheck_awaiting_assets(
+        provider.as_ref(),
+        &fin.data_map,
+        has_assets,
+        fout,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::data::release::{FILTERED_ASSETS_KEY, RAW_VERSION_KEY};
+    use super::base_provider::*;
+    use super::*;
+    use async_trait::async_trait;
+
+    struct TagProvider;
+
+    #[async_trait]
+    impl BaseProvider for TagProvider {
+        fn get_cache_request_key(
+            &self,
+            _function_type: &FunctionType,
+            _data_map: &DataMap,
+        ) -> Vec<String> {
+            vec![]
+        }
+
+        async fn check_app_available(&self, _fin: &FIn) -> FOut<bool> {
+            FOut::new(true)
+        }
+
+        async fn get_releases(&self, _fin: &FIn) -> FOut<Vec<ReleaseData>> {
+            FOut::new(
+                ["app_v1.2.3_build45", "1.2.2"]
+                    .iter()
+                    .map(|version| ReleaseData {
+                        version_number: version.to_string(),
+                        changelog: "".to_string(),
+                        assets: vec![],
+                        extra: None,
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_normalize_version() {
+        let uuid = "test-normalize-version";
+        add_provider(uuid, TagProvider);
+        let app_data = AppDataMap::from([(VERSION_EXTRACT, r"_v([\d.]+)_")]);
+        let hub_data = HubDataMap::new();
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let releases = get_releases(uuid, &fin).await.unwrap().result.unwrap();
+        assert_eq!(releases[0].version_number, "1.2.3");
+        assert_eq!(
+            releases[0].extra.as_ref().unwrap()[RAW_VERSION_KEY],
+            "app_v1.2.3_build45"
+        );
+        assert_eq!(releases[1].version_number, "1.2.2");
+        assert_eq!(releases[1].extra, None);
+
+        let release = get_latest_release(uuid, &fin)
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        assert_eq!(release.version_number, "1.2.3");
+
+        let app_data = AppDataMap::from([(VERSION_EXTRACT, "(unclosed")]);
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        assert!(get_releases(uuid, &fin).await.unwrap().result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_dedup() {
+        let body =
+            std::fs::read_to_string("tests/files/web/github_api_release_duplicate.json").unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let _m = server
+            .mock("GET", "/repos/DUpdateSystem/UpgradeAll/releases")
+            .with_status(200)
+            .with_body(body)
+            .create();
+
+        let app_data = AppDataMap::from([("owner", "DUpdateSystem"), ("repo", "UpgradeAll")]);
+        let proxy_url = format!("{} -> {}", github::GITHUB_API_URL, server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let releases = get_releases("fd9b2602-62c5-4d55-bd1e-0d6537714ca0", &fin)
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        assert_eq!(releases.len(), 2);
+        assert_eq!(releases[0].version_number, "1.0.0");
+        assert_eq!(releases[0].changelog, "Release 1.0.0");
+        let assets = releases[0]
+            .assets
+            .iter()
+            .map(|asset| asset.file_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            assets,
+            vec!["UpgradeAll_1.0.0.apk", "UpgradeAll_1.0.0-arm64.apk"]
+        );
+        assert_eq!(releases[1].version_number, "0.9.0");
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_fdroid_keeps_abi_packages() {
+        let body = std::fs::read_to_string("tests/files/web/f-droid.xml").unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let _m = server
+            .mock("GET", "/repo/index.xml")
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let app_data = AppDataMap::from([(ANDROID_APP_TYPE, "io.github.divverent.aaaaxy")]);
+        let proxy_url = format!("https://f-droid.org -> {}", server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let releases = get_releases("6a6d590b-1809-41bf-8ce3-7e3f6c8da945", &fin)
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        // One release per ABI build, each with its own APK
+        assert_eq!(releases.len(), 12);
+        assert!(releases[..4]
+            .iter()
+            .all(|release| release.version_number == "1.5.6+20240219.3325.eaf4bbab"));
+        let abis = releases[..4]
+            .iter()
+            .map(|release| release.extra.as_ref().unwrap()["nativecode"].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(abis, vec!["arm64-v8a", "armeabi-v7a", "x86_64", "x86"]);
+        assert!(releases[..4]
+            .iter()
+            .all(|release| release.assets.len() == 1));
+        assert_eq!(releases[0].assets[0].size, Some(45853276));
+    }
+
+    struct UploadingProvider {
+        assets: usize,
+    }
+
+    #[async_trait]
+    impl BaseProvider for UploadingProvider {
+        fn get_cache_request_key(
+            &self,
+            _function_type: &FunctionType,
+            _data_map: &DataMap,
+        ) -> Vec<String> {
+            vec!["releases".to_string()]
+        }
+
+        async fn check_app_available(&self, _fin: &FIn) -> FOut<bool> {
+            FOut::new(true)
+        }
+
+        async fn get_releases(&self, _fin: &FIn) -> FOut<Vec<ReleaseData>> {
+            let release = ReleaseData {
+                version_number: "1.0.0".to_string(),
+                changelog: "".to_string(),
+                assets: (0..self.assets)
+                    .map(|i| super::super::data::release::AssetData {
+                        file_name: format!("app-{}.apk", i),
+                        file_type: "apk".to_string(),
+                        download_url: format!("https://example.com/app-{}.apk", i),
+                        size: None,
+                        extra: None,
+                    })
+                    .collect(),
+                extra: None,
+            };
+            FOut::new(vec![release]).set_cache_policy("releases", Some(CachePolicy::MaxAge(3600)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recheck_awaiting_assets() {
+        let uuid = "test-recheck-awaiting-assets";
+        add_provider(uuid, UploadingProvider { assets: 0 });
+        let hub_data = HubDataMap::new();
+        let policy = |fout: FOut<Vec<ReleaseData>>| fout.cache_policy_map.unwrap()["releases"];
+
+        let app_data = AppDataMap::new();
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let fout = get_releases(uuid, &fin).await.unwrap();
+        assert_eq!(
+            policy(fout),
+            CachePolicy::Recheck(AWAITING_ASSETS_DEFAULT_EXPIRE_TIME)
+        );
+        let fout = get_latest_release(uuid, &fin).await.unwrap();
+        assert_eq!(
+            fout.cache_policy_map.unwrap()["releases"],
+            CachePolicy::Recheck(AWAITING_ASSETS_DEFAULT_EXPIRE_TIME)
+        );
+
+        let app_data = AppDataMap::from([(AWAITING_ASSETS_EXPIRE_TIME, "60")]);
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let fout = get_releases(uuid, &fin).await.unwrap();
+        assert_eq!(policy(fout), CachePolicy::Recheck(60));
+
+        let app_data = AppDataMap::from([(SOURCE_ONLY, "true")]);
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let fout = get_releases(uuid, &fin).await.unwrap();
+        assert_eq!(policy(fout), CachePolicy::MaxAge(3600));
+
+        let uuid = "test-recheck-awaiting-assets-uploaded";
+        add_provider(uuid, UploadingProvider { assets: 1 });
+        let app_data = AppDataMap::new();
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let fout = get_releases(uuid, &fin).await.unwrap();
+        assert_eq!(policy(fout), CachePolicy::MaxAge(3600));
+    }
+
+    struct ManyAssetsProvider;
+
+    #[async_trait]
+    impl BaseProvider for ManyAssetsProvider {
+        fn get_cache_request_key(
+            &self,
+            _function_type: &FunctionType,
+            _data_map: &DataMap,
+        ) -> Vec<String> {
+            vec![]
+        }
+
+        async fn check_app_available(&self, _fin: &FIn) -> FOut<bool> {
+            FOut::new(true)
+        }
+
+        async fn get_releases(&self, _fin: &FIn) -> FOut<Vec<ReleaseData>> {
+            let file_names = [
+                "app-arm64.apk",
+                "app-arm64.apk.asc",
+                "app-arm64.apk.sha256",
+                "app-x86_64.apk",
+                "app-x86_64.apk.asc",
+                "app-x86_64.apk.sha256",
+                "app-universal.apk",
+                "app-universal.apk.asc",
+                "app-universal.apk.sha256",
+                "app-src.tar.gz",
+                "app-src.zip",
+                "mapping.txt",
+            ];
+            let release = ReleaseData {
+                version_number: "1.0.0".to_string(),
+                changelog: "".to_string(),
+                assets: file_names
+                    .iter()
+                    .map(|file_name| super::super::data::release::AssetData {
+                        file_name: file_name.to_string(),
+                        file_type: "".to_string(),
+                        download_url: format!("https://example.com/{}", file_name),
+                        size: None,
+                        extra: None,
+                    })
+                    .collect(),
+                extra: None,
+            };
+            FOut::new(vec![release])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_asset_filter() {
+        let uuid = "test-asset-filter";
+        add_provider(uuid, ManyAssetsProvider);
+        let file_names = |release: &ReleaseData| {
+            release
+                .assets
+                .iter()
+                .map(|asset| asset.file_name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let app_data = AppDataMap::new();
+        let hub_data = HubDataMap::new();
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let releases = get_releases(uuid, &fin).await.unwrap().result.unwrap();
+        assert_eq!(releases[0].assets.len(), 12);
+        assert_eq!(releases[0].extra, None);
+
+        let hub_data = HubDataMap::from([(ASSET_EXCLUDE_PATTERNS, "*.asc,*.sha256,*-src.*")]);
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let releases = get_releases(uuid, &fin).await.unwrap().result.unwrap();
+        assert_eq!(
+            file_names(&releases[0]),
+            vec![
+                "app-arm64.apk",
+                "app-x86_64.apk",
+                "app-universal.apk",
+                "mapping.txt"
+            ]
+        );
+        assert_eq!(
+            releases[0].extra.as_ref().unwrap()[FILTERED_ASSETS_KEY],
+            "8"
+        );
+
+        // app_data patterns take precedence over the hub's
+        let app_data = AppDataMap::from([(ASSET_INCLUDE_PATTERNS, "*.apk")]);
+        let release = get_latest_release(uuid, &FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        assert_eq!(
+            file_names(&release),
+            vec!["app-arm64.apk", "app-x86_64.apk", "app-universal.apk"]
+        );
+
+        let app_data = AppDataMap::from([(ASSET_INCLUDE_PATTERNS, "*.aab")]);
+        let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+        let releases = get_releases(uuid, &fin).await.unwrap().result.unwrap();
+        assert!(releases[0].assets.is_empty());
+        assert_eq!(
+            releases[0].extra.as_ref().unwrap()[FILTERED_ASSETS_KEY],
+            "12"
+        );
+    }
+
+    #[test]
+    fn test_poisoned_provider_map() {
+        let _ = std::thread::spawn(|| {
+            let _map = PROVIDER_MAP.write().unwrap_or_else(PoisonError::into_inner);
+            panic!("poisoning the provider map");
+        })
+        .join();
+        assert!(PROVIDER_MAP.is_poisoned());
+        assert!(get_provider("fd9b2602-62c5-4d55-bd1e-0d6537714ca0").is_some());
+        add_provider("test-poisoned-provider-map", TagProvider);
+        assert!(get_provider("test-poisoned-provider-map").is_some());
+    }
+
+    #[test]
+    fn test_add_provider_constructor_panics() {
+        let uuid = "test-constructor-panics";
+        assert!(!add_provider_with(uuid, || -> TagProvider {
+            panic!("bad build")
+        }));
+        assert!(get_provider(uuid).is_none());
+        assert!(get_skipped_providers().contains(&(uuid.to_string(), "bad build".to_string())));
+        assert!(get_provider("fd9b2602-62c5-4d55-bd1e-0d6537714ca0").is_some());
+
+        assert!(add_provider_with(uuid, || TagProvider));
+        assert!(get_provider(uuid).is_some());
+    }
+
+    #[test]
+    fn test_canonicalize_app_data() {
+        let mut app_values = BTreeMap::from([
+            ("owner", " DUpdateSystem/".to_string()),
+            ("repo", "UpgradeAll.git/".to_string()),
+            ("note", " Kept As Is/".to_string()),
+        ]);
+        canonicalize_app_data("fd9b2602-62c5-4d55-bd1e-0d6537714ca0", &mut app_values);
+        assert_eq!(app_values["owner"], "dupdatesystem");
+        assert_eq!(app_values["repo"], "upgradeall");
+        assert_eq!(app_values["note"], " Kept As Is/");
+
+        // GitLab paths keep their case
+        let mut app_values = BTreeMap::from([
+            ("owner", "Group%2FSubGroup".to_string()),
+            ("repo", "Project.git".to_string()),
+        ]);
+        canonicalize_app_data("a84e2fbe-1478-4db5-80ae-75d00454c7eb", &mut app_values);
+        assert_eq!(app_values["owner"], "Group/SubGroup");
+        assert_eq!(app_values["repo"], "Project");
     }
 }
@@ -3,13 +3,18 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use core::fmt;
 use regex::Regex;
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{
     collections::{BTreeMap, HashMap},
     error::Error,
 };
 
 use super::super::data::release::*;
+use super::super::data::search::SearchResultData;
+use crate::utils::http::{CachePolicy, Validators};
+use crate::utils::netrc;
+use crate::utils::versioning::VersionNormalizer;
 
 pub type HubDataMap<'a> = BTreeMap<&'a str, &'a str>;
 pub type AppDataMap<'a> = BTreeMap<&'a str, &'a str>;
@@ -30,25 +35,249 @@ impl fmt::Display for DataMap<'_> {
     }
 }
 
+#[derive(Debug)]
+pub struct MissingKeyError {
+    pub key: String,
+}
+
+impl fmt::Display for MissingKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MissingKeyError: app_data has no {}", self.key)
+    }
+}
+
+impl Error for MissingKeyError {}
+
+#[derive(Debug)]
+pub struct SearchNotSupportedError;
+
+impl fmt::Display for SearchNotSupportedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SearchNotSupportedError: the hub can't be searched")
+    }
+}
+
+impl Error for SearchNotSupportedError {}
+
+/// The hub answered, but lists no release for the app.
+#[derive(Debug)]
+pub struct NoReleaseError;
+
+impl fmt::Display for NoReleaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NoReleaseError: the app has no release")
+    }
+}
+
+impl Error for NoReleaseError {}
+
+impl<'a> DataMap<'a> {
+    pub fn get_app_value(&self, key: &str) -> Result<&'a str, MissingKeyError> {
+        self.app_data
+            .get(key)
+            .copied()
+            .ok_or_else(|| MissingKeyError {
+                key: key.to_string(),
+            })
+    }
+}
+
+/// A response the provider couldn't make sense of, reported instead of an
+/// empty result when `strict_parsing` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    Url {
+        url: String,
+    },
+    Body {
+        url: String,
+        reason: String,
+    },
+    /// Every one of `count` entries lacked fields the provider needs
+    AllDropped {
+        url: String,
+        count: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Url { url } => write!(f, "ParseError: invalid URL {}", url),
+            ParseError::Body { url, reason } => {
+                write!(f, "ParseError: unexpected body from {}: {}", url, reason)
+            }
+            ParseError::AllDropped { url, count } => write!(
+                f,
+                "ParseError: none of the {} entries from {} could be read",
+                count, url
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl DataMap<'_> {
+    /// Whether hub_data sets `strict_parsing`, so unreadable responses fail
+    /// instead of reading as no data.
+    pub fn is_strict(&self) -> bool {
+        self.hub_data.get(STRICT_PARSING) == Some(&"true")
+    }
+}
+
+impl DataMap<'_> {
+    /// Version normalization rules, from app_data first, then hub_data.
+    pub fn get_version_normalizer(&self) -> Result<Option<VersionNormalizer>, regex::Error> {
+        let get = |key: &str| {
+            self.app_data
+                .get(key)
+                .or_else(|| self.hub_data.get(key))
+                .copied()
+        };
+        let normalizer = VersionNormalizer::new(
+            get(VERSION_EXTRACT),
+            get(VERSION_STRIP_PREFIXES),
+            get(VERSION_STRIP_SUFFIXES),
+        )?;
+        Ok(Some(normalizer).filter(|normalizer| !normalizer.is_empty()))
+    }
+}
+
+/// Selects the releases of one component in a repo that tags several, and
+/// the version part of their tags.
+///
+/// A tag must start with `prefix`, which is stripped, and the rest must match
+/// `regex`, whose first capture group (if any) is the version.
+#[derive(Debug)]
+pub struct TagFilter {
+    prefix: Option<String>,
+    regex: Option<Regex>,
+}
+
+impl TagFilter {
+    pub fn version_of<'t>(&self, tag: &'t str) -> Option<&'t str> {
+        let mut version = tag;
+        if let Some(prefix) = &self.prefix {
+            version = version.strip_prefix(prefix.as_str())?;
+        }
+        if let Some(regex) = &self.regex {
+            let caps = regex.captures(version)?;
+            if let Some(m) = caps.get(1) {
+                version = m.as_str();
+            }
+        }
+        Some(version)
+    }
+}
+
+impl DataMap<'_> {
+    /// Tag filter from `tag_prefix` and `tag_filter_regex`, app_data first,
+    /// then hub_data.
+    pub fn get_tag_filter(&self) -> Result<Option<TagFilter>, regex::Error> {
+        let get = |key: &str| {
+            self.app_data
+                .get(key)
+                .or_else(|| self.hub_data.get(key))
+                .copied()
+                .filter(|value| !value.is_empty())
+        };
+        let prefix = get(TAG_PREFIX).map(|prefix| prefix.to_string());
+        let regex = get(TAG_FILTER_REGEX).map(Regex::new).transpose()?;
+        if prefix.is_none() && regex.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(TagFilter { prefix, regex }))
+    }
+}
+
+/// Which assets are listed at all, from comma-separated glob lists over the
+/// file name; `*` matches any run of characters and `?` any one.
+///
+/// An asset is kept if it matches an include pattern (or none are set) and
+/// no exclude pattern.
+#[derive(Debug)]
+pub struct AssetFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl AssetFilter {
+    fn parse_globs(globs: Option<&str>) -> Result<Vec<Regex>, regex::Error> {
+        globs
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|glob| !glob.is_empty())
+            .map(|glob| {
+                let pattern = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+                Regex::new(&format!("^{}$", pattern))
+            })
+            .collect()
+    }
+
+    pub fn keeps(&self, file_name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(file_name)))
+            && !self.exclude.iter().any(|re| re.is_match(file_name))
+    }
+}
+
 impl DataMap<'_> {
-    pub fn get_hash(&self) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        self.hash(&mut hasher);
-        hasher.finish()
+    /// Asset filter from `asset_include_patterns` and
+    /// `asset_exclude_patterns`, app_data first, then hub_data.
+    pub fn get_asset_filter(&self) -> Result<Option<AssetFilter>, regex::Error> {
+        let get = |key: &str| {
+            self.app_data
+                .get(key)
+                .or_else(|| self.hub_data.get(key))
+                .copied()
+        };
+        let filter = AssetFilter {
+            include: AssetFilter::parse_globs(get(ASSET_INCLUDE_PATTERNS))?,
+            exclude: AssetFilter::parse_globs(get(ASSET_EXCLUDE_PATTERNS))?,
+        };
+        if filter.include.is_empty() && filter.exclude.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(filter))
+    }
+
+    /// How to pick the asset to download from `asset_filter` (a regex over
+    /// the file name) and `preferred_abi`, app_data first, then hub_data.
+    pub fn get_asset_selector(&self) -> Result<AssetSelector, regex::Error> {
+        let get = |key: &str| {
+            self.app_data
+                .get(key)
+                .or_else(|| self.hub_data.get(key))
+                .copied()
+        };
+        Ok(AssetSelector {
+            file_name: get(ASSET_FILTER).map(Regex::new).transpose()?,
+            preferred_abi: get(PREFERRED_ABI).map(str::to_string),
+        })
     }
 }
 
 pub type CacheMap<K, T> = HashMap<K, T>;
 
+#[derive(Debug, Hash)]
 pub enum FunctionType {
     CheckAppAvailable,
     GetLatestRelease,
     GetReleases,
 }
 
+/// An expired response that can be revalidated instead of fetched again.
+#[derive(Debug, Clone)]
+pub struct StaleCache {
+    pub body: Bytes,
+    pub validators: Validators,
+}
+
 pub struct FIn<'a> {
     pub data_map: DataMap<'a>,
     cache_map: Option<HashMap<String, Bytes>>,
+    stale_map: Option<HashMap<String, StaleCache>>,
 }
 
 impl<'a> FIn<'a> {
@@ -60,15 +289,24 @@ impl<'a> FIn<'a> {
         FIn {
             data_map: DataMap { app_data, hub_data },
             cache_map,
+            stale_map: None,
         }
     }
     pub fn new(data_map: DataMap<'a>, cache_map: Option<CacheMap<String, Bytes>>) -> Self {
         FIn {
             data_map,
             cache_map,
+            stale_map: None,
         }
     }
 
+    /// Expired responses, for keys missing from the cache map, that
+    /// providers may send conditional requests for.
+    pub fn with_stale_map(mut self, stale_map: HashMap<String, StaleCache>) -> Self {
+        self.stale_map = Some(stale_map);
+        self
+    }
+
     pub fn get_cache(&self, key: &str) -> Option<&Bytes> {
         if let Some(cache_map) = &self.cache_map {
             if let Some(value) = cache_map.get(key) {
@@ -77,12 +315,20 @@ impl<'a> FIn<'a> {
         }
         None
     }
+
+    pub fn get_stale_cache(&self, key: &str) -> Option<&StaleCache> {
+        self.stale_map.as_ref()?.get(key)
+    }
 }
 
 #[derive(Debug)]
 pub struct FOut<T> {
     pub result: Result<T, Box<dyn Error + Send + Sync>>,
     pub cached_map: Option<HashMap<String, Bytes>>,
+    // Caching directives of the responses in `cached_map`, by key
+    pub cache_policy_map: Option<HashMap<String, CachePolicy>>,
+    // Validators of the responses in `cached_map`, by key
+    pub validators_map: Option<HashMap<String, Validators>>,
 }
 
 impl<T> FOut<T> {
@@ -90,6 +336,8 @@ impl<T> FOut<T> {
         FOut {
             result: Ok(data),
             cached_map: None,
+            cache_policy_map: None,
+            validators_map: None,
         }
     }
 
@@ -100,6 +348,8 @@ impl<T> FOut<T> {
                 "no data",
             ))),
             cached_map: None,
+            cache_policy_map: None,
+            validators_map: None,
         }
     }
 
@@ -109,11 +359,37 @@ impl<T> FOut<T> {
         self
     }
 
+    pub fn set_cache_policy(mut self, key: &str, policy: Option<CachePolicy>) -> Self {
+        if let Some(policy) = policy {
+            let cache_policy_map = self.cache_policy_map.get_or_insert_with(HashMap::new);
+            cache_policy_map.insert(key.to_string(), policy);
+        }
+        self
+    }
+
+    pub fn set_validators(mut self, key: &str, validators: Option<Validators>) -> Self {
+        if let Some(validators) = validators {
+            let validators_map = self.validators_map.get_or_insert_with(HashMap::new);
+            validators_map.insert(key.to_string(), validators);
+        }
+        self
+    }
+
     pub fn set_error(mut self, error: Box<dyn Error + Send + Sync>) -> Self {
         self.result = Err(error);
         self
     }
 
+    /// Fails with `error` in strict mode; otherwise keeps the lenient
+    /// result the provider already built.
+    pub fn set_parse_error(self, data_map: &DataMap, error: ParseError) -> Self {
+        if data_map.is_strict() {
+            self.set_error(Box::new(error))
+        } else {
+            self
+        }
+    }
+
     pub fn set_data(mut self, data: T) -> Self {
         self.result = Ok(data);
         self
@@ -127,6 +403,38 @@ impl<T> FOut<T> {
 
 #[async_trait]
 pub trait BaseProvider {
+    /// Bump when the parsing semantics change, so previously cached parse
+    /// results of this provider are treated as misses.
+    fn get_parse_version(&self) -> u32 {
+        0
+    }
+
+    /// Whether releases sharing a version number are the same release listed
+    /// twice (e.g. a re-published tag) and should be merged. Stores that ship
+    /// one package per ABI under the same version keep this off.
+    fn merges_duplicate_versions(&self) -> bool {
+        false
+    }
+
+    /// app_data keys the provider can't do without. Apps missing any of them
+    /// are reported as misconfigured without a request being made.
+    fn required_app_keys(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// app_data keys holding repo path segments. Their values are trimmed,
+    /// percent-decoded and stripped of trailing `/` and `.git` before use, so
+    /// spellings of the same repo share cache entries.
+    fn repo_path_keys(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Whether the forge matches repo paths case-insensitively, so the
+    /// `repo_path_keys` values are lowercased too.
+    fn repo_path_case_insensitive(&self) -> bool {
+        false
+    }
+
     fn get_cache_request_key(
         &self,
         function_type: &FunctionType,
@@ -144,10 +452,7 @@ pub trait BaseProvider {
                 if let Some(release) = release {
                     Ok(release)
                 } else {
-                    Err(
-                        Box::new(std::io::Error::new(std::io::ErrorKind::NotFound, "no data"))
-                            as Box<dyn std::error::Error + Send + Sync>,
-                    )
+                    Err(Box::new(NoReleaseError) as Box<dyn std::error::Error + Send + Sync>)
                 }
             }
             Err(e) => Err(e),
@@ -156,10 +461,23 @@ pub trait BaseProvider {
         FOut {
             result: fout_result,
             cached_map: result.cached_map,
+            cache_policy_map: result.cache_policy_map,
+            validators_map: result.validators_map,
         }
     }
 
     async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>>;
+
+    /// Cache keys of the responses `search_apps` reads, e.g. a repo index
+    /// `get_releases` also fetches.
+    fn get_search_cache_request_key(&self, _data_map: &DataMap) -> Vec<String> {
+        vec![]
+    }
+
+    /// Apps on the hub matching `query`. Only hub_data is set in `fin`.
+    async fn search_apps(&self, _fin: &FIn, _query: &str) -> FOut<Vec<SearchResultData>> {
+        FOut::new_empty().set_error(Box::new(SearchNotSupportedError))
+    }
 }
 
 pub trait BaseProviderExt: BaseProvider {
@@ -181,6 +499,28 @@ pub trait BaseProviderExt: BaseProvider {
         }
     }
 
+    /// See [`resolve_credential`]; reads the process environment and netrc.
+    /// Discovered credentials are only sent to the origin of `url` itself,
+    /// never to a reverse proxy standing in for it.
+    fn get_credential(
+        &self,
+        fin: &FIn,
+        env_vars: &[&'static str],
+        url: &str,
+    ) -> Option<Credential> {
+        let origin = url_origin(url);
+        let proxied = self.replace_proxy_url(fin, url);
+        let auto_discover = credentials_auto_discover() && url_origin(&proxied) == origin;
+        resolve_credential(
+            fin.data_map.hub_data,
+            auto_discover,
+            env_vars,
+            url_host(url),
+            |name| std::env::var(name).ok(),
+            netrc::read_netrc,
+        )
+    }
+
     fn replace_proxy_url(&self, fin: &FIn, url: &str) -> String {
         let mut result_url = url.to_string();
         for (url_prefix, proxy_url) in self.url_proxy_map(fin).iter() {
@@ -198,6 +538,95 @@ pub trait BaseProviderExt: BaseProvider {
     }
 }
 
+/// Where a provider token came from; reported instead of the token itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialSource {
+    HubData,
+    Env(&'static str),
+    Netrc,
+}
+
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialSource::HubData => write!(f, "hub_data"),
+            CredentialSource::Env(name) => write!(f, "env {}", name),
+            CredentialSource::Netrc => write!(f, "netrc"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credential {
+    pub token: String,
+    pub source: CredentialSource,
+}
+
+static CREDENTIALS_AUTO_DISCOVER: AtomicBool = AtomicBool::new(false);
+
+/// Lets providers fall back to tokens from the environment and netrc when
+/// hub_data has none. Off by default; it's up to whoever runs getter, as
+/// callers could otherwise have the tokens sent where they like.
+pub fn set_credentials_auto_discover(enabled: bool) {
+    CREDENTIALS_AUTO_DISCOVER.store(enabled, Ordering::Relaxed);
+}
+
+pub fn credentials_auto_discover() -> bool {
+    CREDENTIALS_AUTO_DISCOVER.load(Ordering::Relaxed)
+}
+
+// Scheme, host and port of `url`
+fn url_origin(url: &str) -> &str {
+    let path_start = url.find("://").map_or(0, |i| i + 3);
+    match url[path_start..].find(['/', '?', '#']) {
+        Some(i) => &url[..path_start + i],
+        None => url,
+    }
+}
+
+fn url_host(url: &str) -> &str {
+    url.split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', ':', '?', '#'])
+        .next()
+        .unwrap_or_default()
+}
+
+/// The hub_data token, or with `auto_discover`, the first of `env_vars`
+/// that is set, then the netrc entry for `host`.
+pub fn resolve_credential(
+    hub_data: &HubDataMap,
+    auto_discover: bool,
+    env_vars: &[&'static str],
+    host: &str,
+    env: impl Fn(&str) -> Option<String>,
+    netrc: impl FnOnce() -> Option<String>,
+) -> Option<Credential> {
+    if let Some(token) = hub_data.get(TOKEN).filter(|token| !token.is_empty()) {
+        return Some(Credential {
+            token: token.to_string(),
+            source: CredentialSource::HubData,
+        });
+    }
+    if !auto_discover {
+        return None;
+    }
+    for name in env_vars {
+        if let Some(token) = env(name).filter(|token| !token.is_empty()) {
+            return Some(Credential {
+                token,
+                source: CredentialSource::Env(name),
+            });
+        }
+    }
+    netrc()
+        .and_then(|content| netrc::find_password(&content, host))
+        .map(|token| Credential {
+            token,
+            source: CredentialSource::Netrc,
+        })
+}
+
 pub const ANDROID_APP_TYPE: &str = "android_app_package";
 pub const ANDROID_MAGISK_MODULE_TYPE: &str = "android_magisk_module";
 pub const ANDROID_CUSTOM_SHELL: &str = "android_custom_shell";
@@ -208,6 +637,25 @@ pub const KEY_REPO_API_URL: &str = "repo_api_url";
 
 pub const REVERSE_PROXY: &str = "reverse_proxy";
 
+pub const TOKEN: &str = "token";
+
+pub const TAG_PREFIX: &str = "tag_prefix";
+pub const TAG_FILTER_REGEX: &str = "tag_filter_regex";
+
+pub const ASSET_INCLUDE_PATTERNS: &str = "asset_include_patterns";
+pub const ASSET_EXCLUDE_PATTERNS: &str = "asset_exclude_patterns";
+pub const ASSET_FILTER: &str = "asset_filter";
+pub const PREFERRED_ABI: &str = "preferred_abi";
+
+pub const STRICT_PARSING: &str = "strict_parsing";
+
+pub const SOURCE_ONLY: &str = "source_only";
+pub const AWAITING_ASSETS_EXPIRE_TIME: &str = "awaiting_assets_expire_time";
+
+pub const VERSION_EXTRACT: &str = "version_extract";
+pub const VERSION_STRIP_PREFIXES: &str = "version_strip_prefixes";
+pub const VERSION_STRIP_SUFFIXES: &str = "version_strip_suffixes";
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -298,6 +746,7 @@ mod tests {
                 hub_data: &BTreeMap::new(),
             },
             cache_map: Some(cache_map),
+            stale_map: None,
         };
 
         let available = mock.check_app_available(&fin).await;
@@ -315,6 +764,7 @@ mod tests {
                 hub_data: &BTreeMap::new(),
             },
             cache_map: Some(cache_map),
+            stale_map: None,
         };
 
         let releases = mock.get_releases(&fin).await;
@@ -332,6 +782,7 @@ mod tests {
                 hub_data: &BTreeMap::new(),
             },
             cache_map: Some(cache_map),
+            stale_map: None,
         };
 
         let latest_release = mock.get_latest_release(&fin).await;
@@ -418,4 +869,72 @@ mod tests {
         );
         assert_eq!(result, url_r);
     }
+
+    #[test]
+    fn test_resolve_credential() {
+        let env = |name: &str| match name {
+            "GH_TOKEN" => Some("gh".to_string()),
+            "GITHUB_TOKEN" => Some("".to_string()),
+            _ => None,
+        };
+        let netrc = || Some("machine api.github.com password from_netrc".to_string());
+        let resolve = |hub_data: &HubDataMap, auto_discover, env_vars: &[&'static str]| {
+            resolve_credential(
+                hub_data,
+                auto_discover,
+                env_vars,
+                "api.github.com",
+                env,
+                netrc,
+            )
+            .map(|credential| (credential.token, credential.source.to_string()))
+        };
+        let no_token = HubDataMap::new();
+
+        let explicit = HubDataMap::from([(TOKEN, "explicit")]);
+        assert_eq!(
+            resolve(&explicit, true, &["GH_TOKEN"]),
+            Some(("explicit".to_string(), "hub_data".to_string()))
+        );
+        assert_eq!(
+            resolve(&no_token, true, &["GITHUB_TOKEN", "GH_TOKEN"]),
+            Some(("gh".to_string(), "env GH_TOKEN".to_string()))
+        );
+        assert_eq!(
+            resolve(&no_token, true, &["GITLAB_TOKEN"]),
+            Some(("from_netrc".to_string(), "netrc".to_string()))
+        );
+        // Discovery is opt-in
+        assert_eq!(resolve(&no_token, false, &["GH_TOKEN"]), None);
+    }
+
+    #[test]
+    fn test_get_credential_proxied() {
+        std::env::set_var("GETTER_TEST_PROXIED_TOKEN", "discovered");
+        set_credentials_auto_discover(true);
+        let mock = MockProvider::new();
+        let url = "https://api.example.com/repos";
+        let app_data = AppDataMap::new();
+        let get = |hub_data: &HubDataMap| {
+            let fin = FIn::new_with_frag(&app_data, hub_data, None);
+            mock.get_credential(&fin, &["GETTER_TEST_PROXIED_TOKEN"], url)
+                .map(|credential| credential.token)
+        };
+        assert_eq!(get(&HubDataMap::new()).as_deref(), Some("discovered"));
+        for proxy in [
+            "https://api.example.com -> https://attacker.example",
+            "https://api.example.com -> http://api.example.com",
+        ] {
+            let proxied = HubDataMap::from([(REVERSE_PROXY, proxy)]);
+            assert_eq!(get(&proxied), None);
+            // A token the caller gave is theirs to send anywhere
+            let explicit = HubDataMap::from([(REVERSE_PROXY, proxy), (TOKEN, "explicit")]);
+            assert_eq!(get(&explicit).as_deref(), Some("explicit"));
+        }
+        // Proxies for other origins don't matter
+        let other = HubDataMap::from([(REVERSE_PROXY, "https://github.com -> https://mirror")]);
+        assert_eq!(get(&other).as_deref(), Some("discovered"));
+        set_credentials_auto_discover(false);
+        assert_eq!(get(&HubDataMap::new()), None);
+    }
 }
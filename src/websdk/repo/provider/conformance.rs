@@ -0,0 +1,360 @@
+//! Contract every [`BaseProvider`] is expected to honor, runnable against any
+//! implementation.
+//!
+//! Network access is captured by a local mock server: the harness sets a
+//! `reverse_proxy` rule that sends every `http(s)` host to it, so providers
+//! must route their requests through [`BaseProviderExt::replace_proxy_url`].
+//!
+//! ```ignore
+//! #[tokio::test]
+//! async fn test_conformance() {
+//!     let body = fs::read("tests/files/web/github_api_release.json").unwrap();
+//!     run_conformance(
+//!         GitHubProvider::new(),
+//!         Fixture {
+//!             app_data: vec![("owner", "DUpdateSystem"), ("repo", "UpgradeAll")],
+//!             hub_data: vec![],
+//!             responses: vec![("/repos/DUpdateSystem/UpgradeAll/releases", body.into())],
+//!         },
+//!     )
+//!     .await;
+//! }
+//! ```
+
+use bytes::Bytes;
+use mockito::{Matcher, Mock, Server, ServerGuard};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::base_provider::*;
+
+/// Inputs of one app the provider is known to resolve.
+pub struct Fixture {
+    pub app_data: Vec<(&'static str, &'static str)>,
+    /// Extra hub data; `reverse_proxy` is owned by the harness.
+    pub hub_data: Vec<(&'static str, &'static str)>,
+    /// Response bodies served by the mock server, by request path.
+    pub responses: Vec<(&'static str, Bytes)>,
+}
+
+const FUNCTION_TYPES: [FunctionType; 3] = [
+    FunctionType::CheckAppAvailable,
+    FunctionType::GetLatestRelease,
+    FunctionType::GetReleases,
+];
+
+fn proxy_rule(server: &ServerGuard) -> String {
+    format!("regex:^https?://[^/]+ -> {}", server.url())
+}
+
+fn hub_data_map<'a>(fixture: &'a Fixture, proxy_rule: &'a str) -> HubDataMap<'a> {
+    let mut hub_data = fixture.hub_data.iter().copied().collect::<HubDataMap>();
+    hub_data.insert(REVERSE_PROXY, proxy_rule);
+    hub_data
+}
+
+/// Mocks that fail the run if any request reaches them.
+async fn no_network_mocks(server: &mut ServerGuard) -> Vec<Mock> {
+    let mut mocks = vec![];
+    for method in ["GET", "HEAD"] {
+        mocks.push(
+            server
+                .mock(method, Matcher::Any)
+                .expect(0)
+                .create_async()
+                .await,
+        );
+    }
+    mocks
+}
+
+/// Runs the provider through the shared contract, panicking with the broken
+/// rule on the first violation:
+///
+/// - cache keys are non-empty and deterministic for the same inputs
+/// - missing app data is reported as an error in [`FOut`], never a panic
+/// - `get_releases` resolves the fixture, and every key of its `cached_map`
+///   is one of its `get_cache_request_key` outputs
+/// - cached bodies are honored: no request is made when they are present
+/// - unparsable cached bodies don't panic, and with `strict_parsing` set
+///   they fail with [`ParseError`], as unparsable request URLs do
+pub async fn run_conformance<P>(provider: P, fixture: Fixture)
+where
+    P: BaseProvider + Send + Sync + 'static,
+{
+    let provider = Arc::new(provider);
+    let fixture = Arc::new(fixture);
+
+    let app_data = fixture.app_data.iter().copied().collect::<AppDataMap>();
+    let hub_data = fixture.hub_data.iter().copied().collect::<HubDataMap>();
+    let data_map = DataMap {
+        app_data: &app_data,
+        hub_data: &hub_data,
+    };
+    for function_type in FUNCTION_TYPES.iter() {
+        let keys = provider.get_cache_request_key(function_type, &data_map);
+        assert!(
+            !keys.is_empty(),
+            "no cache request key for {:?}",
+            function_type
+        );
+        assert_eq!(
+            keys,
+            provider.get_cache_request_key(function_type, &data_map),
+            "cache request keys for {:?} aren't deterministic",
+            function_type
+        );
+    }
+    let release_keys = provider.get_cache_request_key(&FunctionType::GetReleases, &data_map);
+
+    let task = {
+        let (provider, fixture) = (provider.clone(), fixture.clone());
+        tokio::spawn(async move {
+            let mut server = Server::new_async().await;
+            let _mocks = no_network_mocks(&mut server).await;
+            let proxy_rule = proxy_rule(&server);
+            let app_data = AppDataMap::new();
+            let hub_data = hub_data_map(&fixture, &proxy_rule);
+            for function_type in FUNCTION_TYPES.iter() {
+                provider.get_cache_request_key(
+                    function_type,
+                    &DataMap {
+                        app_data: &app_data,
+                        hub_data: &hub_data,
+                    },
+                );
+            }
+            let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+            (
+                provider.check_app_available(&fin).await.result.is_err(),
+                provider.get_releases(&fin).await.result.is_err(),
+            )
+        })
+    };
+    match task.await {
+        Ok((available_err, releases_err)) => {
+            assert!(
+                available_err,
+                "check_app_available succeeded on empty app_data"
+            );
+            assert!(releases_err, "get_releases succeeded on empty app_data");
+        }
+        Err(e) => panic!("panicked on empty app_data: {}", e),
+    }
+
+    let task = {
+        let (provider, fixture) = (provider.clone(), fixture.clone());
+        tokio::spawn(async move {
+            let mut server = Server::new_async().await;
+            let mut mocks = vec![];
+            for (path, body) in fixture.responses.iter() {
+                mocks.push(
+                    server
+                        .mock("GET", *path)
+                        .with_status(200)
+                        .with_body(body)
+                        .create_async()
+                        .await,
+                );
+            }
+            let proxy_rule = proxy_rule(&server);
+            let app_data = fixture.app_data.iter().copied().collect::<AppDataMap>();
+            let hub_data = hub_data_map(&fixture, &proxy_rule);
+            let fout = provider
+                .get_releases(&FIn::new_with_frag(&app_data, &hub_data, None))
+                .await;
+            (fout.result.ok(), fout.cached_map)
+        })
+    };
+    let (releases, cached_map) = match task.await {
+        Ok((Some(releases), cached_map)) => (releases, cached_map.unwrap_or_default()),
+        Ok((None, _)) => panic!("get_releases failed on the fixture"),
+        Err(e) => panic!("panicked on the fixture: {}", e),
+    };
+    assert!(!releases.is_empty(), "no releases in the fixture");
+    assert!(!cached_map.is_empty(), "fetched responses aren't cached");
+    for key in cached_map.keys() {
+        assert!(
+            release_keys.contains(key),
+            "cached key {} isn't a cache request key",
+            key
+        );
+    }
+
+    let task = {
+        let (provider, fixture) = (provider.clone(), fixture.clone());
+        tokio::spawn(async move {
+            let mut server = Server::new_async().await;
+            let mocks = no_network_mocks(&mut server).await;
+            let proxy_rule = proxy_rule(&server);
+            let app_data = fixture.app_data.iter().copied().collect::<AppDataMap>();
+            let hub_data = hub_data_map(&fixture, &proxy_rule);
+            let fout = provider
+                .get_releases(&FIn::new_with_frag(&app_data, &hub_data, Some(cached_map)))
+                .await;
+            let mut offline = true;
+            for mock in mocks.iter() {
+                offline &= mock.matched_async().await;
+            }
+            (fout.result.ok(), offline)
+        })
+    };
+    match task.await {
+        Ok((cached_releases, offline)) => {
+            assert!(offline, "requested the network despite a cache entry");
+            assert_eq!(
+                cached_releases.as_ref(),
+                Some(&releases),
+                "cached releases differ from fetched ones"
+            );
+        }
+        Err(e) => panic!("panicked on the cache: {}", e),
+    }
+
+    let task = {
+        let (provider, fixture, release_keys) =
+            (provider.clone(), fixture.clone(), release_keys.clone());
+        tokio::spawn(async move {
+            let mut server = Server::new_async().await;
+            let _mocks = no_network_mocks(&mut server).await;
+            let proxy_rule = proxy_rule(&server);
+            let app_data = fixture.app_data.iter().copied().collect::<AppDataMap>();
+            let hub_data = hub_data_map(&fixture, &proxy_rule);
+            let cache_map = release_keys
+                .into_iter()
+                .map(|key| (key, Bytes::from_static(b"\0not a response")))
+                .collect::<HashMap<_, _>>();
+            provider
+                .get_releases(&FIn::new_with_frag(&app_data, &hub_data, Some(cache_map)))
+                .await;
+        })
+    };
+    if let Err(e) = task.await {
+        panic!("panicked on a corrupt cache: {}", e);
+    }
+
+    let task = {
+        let (provider, fixture) = (provider.clone(), fixture.clone());
+        tokio::spawn(async move {
+            let app_data = fixture.app_data.iter().copied().collect::<AppDataMap>();
+            let mut hub_data = fixture.hub_data.iter().copied().collect::<HubDataMap>();
+            hub_data.insert(STRICT_PARSING, "true");
+            let cache_map = release_keys
+                .into_iter()
+                .map(|key| (key, Bytes::from_static(b"\xff\0not a response")))
+                .collect::<HashMap<_, _>>();
+            let corrupt = provider
+                .get_releases(&FIn::new_with_frag(&app_data, &hub_data, Some(cache_map)))
+                .await;
+            hub_data.insert(REVERSE_PROXY, INVALID_URL_RULE);
+            let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+            (
+                is_parse_error(&corrupt),
+                is_parse_error(&provider.check_app_available(&fin).await),
+                is_parse_error(&provider.get_releases(&fin).await),
+            )
+        })
+    };
+    match task.await {
+        Ok((corrupt, available_url, releases_url)) => {
+            assert!(corrupt, "corrupt cache isn't a ParseError in strict mode");
+            assert!(
+                available_url,
+                "check_app_available on an invalid URL isn't a ParseError in strict mode"
+            );
+            assert!(
+                releases_url,
+                "get_releases on an invalid URL isn't a ParseError in strict mode"
+            );
+        }
+        Err(e) => panic!("panicked in strict mode: {}", e),
+    }
+}
+
+/// Rewrites every request into a URL that doesn't parse.
+const INVALID_URL_RULE: &str = "regex:^https?://[^/]+ -> http://[invalid";
+
+pub fn is_parse_error<T>(fout: &FOut<T>) -> bool {
+    matches!(&fout.result, Err(e) if e.is::<ParseError>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::data::release::ReleaseData;
+    use super::*;
+    use async_trait::async_trait;
+
+    use crate::utils::http::get;
+
+    const RELEASES_URL: &str = "https://example.com/releases";
+
+    fn fixture() -> Fixture {
+        Fixture {
+            app_data: vec![("id", "app")],
+            hub_data: vec![],
+            responses: vec![("/releases", Bytes::from_static(b"1.0.0"))],
+        }
+    }
+
+    fn releases(body: &Bytes) -> Vec<ReleaseData> {
+        vec![ReleaseData {
+            version_number: String::from_utf8_lossy(body).to_string(),
+            changelog: "".to_string(),
+            assets: vec![],
+            extra: None,
+        }]
+    }
+
+    /// Indexes app data and always fetches, ignoring the cache.
+    struct BrokenProvider {
+        check_id: bool,
+    }
+
+    impl BaseProviderExt for BrokenProvider {}
+
+    #[async_trait]
+    impl BaseProvider for BrokenProvider {
+        fn get_cache_request_key(
+            &self,
+            _function_type: &FunctionType,
+            _data_map: &DataMap,
+        ) -> Vec<String> {
+            vec![RELEASES_URL.to_string()]
+        }
+
+        async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
+            if self.check_id {
+                if let Err(e) = fin.data_map.get_app_value("id") {
+                    return FOut::new_empty().set_error(Box::new(e));
+                }
+            }
+            FOut::new(!fin.data_map.app_data["id"].is_empty())
+        }
+
+        async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
+            if self.check_id {
+                if let Err(e) = fin.data_map.get_app_value("id") {
+                    return FOut::new_empty().set_error(Box::new(e));
+                }
+            }
+            let url = self.replace_proxy_url(fin, RELEASES_URL);
+            let body = match get(url.parse().unwrap(), &HashMap::new()).await {
+                Ok(rsp) => rsp.body.unwrap_or_default(),
+                Err(e) => return FOut::new_empty().set_error(e),
+            };
+            FOut::new(releases(&body)).set_cache(RELEASES_URL, body)
+        }
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "panicked on empty app_data")]
+    async fn test_panic_on_missing_app_data() {
+        run_conformance(BrokenProvider { check_id: false }, fixture()).await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "requested the network despite a cache entry")]
+    async fn test_ignored_cache() {
+        run_conformance(BrokenProvider { check_id: true }, fixture()).await;
+    }
+}
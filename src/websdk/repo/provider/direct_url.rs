@@ -0,0 +1,340 @@
+use async_trait::async_trait;
+use hyper::Uri;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use super::super::data::release::*;
+use super::base_provider::*;
+
+use crate::utils::http::{head_request, http_status_is_ok, ResponseData};
+
+pub const DIRECT_URL_HUB_UUID: &str = "6dc2d518-3f3a-47e8-9cf2-2cd1b73d499b";
+
+const URL_KEY: &str = "url";
+const URL_VERSION_REGEX_KEY: &str = "url_version_regex";
+
+const ETAG_KEY: &str = "etag";
+const LAST_MODIFIED_KEY: &str = "last_modified";
+
+const MAX_REDIRECTS: usize = 5;
+
+static DEFAULT_VERSION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+(?:\.\d+)+").unwrap());
+
+#[derive(Debug)]
+pub struct DirectUrlError {
+    pub message: String,
+}
+
+impl std::fmt::Display for DirectUrlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "DirectUrlError: {}", self.message)
+    }
+}
+
+impl std::error::Error for DirectUrlError {}
+
+/// Tracks a single file at a stable `url` with no release feed.
+///
+/// The version comes from, in order: a version found in the redirect target
+/// or Content-Disposition filename (`url_version_regex`, by default the
+/// first dotted number), the ETag, or the Last-Modified time as unix seconds.
+pub struct DirectUrlProvider;
+
+struct HeadResult {
+    // Last redirect target, if any
+    location: Option<String>,
+    rsp: ResponseData,
+}
+
+impl DirectUrlProvider {
+    pub fn new() -> Self {
+        DirectUrlProvider {}
+    }
+
+    fn resolve_location(base: &Uri, location: &str) -> String {
+        if location.starts_with('/') {
+            format!(
+                "{}://{}{}",
+                base.scheme_str().unwrap_or("https"),
+                base.authority().map(|a| a.as_str()).unwrap_or_default(),
+                location
+            )
+        } else {
+            location.to_string()
+        }
+    }
+
+    async fn head_follow(
+        url: &str,
+    ) -> Result<HeadResult, Box<dyn std::error::Error + Send + Sync>> {
+        let mut uri: Uri = url.parse()?;
+        let mut location = None;
+        for _ in 0..=MAX_REDIRECTS {
+            let rsp = head_request(uri.clone(), &HashMap::new()).await?;
+            match rsp.headers.get("location") {
+                Some(next) if (300..400).contains(&rsp.status) => {
+                    let next = Self::resolve_location(&uri, next);
+                    uri = next.parse()?;
+                    location = Some(next);
+                }
+                _ => return Ok(HeadResult { location, rsp }),
+            }
+        }
+        Err(Box::new(DirectUrlError {
+            message: format!("too many redirects for {}", url),
+        }))
+    }
+
+    fn get_disposition_filename(rsp: &ResponseData) -> Option<String> {
+        let disposition = rsp.headers.get("content-disposition")?;
+        disposition.split(';').find_map(|part| {
+            let (key, value) = part.trim().split_once('=')?;
+            if key.eq_ignore_ascii_case("filename") {
+                Some(value.trim_matches('"').to_string())
+            } else {
+                None
+            }
+        })
+    }
+
+    fn find_version(text: &str, regex: Option<&Regex>) -> Option<String> {
+        // Only look at the last path segment, hosts often carry digits
+        let file_name = text.rsplit('/').next().unwrap_or(text);
+        let caps = regex
+            .unwrap_or(&DEFAULT_VERSION_REGEX)
+            .captures(file_name)?;
+        caps.get(1)
+            .or_else(|| caps.get(0))
+            .map(|m| m.as_str().to_string())
+    }
+
+    fn get_version(result: &HeadResult, regex: Option<&Regex>) -> Option<String> {
+        let from_name = Self::get_disposition_filename(&result.rsp)
+            .into_iter()
+            .chain(result.location.clone())
+            .find_map(|text| Self::find_version(&text, regex));
+        from_name
+            .or_else(|| {
+                result
+                    .rsp
+                    .headers
+                    .get("etag")
+                    .map(|etag| etag.trim_start_matches("W/").trim_matches('"').to_string())
+            })
+            .or_else(|| {
+                let last_modified = result.rsp.headers.get("last-modified")?;
+                let time = httpdate::parse_http_date(last_modified).ok()?;
+                let secs = time.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs();
+                Some(secs.to_string())
+            })
+    }
+}
+
+impl BaseProviderExt for DirectUrlProvider {}
+
+#[async_trait]
+impl BaseProvider for DirectUrlProvider {
+    fn required_app_keys(&self) -> &'static [&'static str] {
+        &[URL_KEY]
+    }
+
+    fn get_cache_request_key(
+        &self,
+        _function_type: &FunctionType,
+        _data_map: &DataMap,
+    ) -> Vec<String> {
+        vec![]
+    }
+
+    async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
+        let Some(url) = fin.data_map.app_data.get(URL_KEY) else {
+            return FOut::new_empty();
+        };
+        let url = self.replace_proxy_url(fin, url);
+        match Self::head_follow(&url).await {
+            Ok(result) => FOut::new(http_status_is_ok(result.rsp.status)),
+            Err(e) => FOut::new_empty().set_error(e),
+        }
+    }
+
+    async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
+        let data_map = &fin.data_map;
+        let Some(origin_url) = data_map.app_data.get(URL_KEY) else {
+            return FOut::new_empty();
+        };
+        let regex = match data_map
+            .app_data
+            .get(URL_VERSION_REGEX_KEY)
+            .or_else(|| data_map.hub_data.get(URL_VERSION_REGEX_KEY))
+            .map(|regex| Regex::new(regex))
+            .transpose()
+        {
+            Ok(regex) => regex,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let url = self.replace_proxy_url(fin, origin_url);
+        let result = match Self::head_follow(&url).await {
+            Ok(result) => result,
+            Err(e) => return FOut::new_empty().set_error(e),
+        };
+        if !http_status_is_ok(result.rsp.status) {
+            return FOut::new_empty().set_error(Box::new(DirectUrlError {
+                message: format!("HEAD {} returned {}", url, result.rsp.status),
+            }));
+        }
+        let Some(version_number) = Self::get_version(&result, regex.as_ref()) else {
+            return FOut::new_empty().set_error(Box::new(DirectUrlError {
+                message: format!("no version, ETag or Last-Modified for {}", url),
+            }));
+        };
+
+        let file_name = Self::get_disposition_filename(&result.rsp)
+            .or_else(|| {
+                result
+                    .location
+                    .as_deref()
+                    .unwrap_or(origin_url)
+                    .rsplit('/')
+                    .next()
+                    .map(|name| name.to_string())
+            })
+            .unwrap_or_default();
+        let file_type = result
+            .rsp
+            .headers
+            .get("content-type")
+            .cloned()
+            .unwrap_or_default();
+        let extra = [
+            (ETAG_KEY, result.rsp.headers.get("etag")),
+            (LAST_MODIFIED_KEY, result.rsp.headers.get("last-modified")),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value?.clone())))
+        .collect::<HashMap<String, String>>();
+        FOut::new(vec![ReleaseData {
+            version_number,
+            changelog: "".to_string(),
+            assets: vec![AssetData {
+                file_name,
+                file_type,
+                download_url: origin_url.to_string(),
+                size: None,
+                extra: None,
+            }],
+            extra: Some(extra).filter(|extra| !extra.is_empty()),
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+
+    async fn get_version(app_data: &AppDataMap<'_>) -> String {
+        let hub_data = HubDataMap::new();
+        DirectUrlProvider::new()
+            .get_latest_release(&FIn::new_with_frag(app_data, &hub_data, None))
+            .await
+            .result
+            .unwrap()
+            .version_number
+    }
+
+    #[tokio::test]
+    async fn test_etag_version() {
+        let mut server = Server::new_async().await;
+        let url = format!("{}/tool/latest/tool-linux-amd64", server.url());
+        let app_data = AppDataMap::from([(URL_KEY, url.as_str())]);
+
+        let m = server
+            .mock("HEAD", "/tool/latest/tool-linux-amd64")
+            .with_status(200)
+            .with_header("etag", "\"abc123\"")
+            .with_header("last-modified", "Wed, 01 May 2024 00:00:00 GMT")
+            .create_async()
+            .await;
+        assert_eq!(get_version(&app_data).await, "abc123");
+        // Unchanged validators keep the version stable
+        assert_eq!(get_version(&app_data).await, "abc123");
+        m.remove_async().await;
+
+        let _m = server
+            .mock("HEAD", "/tool/latest/tool-linux-amd64")
+            .with_status(200)
+            .with_header("etag", "W/\"def456\"")
+            .create_async()
+            .await;
+        assert_eq!(get_version(&app_data).await, "def456");
+    }
+
+    #[tokio::test]
+    async fn test_last_modified_version() {
+        let mut server = Server::new_async().await;
+        let url = format!("{}/tool/latest/tool-linux-amd64", server.url());
+        let app_data = AppDataMap::from([(URL_KEY, url.as_str())]);
+        let _m = server
+            .mock("HEAD", "/tool/latest/tool-linux-amd64")
+            .with_status(200)
+            .with_header("last-modified", "Wed, 01 May 2024 00:00:00 GMT")
+            .create_async()
+            .await;
+        assert_eq!(get_version(&app_data).await, "1714521600");
+    }
+
+    #[tokio::test]
+    async fn test_redirect_version() {
+        let mut server = Server::new_async().await;
+        let url = format!("{}/tool/latest/tool-linux-amd64", server.url());
+        let _m = server
+            .mock("HEAD", "/tool/latest/tool-linux-amd64")
+            .with_status(302)
+            .with_header("location", "/tool/v1.2.3/tool-1.2.3-linux-amd64")
+            .create_async()
+            .await;
+        let _m = server
+            .mock("HEAD", "/tool/v1.2.3/tool-1.2.3-linux-amd64")
+            .with_status(200)
+            .with_header("etag", "\"abc123\"")
+            .create_async()
+            .await;
+
+        let app_data = AppDataMap::from([(URL_KEY, url.as_str())]);
+        let hub_data = HubDataMap::new();
+        let release = DirectUrlProvider::new()
+            .get_latest_release(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(release.version_number, "1.2.3");
+        assert_eq!(release.assets[0].file_name, "tool-1.2.3-linux-amd64");
+        assert_eq!(release.assets[0].download_url, url);
+
+        let app_data = AppDataMap::from([
+            (URL_KEY, url.as_str()),
+            (URL_VERSION_REGEX_KEY, r"tool-(\d+\.\d+)"),
+        ]);
+        assert_eq!(get_version(&app_data).await, "1.2");
+    }
+
+    #[tokio::test]
+    async fn test_content_disposition_version() {
+        let mut server = Server::new_async().await;
+        let url = format!("{}/download", server.url());
+        let _m = server
+            .mock("HEAD", "/download")
+            .with_status(200)
+            .with_header(
+                "content-disposition",
+                "attachment; filename=\"tool-2.0.1.tar.gz\"",
+            )
+            .with_header("etag", "\"abc123\"")
+            .create_async()
+            .await;
+        let app_data = AppDataMap::from([(URL_KEY, url.as_str())]);
+        assert_eq!(get_version(&app_data).await, "2.0.1");
+    }
+}
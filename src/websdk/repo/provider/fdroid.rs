@@ -2,12 +2,13 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use quick_xml::events::Event;
 use quick_xml::Reader;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 
 use crate::utils::http::{get, head, http_status_is_ok};
 
 use super::super::data::release::*;
+use super::super::data::search::SearchResultData;
 use super::base_provider::*;
 
 const FDROID_URL: &str = "https://f-droid.org";
@@ -38,93 +39,215 @@ impl BaseProviderExt for FDroidProvider {}
 
 #[async_trait]
 impl BaseProvider for FDroidProvider {
+    fn required_app_keys(&self) -> &'static [&'static str] {
+        &[ANDROID_APP_TYPE]
+    }
+
     fn get_cache_request_key(
         &self,
         function_type: &FunctionType,
         data_map: &DataMap,
     ) -> Vec<String> {
         let (url, api_url) = FDroidProvider::get_urls(data_map);
-        let id_map = data_map.app_data;
         match function_type {
-            FunctionType::CheckAppAvailable => vec![format!(
-                "{}/packages/{}/HEAD",
-                url, id_map[ANDROID_APP_TYPE]
-            )],
+            FunctionType::CheckAppAvailable => match data_map.get_app_value(ANDROID_APP_TYPE) {
+                Ok(package_id) => vec![format!("{}/packages/{}/HEAD", url, package_id)],
+                Err(_) => vec![],
+            },
             FunctionType::GetLatestRelease | FunctionType::GetReleases => vec![api_url.to_string()],
         }
     }
 
     async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
         let (url, _) = FDroidProvider::get_urls(&fin.data_map);
-        let id_map = fin.data_map.app_data;
-        let package_id = id_map[ANDROID_APP_TYPE];
+        let package_id = match fin.data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => package_id,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
         let api_url = format!("{}/packages/{}", url, package_id);
         let api_url = self.replace_proxy_url(fin, &api_url);
 
-        if let Ok(parsed_url) = api_url.parse() {
-            if let Ok(rsp) = head(parsed_url, &HashMap::new()).await {
-                return FOut::new(http_status_is_ok(rsp.status));
-            }
+        let Ok(parsed_url) = api_url.parse() else {
+            return FOut::new_empty()
+                .set_parse_error(&fin.data_map, ParseError::Url { url: api_url });
+        };
+        if let Ok(rsp) = head(parsed_url, &HashMap::new()).await {
+            return FOut::new(http_status_is_ok(rsp.status));
         }
         FOut::new_empty()
     }
 
     async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
         let (url, api_url) = FDroidProvider::get_urls(&fin.data_map);
-        let id_map = fin.data_map.app_data;
-        let package_id = id_map[ANDROID_APP_TYPE];
+        let package_id = match fin.data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => package_id,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
         let api_url = self.replace_proxy_url(fin, &api_url);
-        let cache_key = self
-            .get_cache_request_key(&FunctionType::GetReleases, &fin.data_map)
-            .first()
-            .unwrap()
-            .clone();
-        let mut cache_map_fout = CacheMap::new();
-        let index_cache = fin.get_cache(&cache_key);
-        let mut index: Option<Bytes> = None;
-        if let Some(i) = index_cache {
-            index = Some(i.clone());
-        } else if let Ok(parsed_url) = api_url.parse() {
-            if let Ok(rsp) = get(parsed_url, &HashMap::new()).await {
-                index = rsp.body;
-                cache_map_fout.insert(cache_key.to_string(), index.clone().unwrap());
-            }
+        let (index, fout) = self.get_index(fin).await;
+        let Some(index) = index else {
+            return fout;
         };
-        if index.is_none() {
-            return FOut::new_empty();
-        }
         let mut releases_fout = Vec::new();
-        if let Ok(content) = std::str::from_utf8(&index.unwrap()) {
-            let mut reader = Reader::from_str(content.trim());
-            loop {
-                let (xml_package_id, releases) =
-                    FDroidProvider::get_releases_from_xml(&mut reader, &url)
-                        .await
-                        .unwrap();
-                if xml_package_id == package_id {
-                    releases_fout = releases;
-                }
-                if xml_package_id.is_empty() {
-                    break;
+        let mut parse_error = None;
+        match std::str::from_utf8(&index) {
+            Ok(content) => {
+                let mut reader = Reader::from_str(content.trim());
+                loop {
+                    let (xml_package_id, releases) =
+                        match FDroidProvider::get_releases_from_xml(&mut reader, &url).await {
+                            Ok(result) => result,
+                            Err(e) => return FOut::new_empty().set_error(e),
+                        };
+                    if xml_package_id == package_id {
+                        releases_fout = releases;
+                    }
+                    if xml_package_id.is_empty() {
+                        break;
+                    }
                 }
             }
+            Err(e) => {
+                parse_error = Some(ParseError::Body {
+                    url: api_url,
+                    reason: e.to_string(),
+                })
+            }
         }
-        let mut fout = FOut::new(releases_fout);
-        if !cache_map_fout.is_empty() {
-            fout = fout.set_cached_map(cache_map_fout);
+        let mut fout = fout.set_data(releases_fout);
+        if let Some(error) = parse_error {
+            fout = fout.set_parse_error(&fin.data_map, error);
         }
         fout
     }
+
+    fn get_search_cache_request_key(&self, data_map: &DataMap) -> Vec<String> {
+        let (_, api_url) = FDroidProvider::get_urls(data_map);
+        vec![api_url]
+    }
+
+    /// Matches `query` case-insensitively against the package id, name and
+    /// summary of every app in the index.
+    async fn search_apps(&self, fin: &FIn, query: &str) -> FOut<Vec<SearchResultData>> {
+        let (index, fout) = self.get_index(fin).await;
+        let Some(index) = index else {
+            return fout;
+        };
+        let content = match std::str::from_utf8(&index) {
+            Ok(content) => content,
+            Err(e) => {
+                let (_, api_url) = FDroidProvider::get_urls(&fin.data_map);
+                return fout.set_data(vec![]).set_parse_error(
+                    &fin.data_map,
+                    ParseError::Body {
+                        url: self.replace_proxy_url(fin, &api_url),
+                        reason: e.to_string(),
+                    },
+                );
+            }
+        };
+        match FDroidProvider::search_index_xml(content, query) {
+            Ok(results) => fout.set_data(results),
+            Err(e) => fout.set_error(e),
+        }
+    }
 }
 
 type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
 
 impl FDroidProvider {
+    /// The repo index, from the cache or fetched. A fetched index is set in
+    /// the returned FOut to be cached.
+    async fn get_index<T>(&self, fin: &FIn<'_>) -> (Option<Bytes>, FOut<T>) {
+        let (_, api_url) = FDroidProvider::get_urls(&fin.data_map);
+        // Cached under the origin URL, whichever proxy fetched it
+        if let Some(index) = fin.get_cache(&api_url) {
+            return (Some(index.clone()), FOut::new_empty());
+        }
+        let url = self.replace_proxy_url(fin, &api_url);
+        let Ok(parsed_url) = url.parse() else {
+            return (
+                None,
+                FOut::new_empty().set_parse_error(&fin.data_map, ParseError::Url { url }),
+            );
+        };
+        match get(parsed_url, &HashMap::new()).await {
+            Ok(rsp) => {
+                let policy = rsp.cache_policy();
+                match rsp.body {
+                    Some(index) => (
+                        Some(index.clone()),
+                        FOut::new_empty()
+                            .set_cache(&api_url, index)
+                            .set_cache_policy(&api_url, policy),
+                    ),
+                    None => (None, FOut::new_empty()),
+                }
+            }
+            Err(_) => (None, FOut::new_empty()),
+        }
+    }
+
+    fn search_index_xml(content: &str, query: &str) -> Result<Vec<SearchResultData>> {
+        let query = query.to_lowercase();
+        let mut reader = Reader::from_str(content.trim());
+        let mut results = vec![];
+        // id, name and summary of the application being read
+        let mut app: Option<(String, String, String)> = None;
+        let mut current_tag = vec![];
+        loop {
+            match reader.read_event()? {
+                Event::Eof => break,
+                Event::Start(e) => {
+                    if e.name().as_ref() == b"application" {
+                        app = Some(Default::default());
+                    }
+                    current_tag = e.name().as_ref().to_vec();
+                }
+                Event::End(e) => {
+                    current_tag.clear();
+                    if e.name().as_ref() != b"application" {
+                        continue;
+                    }
+                    let Some((id, name, summary)) = app.take() else {
+                        continue;
+                    };
+                    let (name, summary) = (name.trim(), summary.trim());
+                    if [id.as_str(), name, summary]
+                        .iter()
+                        .any(|field| field.to_lowercase().contains(&query))
+                    {
+                        results.push(SearchResultData {
+                            name: if name.is_empty() { &id } else { name }.to_string(),
+                            description: summary.to_string(),
+                            app_data: BTreeMap::from([(ANDROID_APP_TYPE.to_string(), id)]),
+                        });
+                    }
+                }
+                Event::Text(e) => {
+                    let Some((id, name, summary)) = app.as_mut() else {
+                        continue;
+                    };
+                    let text = e.unescape()?;
+                    match current_tag.as_slice() {
+                        b"id" => id.push_str(&text),
+                        b"name" => name.push_str(&text),
+                        b"summary" => summary.push_str(&text),
+                        _ => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(results)
+    }
+
     async fn decode_package_xml(reader: &mut Reader<&[u8]>, url: &str) -> Result<ReleaseData> {
         let xml_key = b"package";
         let mut version_number = String::new();
         let mut changelog = String::new();
         let mut file_name = String::new();
+        let mut size = None;
         let mut extra = HashMap::new();
 
         let mut current_tag = String::new();
@@ -151,6 +274,7 @@ impl FDroidProvider {
                                 extra.insert(current_tag.clone(), text.to_string());
                             }
                             "apkname" => file_name += &text,
+                            "size" => size = text.trim().parse().ok(),
                             _ => (),
                         }
                     }
@@ -169,6 +293,8 @@ impl FDroidProvider {
                 file_name,
                 file_type,
                 download_url,
+                size,
+                extra: None,
             }],
             extra,
         })
@@ -247,6 +373,7 @@ impl FDroidProvider {
 
 #[cfg(test)]
 mod tests {
+    use super::super::conformance::{is_parse_error, run_conformance, Fixture};
     use super::*;
     use mockito::Server;
     use std::fs;
@@ -354,4 +481,75 @@ mod tests {
         assert!(!releases.is_empty());
         assert_eq!(releases[0].assets[0].file_type, "zip");
     }
+
+    #[tokio::test]
+    async fn test_search_apps() {
+        let body = fs::read("tests/files/web/f-droid.xml").unwrap();
+        let app_data = AppDataMap::new();
+        let hub_data = HubDataMap::new();
+        let api_url = FDroidProvider::get_api_url(FDROID_URL);
+        let cache_map = CacheMap::from([(api_url, Bytes::from(body))]);
+        let fin = FIn::new_with_frag(&app_data, &hub_data, Some(cache_map));
+        let provider = FDroidProvider::new();
+        let results = provider.search_apps(&fin, "KLOONI").await.result.unwrap();
+        assert_eq!(
+            results,
+            vec![SearchResultData {
+                name: "1010! Klooni".to_string(),
+                description: "A libGDX game based on 1010".to_string(),
+                app_data: BTreeMap::from([(
+                    ANDROID_APP_TYPE.to_string(),
+                    "dev.lonami.klooni".to_string()
+                )]),
+            }]
+        );
+        // Summaries are searched too
+        let results = provider
+            .search_apps(&fin, "check your internet speed")
+            .await;
+        assert!(results
+            .result
+            .unwrap()
+            .iter()
+            .any(|result| result.app_data[ANDROID_APP_TYPE] == "com.dosse.speedtest"));
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        let body = fs::read("tests/files/web/f-droid.xml").unwrap();
+        run_conformance(
+            FDroidProvider::new(),
+            Fixture {
+                app_data: vec![(ANDROID_APP_TYPE, "org.fdroid.fdroid.privileged")],
+                hub_data: vec![],
+                responses: vec![("/repo/index.xml", body.into())],
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_strict_parsing() {
+        let app_data = AppDataMap::from([(ANDROID_APP_TYPE, "org.fdroid.fdroid")]);
+        let cache_key = FDroidProvider::get_api_url(FDROID_URL);
+        let provider = &FDroidProvider::new();
+        for strict in [false, true] {
+            let hub_data =
+                HubDataMap::from([(STRICT_PARSING, if strict { "true" } else { "false" })]);
+            let get_releases = |body: &'static [u8]| {
+                let cache_map = HashMap::from([(cache_key.clone(), Bytes::from(body))]);
+                let fin = FIn::new_with_frag(&app_data, &hub_data, Some(cache_map));
+                async move { provider.get_releases(&fin).await }
+            };
+
+            let fout = get_releases(b"<fdroid>\xff</fdroid>").await;
+            assert_eq!(is_parse_error(&fout), strict);
+            if !strict {
+                assert!(fout.result.unwrap().is_empty());
+            }
+            // The package isn't in the repo
+            let fout = get_releases(b"<fdroid></fdroid>").await;
+            assert!(fout.result.unwrap().is_empty());
+        }
+    }
 }
@@ -0,0 +1,342 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::utils::http::get;
+
+use super::super::data::release::*;
+use super::base_provider::*;
+
+pub const FDROID_REPO_HUB_UUID: &str = "a54b4863-3825-4195-9f96-0fb9787f9160";
+
+const INDEX_FILE_NAME: &str = "index-v1.json";
+const CHANGELOG_LOCALE: &str = "en-US";
+
+#[derive(Debug)]
+pub struct FDroidRepoError {
+    pub message: String,
+}
+
+impl std::fmt::Display for FDroidRepoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "FDroidRepoError: {}", self.message)
+    }
+}
+
+impl std::error::Error for FDroidRepoError {}
+
+#[derive(Deserialize)]
+struct Index {
+    #[serde(default)]
+    apps: Vec<Value>,
+    #[serde(default)]
+    packages: HashMap<String, Vec<Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexPackage {
+    apk_name: String,
+    version_name: String,
+    version_code: Option<u64>,
+    size: Option<u64>,
+    #[serde(default)]
+    nativecode: Vec<String>,
+}
+
+/// Any F-Droid compatible repo (IzzyOnDroid and the like) through its
+/// index-v1.json.
+///
+/// hub_data `repo_url` is either the repo base, which APK names are
+/// relative to, or the index URL itself.
+pub struct FDroidRepoProvider;
+
+impl FDroidRepoProvider {
+    pub fn new() -> Self {
+        FDroidRepoProvider {}
+    }
+
+    /// The repo base and index URLs.
+    fn get_urls(data_map: &DataMap) -> Result<(String, String), FDroidRepoError> {
+        let repo_url = data_map
+            .hub_data
+            .get(KEY_REPO_URL)
+            .ok_or_else(|| FDroidRepoError {
+                message: format!("hub_data has no {}", KEY_REPO_URL),
+            })?
+            .trim_end_matches('/');
+        Ok(match repo_url.strip_suffix(INDEX_FILE_NAME) {
+            Some(base_url) => (
+                base_url.trim_end_matches('/').to_string(),
+                repo_url.to_string(),
+            ),
+            None => (
+                repo_url.to_string(),
+                format!("{}/{}", repo_url, INDEX_FILE_NAME),
+            ),
+        })
+    }
+
+    /// The index body, from the cache or fetched, with the FOut carrying
+    /// the fetched body for caching.
+    async fn get_index<T>(&self, fin: &FIn<'_>, index_url: &str) -> (Option<Bytes>, FOut<T>) {
+        let fout = FOut::new_empty();
+        // Cached under the origin URL, whichever proxy fetched it
+        if let Some(body) = fin.get_cache(index_url) {
+            return (Some(body.clone()), fout);
+        }
+        let url = self.replace_proxy_url(fin, index_url);
+        let Ok(parsed_url) = url.parse() else {
+            return (
+                None,
+                fout.set_parse_error(&fin.data_map, ParseError::Url { url }),
+            );
+        };
+        match get(parsed_url, &HashMap::new()).await {
+            Ok(rsp) => {
+                let policy = rsp.cache_policy();
+                match rsp.body {
+                    Some(body) => (
+                        Some(body.clone()),
+                        fout.set_cache(index_url, body)
+                            .set_cache_policy(index_url, policy),
+                    ),
+                    None => (None, fout),
+                }
+            }
+            Err(_) => (None, fout),
+        }
+    }
+
+    fn get_changelog(index: &Index, package_id: &str) -> String {
+        index
+            .apps
+            .iter()
+            .find(|app| app.get("packageName").and_then(|v| v.as_str()) == Some(package_id))
+            .and_then(|app| app.pointer(&format!("/localized/{}/whatsNew", CHANGELOG_LOCALE)))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn to_release(base_url: &str, package: IndexPackage) -> ReleaseData {
+        let mut extra = HashMap::new();
+        if let Some(version_code) = package.version_code {
+            extra.insert("versionCode".to_string(), version_code.to_string());
+        }
+        if !package.nativecode.is_empty() {
+            extra.insert("nativecode".to_string(), package.nativecode.join(","));
+        }
+        ReleaseData {
+            version_number: package.version_name,
+            changelog: "".to_string(),
+            assets: vec![AssetData {
+                file_type: package
+                    .apk_name
+                    .rsplit('.')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                download_url: format!("{}/{}", base_url, package.apk_name),
+                file_name: package.apk_name,
+                size: package.size,
+                extra: None,
+            }],
+            extra: Some(extra).filter(|extra| !extra.is_empty()),
+        }
+    }
+}
+
+impl BaseProviderExt for FDroidRepoProvider {}
+
+#[async_trait]
+impl BaseProvider for FDroidRepoProvider {
+    fn required_app_keys(&self) -> &'static [&'static str] {
+        &[ANDROID_APP_TYPE]
+    }
+
+    fn get_cache_request_key(
+        &self,
+        _function_type: &FunctionType,
+        data_map: &DataMap,
+    ) -> Vec<String> {
+        match Self::get_urls(data_map) {
+            Ok((_, index_url)) => vec![index_url],
+            Err(_) => vec![],
+        }
+    }
+
+    async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
+        let package_id = match fin.data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => package_id,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let (_, index_url) = match Self::get_urls(&fin.data_map) {
+            Ok(urls) => urls,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let (body, fout) = self.get_index(fin, &index_url).await;
+        let Some(body) = body else {
+            return fout;
+        };
+        match serde_json::from_slice::<Index>(&body) {
+            Ok(index) => fout.set_data(index.packages.contains_key(package_id)),
+            Err(e) => fout.set_data(false).set_parse_error(
+                &fin.data_map,
+                ParseError::Body {
+                    url: index_url,
+                    reason: e.to_string(),
+                },
+            ),
+        }
+    }
+
+    async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
+        let package_id = match fin.data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => package_id,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let (base_url, index_url) = match Self::get_urls(&fin.data_map) {
+            Ok(urls) => urls,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let (body, fout) = self.get_index(fin, &index_url).await;
+        let Some(body) = body else {
+            return fout;
+        };
+        let mut index = match serde_json::from_slice::<Index>(&body) {
+            Ok(index) => index,
+            Err(e) => {
+                return fout.set_parse_error(
+                    &fin.data_map,
+                    ParseError::Body {
+                        url: index_url,
+                        reason: e.to_string(),
+                    },
+                )
+            }
+        };
+        let packages = index.packages.remove(package_id).unwrap_or_default();
+        let count = packages.len();
+        let mut packages = packages
+            .into_iter()
+            .filter_map(|package| serde_json::from_value::<IndexPackage>(package).ok())
+            .collect::<Vec<_>>();
+        // Newest first, whatever order the index lists them in
+        packages.sort_by_key(|package| std::cmp::Reverse(package.version_code));
+        let mut releases = packages
+            .into_iter()
+            .map(|package| Self::to_release(&base_url, package))
+            .collect::<Vec<_>>();
+        if let Some(release) = releases.first_mut() {
+            release.changelog = Self::get_changelog(&index, package_id);
+        }
+        let all_dropped = releases.is_empty() && count > 0;
+        let fout = fout.set_data(releases);
+        if all_dropped {
+            fout.set_parse_error(
+                &fin.data_map,
+                ParseError::AllDropped {
+                    url: index_url,
+                    count,
+                },
+            )
+        } else {
+            fout
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::conformance::{run_conformance, Fixture};
+    use super::*;
+    use mockito::Server;
+    use std::fs;
+
+    const REPO_URL: &str = "https://apt.izzysoft.de/fdroid/repo";
+
+    #[test]
+    fn test_get_urls() {
+        let app_data = AppDataMap::new();
+        for repo_url in [
+            "https://apt.izzysoft.de/fdroid/repo",
+            "https://apt.izzysoft.de/fdroid/repo/",
+            "https://apt.izzysoft.de/fdroid/repo/index-v1.json",
+        ] {
+            let hub_data = HubDataMap::from([(KEY_REPO_URL, repo_url)]);
+            let data_map = DataMap {
+                app_data: &app_data,
+                hub_data: &hub_data,
+            };
+            assert_eq!(
+                FDroidRepoProvider::get_urls(&data_map).unwrap(),
+                (REPO_URL.to_string(), format!("{}/index-v1.json", REPO_URL))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_releases() {
+        let body = fs::read_to_string("tests/files/web/fdroid_index-v1.json").unwrap();
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/fdroid/repo/index-v1.json")
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let app_data = AppDataMap::from([(ANDROID_APP_TYPE, "com.example.notes")]);
+        let proxy_url = format!("https://apt.izzysoft.de -> {}", server.url());
+        let hub_data = HubDataMap::from([
+            (KEY_REPO_URL, REPO_URL),
+            (REVERSE_PROXY, proxy_url.as_str()),
+        ]);
+        let provider = FDroidRepoProvider::new();
+        let releases = provider
+            .get_releases(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+
+        let release_json =
+            fs::read_to_string("tests/files/data/provider_fdroid_repo_releases.json").unwrap();
+        let releases_saved = serde_json::from_str::<Vec<ReleaseData>>(&release_json).unwrap();
+        assert_eq!(releases, releases_saved);
+    }
+
+    #[tokio::test]
+    async fn test_check_app_available() {
+        let body = fs::read("tests/files/web/fdroid_index-v1.json").unwrap();
+        let hub_data = HubDataMap::from([(KEY_REPO_URL, REPO_URL)]);
+        let provider = FDroidRepoProvider::new();
+        for (package_id, available) in [("org.example.weather", true), ("nonexist", false)] {
+            let app_data = AppDataMap::from([(ANDROID_APP_TYPE, package_id)]);
+            let cache_map = HashMap::from([(
+                format!("{}/index-v1.json", REPO_URL),
+                Bytes::from(body.clone()),
+            )]);
+            let fout = provider
+                .check_app_available(&FIn::new_with_frag(&app_data, &hub_data, Some(cache_map)))
+                .await;
+            assert_eq!(fout.result.unwrap(), available);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        let body = fs::read("tests/files/web/fdroid_index-v1.json").unwrap();
+        run_conformance(
+            FDroidRepoProvider::new(),
+            Fixture {
+                app_data: vec![(ANDROID_APP_TYPE, "com.example.notes")],
+                hub_data: vec![(KEY_REPO_URL, REPO_URL)],
+                responses: vec![("/fdroid/repo/index-v1.json", body.into())],
+            },
+        )
+        .await;
+    }
+}
@@ -1,13 +1,14 @@
 use async_trait::async_trait;
-use bytes::Bytes;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use super::super::data::release::*;
+use super::super::data::search::SearchResultData;
 use super::base_provider::*;
 
 use crate::utils::{
-    http::{get, head, http_status_is_ok},
+    convert::percent_encode,
+    http::{get, get_conditional, head, http_status_is_ok},
     versioning::Version,
 };
 
@@ -16,6 +17,11 @@ const GITHUB_URL: &str = "https://github.com";
 
 const VERSION_NUMBER_KEY: &str = "version_number_key";
 const VERSION_CODE_KEY: &str = "version_code_key";
+const INCLUDE_DRAFTS_KEY: &str = "include_drafts";
+const MAX_RELEASES_KEY: &str = "max_releases";
+
+// The most the releases endpoint returns per page
+const MAX_PER_PAGE: usize = 100;
 
 pub struct GitHubProvider;
 
@@ -23,143 +29,359 @@ impl GitHubProvider {
     pub fn new() -> Self {
         GitHubProvider {}
     }
+
+    fn get_owner_repo<'a>(data_map: &DataMap<'a>) -> Result<(&'a str, &'a str), MissingKeyError> {
+        Ok((
+            data_map.get_app_value("owner")?,
+            data_map.get_app_value("repo")?,
+        ))
+    }
+
+    /// `max_releases` from app_data first, then hub_data, with the page
+    /// size that reaches it in the fewest requests.
+    fn get_paging(data_map: &DataMap) -> Option<(usize, usize)> {
+        let max_releases = data_map
+            .app_data
+            .get(MAX_RELEASES_KEY)
+            .or_else(|| data_map.hub_data.get(MAX_RELEASES_KEY))?
+            .parse::<usize>()
+            .ok()
+            .filter(|max_releases| *max_releases > 0)?;
+        Some((max_releases, max_releases.min(MAX_PER_PAGE)))
+    }
+
+    /// Headers for a request to `url`, with the token if there is one.
+    fn get_api_header_map(&self, fin: &FIn, url: &str) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert("User-Agent".to_string(), "Awesome-Octocat-App".to_string());
+        if let Some(credential) = self.get_credential(fin, &["GITHUB_TOKEN", "GH_TOKEN"], url) {
+            map.insert(
+                "Authorization".to_string(),
+                format!("Bearer {}", credential.token),
+            );
+        }
+        map
+    }
+
+    /// Release list URLs, one per page. Without `max_releases` that is the
+    /// bare endpoint and GitHub's default page.
+    fn get_release_page_urls(data_map: &DataMap, owner: &str, repo: &str) -> Vec<String> {
+        let url = format!("{}/repos/{}/{}/releases", GITHUB_API_URL, owner, repo);
+        match Self::get_paging(data_map) {
+            Some((max_releases, per_page)) => (1..=max_releases.div_ceil(per_page))
+                .map(|page| format!("{}?per_page={}&page={}", url, per_page, page))
+                .collect(),
+            None => vec![url],
+        }
+    }
 }
 
 impl BaseProviderExt for GitHubProvider {}
 
 #[async_trait]
 impl BaseProvider for GitHubProvider {
+    fn required_app_keys(&self) -> &'static [&'static str] {
+        &["owner", "repo"]
+    }
+
+    fn repo_path_keys(&self) -> &'static [&'static str] {
+        &["owner", "repo"]
+    }
+
+    fn repo_path_case_insensitive(&self) -> bool {
+        true
+    }
+
+    fn merges_duplicate_versions(&self) -> bool {
+        true
+    }
+
     fn get_cache_request_key(
         &self,
         function_type: &FunctionType,
         data_map: &DataMap,
     ) -> Vec<String> {
-        let id_map = data_map.app_data;
+        let Ok((owner, repo)) = Self::get_owner_repo(data_map) else {
+            return vec![];
+        };
         match function_type {
-            FunctionType::CheckAppAvailable => vec![format!(
-                "{}/{}/{}/HEAD",
-                GITHUB_URL, id_map["owner"], id_map["repo"]
-            )],
-            FunctionType::GetLatestRelease | FunctionType::GetReleases => vec![format!(
-                "{}/repos/{}/{}/releases",
-                GITHUB_API_URL, id_map["owner"], id_map["repo"]
-            )],
+            FunctionType::CheckAppAvailable => {
+                vec![format!("{}/{}/{}/HEAD", GITHUB_URL, owner, repo)]
+            }
+            FunctionType::GetLatestRelease | FunctionType::GetReleases => {
+                Self::get_release_page_urls(data_map, owner, repo)
+            }
         }
     }
 
     async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
-        let id_map = fin.data_map.app_data;
-        let api_url = format!("{}/{}/{}", GITHUB_URL, id_map["owner"], id_map["repo"]);
+        let (owner, repo) = match Self::get_owner_repo(&fin.data_map) {
+            Ok(owner_repo) => owner_repo,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let api_url = format!("{}/{}/{}", GITHUB_URL, owner, repo);
         let api_url = self.replace_proxy_url(fin, &api_url);
 
-        if let Ok(parsed_url) = api_url.parse() {
-            if let Ok(rsp) = head(parsed_url, &HashMap::new()).await {
-                return FOut::new(http_status_is_ok(rsp.status));
-            }
+        let Ok(parsed_url) = api_url.parse() else {
+            return FOut::new_empty()
+                .set_parse_error(&fin.data_map, ParseError::Url { url: api_url });
+        };
+        if let Ok(rsp) = head(parsed_url, &HashMap::new()).await {
+            return FOut::new(http_status_is_ok(rsp.status));
         }
         FOut::new_empty()
     }
 
     async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
-        let id_map = fin.data_map.app_data;
-        let url = format!(
-            "{}/repos/{}/{}/releases",
-            GITHUB_API_URL, id_map["owner"], id_map["repo"]
-        );
-        let url = self.replace_proxy_url(fin, &url);
+        let (owner, repo) = match Self::get_owner_repo(&fin.data_map) {
+            Ok(owner_repo) => owner_repo,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let tag_filter = match fin.data_map.get_tag_filter() {
+            Ok(tag_filter) => tag_filter,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        // Cached under the origin URLs, whichever proxy fetched them
+        let page_keys = Self::get_release_page_urls(&fin.data_map, owner, repo);
+        let paging = Self::get_paging(&fin.data_map);
+        let url = self.replace_proxy_url(fin, &page_keys[0]);
+        let header_map = self.get_api_header_map(fin, &page_keys[0]);
         let mut fout = FOut::new_empty();
-        let cache_body = fin.get_cache(&url);
-        let mut rsp_body = None;
-        if cache_body.is_none() {
-            if let Ok(parsed_url) = url.parse() {
-                let header_map = {
-                    let mut map = HashMap::new();
-                    map.insert("User-Agent".to_string(), "Awesome-Octocat-App".to_string());
-                    map
+        let mut cached_map = HashMap::new();
+        let mut data = vec![];
+        let mut body_error = None;
+        for cache_key in page_keys.iter() {
+            let page_url = self.replace_proxy_url(fin, cache_key);
+            let mut has_next = true;
+            let body = if let Some(content) = fin.get_cache(cache_key) {
+                content.clone()
+            } else {
+                let Ok(parsed_url) = page_url.parse() else {
+                    return fout.set_parse_error(&fin.data_map, ParseError::Url { url: page_url });
+                };
+                // An expired page is revalidated, which GitHub doesn't count
+                // against the rate limit when it comes back 304
+                let stale = fin.get_stale_cache(cache_key);
+                let rsp = get_conditional(parsed_url, &header_map, stale.map(|s| &s.validators))
+                    .await
+                    .ok();
+                let Some((content, rsp)) = rsp.and_then(|rsp| match stale {
+                    Some(stale) if rsp.is_not_modified() => Some((stale.body.clone(), rsp)),
+                    _ => Some((rsp.body.clone()?, rsp)),
+                }) else {
+                    // A partial list would pass for the whole history
+                    return fout.set_cached_map(cached_map);
                 };
-                if let Ok(rsp) = get(parsed_url, &header_map).await {
-                    if let Some(content) = rsp.body {
-                        rsp_body = Some(content);
+                has_next = match rsp.headers.get("link") {
+                    Some(link) => link.contains("rel=\"next\""),
+                    None => true,
+                };
+                // A 304 may leave out validators that still hold
+                let validators = rsp.validators().or_else(|| {
+                    stale
+                        .filter(|_| rsp.is_not_modified())
+                        .map(|s| s.validators.clone())
+                });
+                fout = fout
+                    .set_cache_policy(cache_key, rsp.cache_policy())
+                    .set_validators(cache_key, validators);
+                cached_map.insert(cache_key.clone(), content.clone());
+                content
+            };
+            match serde_json::from_slice::<Vec<Value>>(&body) {
+                Ok(page) => {
+                    let full_page = paging.is_some_and(|(_, per_page)| page.len() >= per_page);
+                    data.extend(page);
+                    if !full_page || !has_next {
+                        break;
                     }
                 }
+                Err(e) => {
+                    body_error = Some(ParseError::Body {
+                        url: page_url,
+                        reason: e.to_string(),
+                    });
+                    break;
+                }
             }
         }
-
-        let body: &Bytes;
-        if let Some(ref content) = rsp_body {
-            body = content;
-        } else if let Some(content) = cache_body {
-            body = content;
-        } else {
-            return fout;
+        if let Some((max_releases, _)) = paging {
+            data.truncate(max_releases);
         }
 
-        if let Ok(data) = serde_json::from_slice::<Vec<Value>>(body) {
-            let release_list = data
-                .iter()
-                .filter_map(|json| {
-                    let assets_data = match json.get("assets") {
-                        Some(assets) => assets
-                            .as_array()?
-                            .iter()
-                            .filter_map(|asset| {
-                                let file_name = asset.get("name")?.as_str()?.to_string();
-                                let file_type = asset.get("content_type")?.as_str()?.to_string();
-                                let download_url =
-                                    asset.get("browser_download_url")?.as_str()?.to_string();
-                                Some(AssetData {
-                                    file_name,
-                                    file_type,
-                                    download_url,
+        let include_drafts = fin.data_map.hub_data.get(INCLUDE_DRAFTS_KEY) == Some(&"true");
+        match body_error {
+            None => {
+                let data = data
+                    .iter()
+                    .filter(|json| include_drafts || json.get("draft") != Some(&Value::Bool(true)))
+                    .collect::<Vec<_>>();
+                let release_list = data
+                    .iter()
+                    .filter_map(|json| {
+                        let assets_data = match json.get("assets") {
+                            Some(assets) => assets
+                                .as_array()?
+                                .iter()
+                                .filter_map(|asset| {
+                                    let file_name = asset.get("name")?.as_str()?.to_string();
+                                    let file_type =
+                                        asset.get("content_type")?.as_str()?.to_string();
+                                    let download_url =
+                                        asset.get("browser_download_url")?.as_str()?.to_string();
+                                    let extra = asset
+                                        .get("download_count")
+                                        .and_then(|v| v.as_u64())
+                                        .map(|count| {
+                                            HashMap::from([(
+                                                DOWNLOAD_COUNT_KEY.to_string(),
+                                                count.to_string(),
+                                            )])
+                                        });
+                                    Some(AssetData {
+                                        file_name,
+                                        file_type,
+                                        download_url,
+                                        size: asset.get("size").and_then(|v| v.as_u64()),
+                                        extra,
+                                    })
                                 })
-                            })
-                            .collect(),
-                        None => vec![],
-                    };
-                    let mut keys_to_try = vec!["name", "tag_name"];
-                    if let Some(tag) = fin.data_map.hub_data.get(VERSION_NUMBER_KEY) {
-                        keys_to_try.insert(0, tag);
-                    }
-                    let mut version_number: Option<String> = None;
-
-                    for key in keys_to_try.iter() {
-                        if let Some(value) = json.get(key).and_then(|v| v.as_str()) {
-                            if Version::new(value.to_string()).is_valid() {
-                                version_number = Some(value.to_string());
-                                break;
+                                .collect(),
+                            None => vec![],
+                        };
+                        let mut extra = HashMap::new();
+                        for (key, extra_key) in [
+                            ("tag_name", TAG_NAME_KEY),
+                            ("html_url", RELEASE_URL_KEY),
+                            ("target_commitish", TARGET_COMMITISH_KEY),
+                        ] {
+                            if let Some(value) = json.get(key).and_then(|v| v.as_str()) {
+                                extra.insert(extra_key.to_string(), value.to_string());
                             }
                         }
-                    }
-                    let changelog = json.get("body")?.as_str()?.to_string();
+                        let mut version_number: Option<String> = None;
+                        if let Some(tag_filter) = &tag_filter {
+                            let tag = json.get("tag_name")?.as_str()?;
+                            version_number = Some(tag_filter.version_of(tag)?.to_string());
+                        } else {
+                            let mut keys_to_try = vec!["name", "tag_name"];
+                            if let Some(tag) = fin.data_map.hub_data.get(VERSION_NUMBER_KEY) {
+                                keys_to_try.insert(0, tag);
+                            }
+                            for key in keys_to_try.iter() {
+                                if let Some(value) = json.get(key).and_then(|v| v.as_str()) {
+                                    if Version::new(value.to_string()).is_valid() {
+                                        version_number = Some(value.to_string());
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                        let changelog = json.get("body")?.as_str()?.to_string();
 
-                    let mut extra = None;
-                    if let Some(tag) = fin.data_map.hub_data.get(VERSION_CODE_KEY) {
-                        if let Some(value) = json.get(tag) {
-                            extra = Some(HashMap::from([(tag.to_string(), value.to_string())]));
+                        if let Some(tag) = fin.data_map.hub_data.get(VERSION_CODE_KEY) {
+                            if let Some(value) = json.get(tag) {
+                                extra.insert(tag.to_string(), value.to_string());
+                            }
                         }
-                    }
-                    Some(ReleaseData {
-                        version_number: version_number?.to_string(),
-                        changelog,
-                        assets: assets_data,
-                        extra,
+                        let extra = Some(extra).filter(|extra| !extra.is_empty());
+                        Some(ReleaseData {
+                            version_number: version_number?.to_string(),
+                            changelog,
+                            assets: assets_data,
+                            extra,
+                        })
                     })
-                })
-                .collect::<Vec<ReleaseData>>();
-            fout = fout.set_data(release_list);
-        };
+                    .collect::<Vec<ReleaseData>>();
+                // A tag filter may rightly match none of them
+                let all_dropped =
+                    release_list.is_empty() && !data.is_empty() && tag_filter.is_none();
+                fout = fout.set_data(release_list);
+                if all_dropped {
+                    fout = fout.set_parse_error(
+                        &fin.data_map,
+                        ParseError::AllDropped {
+                            url: url.clone(),
+                            count: data.len(),
+                        },
+                    );
+                }
+            }
+            Some(error) => {
+                fout = fout.set_parse_error(&fin.data_map, error);
+            }
+        }
 
-        if let Some(content) = rsp_body {
-            fout.set_cached_map(HashMap::from([(url, content)]))
-        } else {
+        if cached_map.is_empty() {
             fout
+        } else {
+            fout.set_cached_map(cached_map)
         }
     }
+
+    async fn search_apps(&self, fin: &FIn, query: &str) -> FOut<Vec<SearchResultData>> {
+        let url = format!(
+            "{}/search/repositories?q={}",
+            GITHUB_API_URL,
+            percent_encode(query)
+        );
+        let header_map = self.get_api_header_map(fin, &url);
+        let url = self.replace_proxy_url(fin, &url);
+        let Ok(parsed_url) = url.parse() else {
+            return FOut::new_empty().set_parse_error(&fin.data_map, ParseError::Url { url });
+        };
+        let Some(body) = get(parsed_url, &header_map)
+            .await
+            .ok()
+            .filter(|rsp| http_status_is_ok(rsp.status))
+            .and_then(|rsp| rsp.body)
+        else {
+            return FOut::new_empty();
+        };
+        let items = match serde_json::from_slice::<Value>(&body) {
+            Ok(json) => json
+                .get("items")
+                .and_then(|items| items.as_array())
+                .cloned()
+                .unwrap_or_default(),
+            Err(e) => {
+                return FOut::new(vec![]).set_parse_error(
+                    &fin.data_map,
+                    ParseError::Body {
+                        url,
+                        reason: e.to_string(),
+                    },
+                )
+            }
+        };
+        let results = items
+            .iter()
+            .filter_map(|item| {
+                let owner = item.get("owner")?.get("login")?.as_str()?;
+                let repo = item.get("name")?.as_str()?;
+                Some(SearchResultData {
+                    name: format!("{}/{}", owner, repo),
+                    description: item
+                        .get("description")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    app_data: BTreeMap::from([
+                        ("owner".to_string(), owner.to_string()),
+                        ("repo".to_string(), repo.to_string()),
+                    ]),
+                })
+            })
+            .collect();
+        FOut::new(results)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::conformance::{is_parse_error, run_conformance, Fixture};
     use super::*;
+    use bytes::Bytes;
     use mockito::Server;
     use std::fs;
 
@@ -210,4 +432,365 @@ mod tests {
         let releases_saved = serde_json::from_str::<Vec<ReleaseData>>(&release_json).unwrap();
         assert_eq!(releases, releases_saved)
     }
+
+    #[tokio::test]
+    async fn test_get_releases_not_modified() {
+        let body = fs::read_to_string("tests/files/web/github_api_release.json").unwrap();
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/DUpdateSystem/UpgradeAll/releases")
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_header("etag", "\"abc\"")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let id_map = AppDataMap::from([("owner", "DUpdateSystem"), ("repo", "UpgradeAll")]);
+        let proxy_url = format!("{} -> {}", GITHUB_API_URL, server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        let github_provider = GitHubProvider::new();
+        let fout = github_provider
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, None))
+            .await;
+        m.assert_async().await;
+        let key = format!("{}/repos/DUpdateSystem/UpgradeAll/releases", GITHUB_API_URL);
+        let validators = fout.validators_map.unwrap().remove(&key).unwrap();
+        assert_eq!(validators.etag.as_deref(), Some("\"abc\""));
+        let stale = StaleCache {
+            body: fout.cached_map.unwrap().remove(&key).unwrap(),
+            validators,
+        };
+
+        let m = server
+            .mock("GET", "/repos/DUpdateSystem/UpgradeAll/releases")
+            .match_header("if-none-match", "\"abc\"")
+            .with_status(304)
+            .create_async()
+            .await;
+        let fin = FIn::new_with_frag(&id_map, &hub_data, None)
+            .with_stale_map(HashMap::from([(key.clone(), stale.clone())]));
+        let fout_revalidated = github_provider.get_releases(&fin).await;
+        m.assert_async().await;
+        // The revalidated body is cached again, validators and all
+        assert_eq!(fout_revalidated.cached_map.unwrap()[&key], stale.body);
+        assert_eq!(
+            fout_revalidated.validators_map.unwrap()[&key],
+            stale.validators
+        );
+        assert_eq!(fout_revalidated.result.unwrap(), fout.result.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_token() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/DUpdateSystem/UpgradeAll/releases")
+            .match_header("Authorization", "Bearer secret")
+            .with_status(200)
+            .with_body("[]")
+            .create_async()
+            .await;
+
+        let id_map = AppDataMap::from([("owner", "DUpdateSystem"), ("repo", "UpgradeAll")]);
+        let proxy_url = format!("{} -> {}", GITHUB_API_URL, server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str()), (TOKEN, "secret")]);
+
+        let github_provider = GitHubProvider::new();
+        let fout = github_provider
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, None))
+            .await;
+        assert!(fout.result.unwrap().is_empty());
+        m.assert_async().await;
+    }
+
+    async fn get_monorepo_releases(extra_hub_data: &[(&str, &str)]) -> Vec<ReleaseData> {
+        let body = fs::read_to_string("tests/files/web/github_api_release_monorepo.json").unwrap();
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/repos/tauri-apps/tauri/releases")
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let id_map = AppDataMap::from([("owner", "tauri-apps"), ("repo", "tauri")]);
+        let proxy_url = format!("{} -> {}", GITHUB_API_URL, server.url());
+        let mut hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        hub_data.extend(extra_hub_data.iter().copied());
+
+        GitHubProvider::new()
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, None))
+            .await
+            .result
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_tag_prefix() {
+        let releases = get_monorepo_releases(&[(TAG_PREFIX, "tauri-v")]).await;
+        let versions = releases
+            .iter()
+            .map(|release| release.version_number.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, ["2.1.0", "2.0.0", "2.0.0-rc.1"]);
+        assert_eq!(
+            releases[0].extra.as_ref().unwrap()["tag_name"],
+            "tauri-v2.1.0"
+        );
+
+        // The newest release of the repo belongs to the CLI
+        let github_provider = GitHubProvider::new();
+        let body = fs::read_to_string("tests/files/web/github_api_release_monorepo.json").unwrap();
+        let id_map = AppDataMap::from([
+            ("owner", "tauri-apps"),
+            ("repo", "tauri"),
+            (TAG_PREFIX, "tauri-v"),
+        ]);
+        let hub_data = HubDataMap::new();
+        let cache_map = HashMap::from([(
+            format!("{}/repos/tauri-apps/tauri/releases", GITHUB_API_URL),
+            Bytes::from(body),
+        )]);
+        let release = github_provider
+            .get_latest_release(&FIn::new_with_frag(&id_map, &hub_data, Some(cache_map)))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(release.version_number, "2.1.0");
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_tag_regex() {
+        let releases =
+            get_monorepo_releases(&[(TAG_FILTER_REGEX, r"^cli-v(\d+\.\d+\.\d+)$")]).await;
+        let versions = releases
+            .iter()
+            .map(|release| release.version_number.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, ["1.4.2", "1.4.1"]);
+
+        let id_map = AppDataMap::from([("owner", "tauri-apps"), ("repo", "tauri")]);
+        let hub_data = HubDataMap::from([(TAG_FILTER_REGEX, "(")]);
+        let fout = GitHubProvider::new()
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, None))
+            .await;
+        assert!(fout.result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_drafts() {
+        let body = fs::read_to_string("tests/files/web/github_api_release_duplicate.json").unwrap();
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/repos/DUpdateSystem/UpgradeAll/releases")
+            .with_status(200)
+            .with_body(body)
+            .create();
+
+        let id_map = AppDataMap::from([("owner", "DUpdateSystem"), ("repo", "UpgradeAll")]);
+        let proxy_url = format!("{} -> {}", GITHUB_API_URL, server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+
+        let github_provider = GitHubProvider::new();
+        let releases = github_provider
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+        let versions = releases
+            .iter()
+            .map(|release| release.version_number.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec!["1.0.0", "1.0.0", "0.9.0"]);
+
+        let hub_data = HubDataMap::from([
+            (REVERSE_PROXY, proxy_url.as_str()),
+            (INCLUDE_DRAFTS_KEY, "true"),
+        ]);
+        let releases = github_provider
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(releases[0].version_number, "1.1.0");
+    }
+
+    // Example adoption of the provider conformance harness; new providers
+    // get the same checks by describing one app they can resolve.
+    #[tokio::test]
+    async fn test_conformance() {
+        let body = fs::read("tests/files/web/github_api_release.json").unwrap();
+        run_conformance(
+            GitHubProvider::new(),
+            Fixture {
+                app_data: vec![("owner", "DUpdateSystem"), ("repo", "UpgradeAll")],
+                hub_data: vec![],
+                responses: vec![("/repos/DUpdateSystem/UpgradeAll/releases", body.into())],
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_search_apps() {
+        let body =
+            fs::read_to_string("tests/files/web/github_api_search_repositories.json").unwrap();
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/search/repositories?q=syncthing%20android")
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let app_data = AppDataMap::new();
+        let proxy_url = format!("{} -> {}", GITHUB_API_URL, server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        let results = GitHubProvider::new()
+            .search_apps(
+                &FIn::new_with_frag(&app_data, &hub_data, None),
+                "syncthing android",
+            )
+            .await
+            .result
+            .unwrap();
+        let result = |owner: &str, repo: &str, description: &str| SearchResultData {
+            name: format!("{}/{}", owner, repo),
+            description: description.to_string(),
+            app_data: BTreeMap::from([
+                ("owner".to_string(), owner.to_string()),
+                ("repo".to_string(), repo.to_string()),
+            ]),
+        };
+        assert_eq!(
+            results,
+            vec![
+                result(
+                    "syncthing",
+                    "syncthing-android",
+                    "Wrapper of syncthing for Android."
+                ),
+                result("Catfriend1", "syncthing-fork", ""),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_app_data() {
+        let id_map = AppDataMap::from([("owner", "DUpdateSystem")]);
+        let hub_data = HubDataMap::new();
+        let fin = FIn::new_with_frag(&id_map, &hub_data, None);
+
+        let github_provider = GitHubProvider::new();
+        let fout = github_provider.get_releases(&fin).await;
+        assert_eq!(
+            fout.result.unwrap_err().to_string(),
+            "MissingKeyError: app_data has no repo"
+        );
+        assert!(github_provider
+            .get_cache_request_key(&FunctionType::GetReleases, &fin.data_map)
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_strict_parsing() {
+        let id_map = AppDataMap::from([("owner", "DUpdateSystem"), ("repo", "UpgradeAll")]);
+        let cache_key = format!("{}/repos/DUpdateSystem/UpgradeAll/releases", GITHUB_API_URL);
+        let github_provider = &GitHubProvider::new();
+        for strict in [false, true] {
+            let hub_data =
+                HubDataMap::from([(STRICT_PARSING, if strict { "true" } else { "false" })]);
+            let get_releases = |body: &'static str| {
+                let cache_map = HashMap::from([(cache_key.clone(), Bytes::from(body))]);
+                let fin = FIn::new_with_frag(&id_map, &hub_data, Some(cache_map));
+                async move { github_provider.get_releases(&fin).await }
+            };
+
+            let fout = get_releases(r#"{"message": "Not Found"}"#).await;
+            assert!(fout.result.is_err());
+            assert_eq!(is_parse_error(&fout), strict);
+            // No release has a version number
+            let fout = get_releases(r#"[{"name": "nightly", "body": ""}]"#).await;
+            assert_eq!(is_parse_error(&fout), strict);
+            if !strict {
+                assert!(fout.result.unwrap().is_empty());
+            }
+            let fout = get_releases("[]").await;
+            assert!(fout.result.unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_pages() {
+        let page = |versions: std::ops::Range<usize>| {
+            serde_json::to_string(
+                &versions
+                    .map(|i| serde_json::json!({"name": format!("1.{}.0", i), "body": ""}))
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap()
+        };
+        let mut server = Server::new_async().await;
+        let page_1 = server
+            .mock("GET", "/repos/DUpdateSystem/UpgradeAll/releases?per_page=100&page=1")
+            .with_status(200)
+            .with_header("link", r#"<https://api.github.com/repositories/1/releases?per_page=100&page=2>; rel="next""#)
+            .with_body(page(0..100))
+            .create_async()
+            .await;
+        let page_2 = server
+            .mock(
+                "GET",
+                "/repos/DUpdateSystem/UpgradeAll/releases?per_page=100&page=2",
+            )
+            .with_status(200)
+            .with_body(page(100..150))
+            .create_async()
+            .await;
+
+        let id_map = AppDataMap::from([("owner", "DUpdateSystem"), ("repo", "UpgradeAll")]);
+        let proxy_url = format!("{} -> {}", GITHUB_API_URL, server.url());
+        let hub_data = HubDataMap::from([
+            (REVERSE_PROXY, proxy_url.as_str()),
+            (MAX_RELEASES_KEY, "200"),
+        ]);
+        let github_provider = GitHubProvider::new();
+        let fin = FIn::new_with_frag(&id_map, &hub_data, None);
+        let keys = github_provider.get_cache_request_key(&FunctionType::GetReleases, &fin.data_map);
+        assert_eq!(keys.len(), 2);
+        let fout = github_provider.get_releases(&fin).await;
+        page_1.assert_async().await;
+        page_2.assert_async().await;
+        let versions = fout
+            .result
+            .unwrap()
+            .into_iter()
+            .map(|release| release.version_number)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            versions,
+            (0..150).map(|i| format!("1.{}.0", i)).collect::<Vec<_>>()
+        );
+        let cached_map = fout.cached_map.unwrap();
+        assert_eq!(cached_map.len(), 2);
+        assert!(keys.iter().all(|key| cached_map.contains_key(key)));
+
+        // The limit cuts the last page short
+        let hub_data = HubDataMap::from([
+            (REVERSE_PROXY, proxy_url.as_str()),
+            (MAX_RELEASES_KEY, "120"),
+        ]);
+        let cache_map = keys
+            .iter()
+            .map(|key| (key.clone(), cached_map[key].clone()))
+            .collect::<HashMap<_, _>>();
+        let releases = github_provider
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, Some(cache_map)))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(releases.len(), 120);
+        assert_eq!(releases[119].version_number, "1.119.0");
+    }
 }
@@ -13,7 +13,6 @@ use crate::utils::{
 };
 
 const GITLAB_URL: &str = "https://gitlab.com";
-const GITLAB_API_URL: &str = "https://gitlab.com/api/v4/projects";
 
 const VERSION_NUMBER_KEY: &str = "version_number_key";
 
@@ -28,24 +27,87 @@ impl GitLabProvider {
 impl BaseProviderExt for GitLabProvider {}
 
 impl GitLabProvider {
-    async fn get_project_id(&self, fin: &FIn<'_>) -> Option<String> {
-        let id_map = fin.data_map.app_data;
-        let api_url = format!(
-            "{}/{}%2F{}",
-            GITLAB_API_URL, id_map["owner"], id_map["repo"]
-        );
-        let api_url = self.replace_proxy_url(fin, &api_url);
+    fn get_owner_repo<'a>(data_map: &DataMap<'a>) -> Result<(&'a str, &'a str), MissingKeyError> {
+        Ok((
+            data_map.get_app_value("owner")?,
+            data_map.get_app_value("repo")?,
+        ))
+    }
+
+    /// The instance from hub_data `repo_url`, gitlab.com by default. It may
+    /// be served under a subpath, e.g. `https://example.com/gitlab`.
+    fn get_base_url<'a>(data_map: &DataMap<'a>) -> &'a str {
+        data_map
+            .hub_data
+            .get(KEY_REPO_URL)
+            .copied()
+            .map(|url| url.trim_end_matches('/'))
+            .unwrap_or(GITLAB_URL)
+    }
+
+    fn get_api_url(base_url: &str) -> String {
+        format!("{}/api/v4/projects", base_url)
+    }
+
+    fn get_project_url(base_url: &str, owner: &str, repo: &str) -> String {
+        format!("{}/{}%2F{}", Self::get_api_url(base_url), owner, repo)
+    }
+
+    // Cache keys are laid out as paths, so the project response can't be
+    // keyed by its URL: that would be a file and the parent of `/releases`
+    fn get_project_cache_key(base_url: &str, owner: &str, repo: &str) -> String {
+        format!("{}/project", Self::get_project_url(base_url, owner, repo))
+    }
+
+    /// GITLAB_TOKEN is meant for gitlab.com, so other instances only get a
+    /// token from hub_data or the netrc entry of their own host.
+    fn get_token(&self, fin: &FIn, base_url: &str) -> Option<Credential> {
+        let env_vars: &[&'static str] = if base_url == GITLAB_URL {
+            &["GITLAB_TOKEN"]
+        } else {
+            &[]
+        };
+        self.get_credential(fin, env_vars, base_url)
+    }
+
+    fn get_header_map(&self, fin: &FIn, base_url: &str) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert("User-Agent".to_string(), "Awesome-Octocat-App".to_string());
+        if let Some(credential) = self.get_token(fin, base_url) {
+            map.insert("PRIVATE-TOKEN".to_string(), credential.token);
+        }
+        map
+    }
+
+    /// Returns the project id, plus the response body when it was fetched
+    /// rather than read from the cache.
+    async fn get_project_id(
+        &self,
+        fin: &FIn<'_>,
+        owner: &str,
+        repo: &str,
+    ) -> (Option<String>, Option<Bytes>) {
+        let base_url = Self::get_base_url(&fin.data_map);
+        let cache_key = Self::get_project_cache_key(base_url, owner, repo);
+        let parse_id = |body: &Bytes| {
+            let data = serde_json::from_slice::<HashMap<String, Value>>(body).ok()?;
+            Some(data.get("id")?.as_number()?.to_string())
+        };
+        if let Some(body) = fin.get_cache(&cache_key) {
+            return (parse_id(body), None);
+        }
+        let api_url = self.replace_proxy_url(fin, &Self::get_project_url(base_url, owner, repo));
 
         if let Ok(parsed_url) = api_url.parse() {
-            if let Ok(rsp) = get(parsed_url, &HashMap::new()).await {
+            if let Ok(rsp) = get(parsed_url, &self.get_header_map(fin, base_url)).await {
                 if let Some(body) = rsp.body {
-                    if let Ok(data) = serde_json::from_slice::<HashMap<String, Value>>(&body) {
-                        return Some(data.get("id")?.as_number()?.to_string());
+                    if let Some(project_id) = parse_id(&body) {
+                        return (Some(project_id), Some(body));
                     }
                 }
             }
         }
-        None
+        (None, None)
     }
 
     fn try_get_download_url_from_changelog(&self, changelog: &str) -> Vec<(String, String)> {
@@ -79,9 +141,9 @@ impl GitLabProvider {
         vec![]
     }
 
-    fn fix_download_url(&self, download_url: &str, project_id: &str) -> String {
+    fn fix_download_url(&self, base_url: &str, download_url: &str, project_id: &str) -> String {
         if download_url.starts_with("/uploads/") {
-            return format!("{}/-/project/{}{}", GITLAB_URL, project_id, download_url);
+            return format!("{}/-/project/{}{}", base_url, project_id, download_url);
         }
         download_url.to_string()
     }
@@ -89,58 +151,85 @@ impl GitLabProvider {
 
 #[async_trait]
 impl BaseProvider for GitLabProvider {
+    fn required_app_keys(&self) -> &'static [&'static str] {
+        &["owner", "repo"]
+    }
+
+    fn repo_path_keys(&self) -> &'static [&'static str] {
+        &["owner", "repo"]
+    }
+
+    fn merges_duplicate_versions(&self) -> bool {
+        true
+    }
+
     fn get_cache_request_key(
         &self,
         function_type: &FunctionType,
         data_map: &DataMap,
     ) -> Vec<String> {
-        let id_map = data_map.app_data;
+        let Ok((owner, repo)) = Self::get_owner_repo(data_map) else {
+            return vec![];
+        };
+        let base_url = Self::get_base_url(data_map);
         match function_type {
-            FunctionType::CheckAppAvailable => vec![format!(
-                "{}/{}/{}/HEAD",
-                GITLAB_URL, id_map["owner"], id_map["repo"]
-            )],
-            FunctionType::GetLatestRelease | FunctionType::GetReleases => vec![format!(
-                "{}/{}/{}/releases",
-                GITLAB_API_URL, id_map["owner"], id_map["repo"]
-            )],
+            FunctionType::CheckAppAvailable => {
+                vec![format!("{}/{}/{}/HEAD", base_url, owner, repo)]
+            }
+            FunctionType::GetLatestRelease | FunctionType::GetReleases => {
+                vec![
+                    format!("{}/releases", Self::get_project_url(base_url, owner, repo)),
+                    Self::get_project_cache_key(base_url, owner, repo),
+                ]
+            }
         }
     }
 
     async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
-        let id_map = fin.data_map.app_data;
-        let api_url = format!("{}/{}/{}", GITLAB_URL, id_map["owner"], id_map["repo"]);
+        let (owner, repo) = match Self::get_owner_repo(&fin.data_map) {
+            Ok(owner_repo) => owner_repo,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let base_url = Self::get_base_url(&fin.data_map);
+        let api_url = format!("{}/{}/{}", base_url, owner, repo);
         let api_url = self.replace_proxy_url(fin, &api_url);
 
-        if let Ok(parsed_url) = api_url.parse() {
-            if let Ok(rsp) = head(parsed_url, &HashMap::new()).await {
-                return FOut::new(http_status_is_ok(rsp.status));
-            }
+        let Ok(parsed_url) = api_url.parse() else {
+            return FOut::new_empty()
+                .set_parse_error(&fin.data_map, ParseError::Url { url: api_url });
+        };
+        if let Ok(rsp) = head(parsed_url, &self.get_header_map(fin, base_url)).await {
+            return FOut::new(http_status_is_ok(rsp.status));
         }
         FOut::new_empty()
     }
 
     async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
-        let id_map = fin.data_map.app_data;
-        let url = format!(
-            "{}/{}%2F{}/releases",
-            GITLAB_API_URL, id_map["owner"], id_map["repo"]
-        );
-        let url = self.replace_proxy_url(fin, &url);
+        let (owner, repo) = match Self::get_owner_repo(&fin.data_map) {
+            Ok(owner_repo) => owner_repo,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let tag_filter = match fin.data_map.get_tag_filter() {
+            Ok(tag_filter) => tag_filter,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        // Cached under the origin URL, whichever proxy fetched it
+        let base_url = Self::get_base_url(&fin.data_map);
+        let cache_key = format!("{}/releases", Self::get_project_url(base_url, owner, repo));
+        let url = self.replace_proxy_url(fin, &cache_key);
         let mut fout = FOut::new_empty();
-        let cache_body = fin.get_cache(&url);
+        let mut cached_map = HashMap::new();
+        let cache_body = fin.get_cache(&cache_key);
         let mut rsp_body = None;
+        let mut rsp_policy = None;
         if cache_body.is_none() {
-            if let Ok(parsed_url) = url.parse() {
-                let header_map = {
-                    let mut map = HashMap::new();
-                    map.insert("User-Agent".to_string(), "Awesome-Octocat-App".to_string());
-                    map
-                };
-                if let Ok(rsp) = get(parsed_url, &header_map).await {
-                    if let Some(content) = rsp.body {
-                        rsp_body = Some(content);
-                    }
+            let Ok(parsed_url) = url.parse() else {
+                return fout.set_parse_error(&fin.data_map, ParseError::Url { url });
+            };
+            if let Ok(rsp) = get(parsed_url, &self.get_header_map(fin, base_url)).await {
+                rsp_policy = rsp.cache_policy();
+                if let Some(content) = rsp.body {
+                    rsp_body = Some(content);
                 }
             }
         }
@@ -154,90 +243,151 @@ impl BaseProvider for GitLabProvider {
             return fout;
         }
 
-        if let Ok(data) = serde_json::from_slice::<Vec<Value>>(body) {
-            let mut release_list = data
-                .iter()
-                .filter_map(|json| {
-                    let assets_data = match json.get("assets")?.get("links") {
-                        Some(links) => links
-                            .as_array()?
-                            .iter()
-                            .filter_map(|asset| {
-                                let file_name = asset.get("name")?.as_str()?.to_string();
-                                let file_type = asset.get("link_type")?.as_str()?.to_string();
-                                let download_url = asset.get("url")?.as_str()?.to_string();
-                                Some(AssetData {
-                                    file_name,
-                                    file_type,
-                                    download_url,
+        match serde_json::from_slice::<Vec<Value>>(body) {
+            Ok(data) => {
+                let mut release_list = data
+                    .iter()
+                    .filter_map(|json| {
+                        let assets_data = match json.get("assets")?.get("links") {
+                            Some(links) => links
+                                .as_array()?
+                                .iter()
+                                .filter_map(|asset| {
+                                    let file_name = asset.get("name")?.as_str()?.to_string();
+                                    let file_type = asset.get("link_type")?.as_str()?.to_string();
+                                    let download_url = asset.get("url")?.as_str()?.to_string();
+                                    Some(AssetData {
+                                        file_name,
+                                        file_type,
+                                        download_url,
+                                        size: None,
+                                        extra: None,
+                                    })
                                 })
-                            })
-                            .collect(),
-                        None => vec![],
-                    };
-                    let mut keys_to_try = vec!["name", "tag_name"];
-                    if let Some(tag) = fin.data_map.hub_data.get(VERSION_NUMBER_KEY) {
-                        keys_to_try.insert(0, tag);
-                    }
-                    let mut version_number: Option<String> = None;
-
-                    for key in keys_to_try.iter() {
-                        if let Some(value) = json.get(key).and_then(|v| v.as_str()) {
-                            if Version::new(value.to_string()).is_valid() {
-                                version_number = Some(value.to_string());
-                                break;
+                                .collect(),
+                            None => vec![],
+                        };
+                        let mut extra = HashMap::new();
+                        for (pointer, extra_key) in [
+                            ("/tag_name", TAG_NAME_KEY),
+                            ("/_links/self", RELEASE_URL_KEY),
+                            ("/commit/id", COMMIT_SHA_KEY),
+                        ] {
+                            if let Some(value) = json.pointer(pointer).and_then(|v| v.as_str()) {
+                                extra.insert(extra_key.to_string(), value.to_string());
                             }
                         }
-                    }
-                    let changelog = json.get("description")?.as_str()?.to_string();
-                    let extra_download_url = self.try_get_download_url_from_changelog(&changelog);
-                    let assets_data = assets_data
-                        .into_iter()
-                        .chain(extra_download_url.into_iter().map(|(k, v)| AssetData {
-                            file_name: k,
-                            file_type: "".to_string(),
-                            download_url: v,
-                        }))
-                        .collect();
-                    Some(ReleaseData {
-                        version_number: version_number?.to_string(),
-                        changelog,
-                        assets: assets_data,
-                        extra: None,
-                    })
-                })
-                .collect::<Vec<ReleaseData>>();
-            let mut project_id = None;
-            for release in release_list.iter_mut() {
-                for asset in release.assets.iter_mut() {
-                    if asset.download_url.starts_with("/uploads/") {
-                        if project_id.is_none() {
-                            project_id = self.get_project_id(fin).await;
+                        let extra = Some(extra).filter(|extra| !extra.is_empty());
+                        let mut version_number: Option<String> = None;
+                        if let Some(tag_filter) = &tag_filter {
+                            let tag = json.get("tag_name")?.as_str()?;
+                            version_number = Some(tag_filter.version_of(tag)?.to_string());
+                        } else {
+                            let mut keys_to_try = vec!["name", "tag_name"];
+                            if let Some(tag) = fin.data_map.hub_data.get(VERSION_NUMBER_KEY) {
+                                keys_to_try.insert(0, tag);
+                            }
+                            for key in keys_to_try.iter() {
+                                if let Some(value) = json.get(key).and_then(|v| v.as_str()) {
+                                    if Version::new(value.to_string()).is_valid() {
+                                        version_number = Some(value.to_string());
+                                        break;
+                                    }
+                                }
+                            }
                         }
-                        if let Some(project_id) = &project_id {
-                            asset.download_url =
-                                self.fix_download_url(&asset.download_url, project_id);
+                        let changelog = json.get("description")?.as_str()?.to_string();
+                        let extra_download_url =
+                            self.try_get_download_url_from_changelog(&changelog);
+                        let assets_data = assets_data
+                            .into_iter()
+                            .chain(extra_download_url.into_iter().map(|(k, v)| AssetData {
+                                file_name: k,
+                                file_type: "".to_string(),
+                                download_url: v,
+                                size: None,
+                                extra: None,
+                            }))
+                            .collect();
+                        Some(ReleaseData {
+                            version_number: version_number?.to_string(),
+                            changelog,
+                            assets: assets_data,
+                            extra,
+                        })
+                    })
+                    .collect::<Vec<ReleaseData>>();
+                let mut project_id = None;
+                for release in release_list.iter_mut() {
+                    for asset in release.assets.iter_mut() {
+                        if asset.download_url.starts_with("/uploads/") {
+                            if project_id.is_none() {
+                                let project_body;
+                                (project_id, project_body) =
+                                    self.get_project_id(fin, owner, repo).await;
+                                if let Some(body) = project_body {
+                                    cached_map.insert(
+                                        Self::get_project_cache_key(base_url, owner, repo),
+                                        body,
+                                    );
+                                }
+                            }
+                            if let Some(project_id) = &project_id {
+                                asset.download_url = self.fix_download_url(
+                                    base_url,
+                                    &asset.download_url,
+                                    project_id,
+                                );
+                            }
                         }
                     }
                 }
+                // A tag filter may rightly match none of them
+                let all_dropped =
+                    release_list.is_empty() && !data.is_empty() && tag_filter.is_none();
+                fout = fout.set_data(release_list);
+                if all_dropped {
+                    fout = fout.set_parse_error(
+                        &fin.data_map,
+                        ParseError::AllDropped {
+                            url: url.clone(),
+                            count: data.len(),
+                        },
+                    );
+                }
             }
-            fout = fout.set_data(release_list);
-        };
+            Err(e) => {
+                fout = fout.set_parse_error(
+                    &fin.data_map,
+                    ParseError::Body {
+                        url: url.clone(),
+                        reason: e.to_string(),
+                    },
+                );
+            }
+        }
 
         if let Some(content) = rsp_body {
-            fout.set_cached_map(HashMap::from([(url, content)]))
-        } else {
+            fout = fout.set_cache_policy(&cache_key, rsp_policy);
+            cached_map.insert(cache_key, content);
+        }
+        if cached_map.is_empty() {
             fout
+        } else {
+            fout.set_cached_map(cached_map)
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::conformance::{is_parse_error, run_conformance, Fixture};
     use super::*;
     use mockito::Server;
     use std::fs;
 
+    use crate::cache::manager::{CacheManager, GroupType};
+
     #[tokio::test]
     async fn test_check_app_available() {
         let mut server = Server::new_async().await;
@@ -270,7 +420,11 @@ mod tests {
             .create();
 
         let id_map = AppDataMap::from([("owner", "fdroid"), ("repo", "fdroidclient")]);
-        let proxy_url = format!("{} -> {}", GITLAB_API_URL, server.url());
+        let proxy_url = format!(
+            "{} -> {}",
+            GitLabProvider::get_api_url(GITLAB_URL),
+            server.url()
+        );
         let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
 
         let gitlab_provider = GitLabProvider::new();
@@ -306,7 +460,11 @@ mod tests {
             .await;
 
         let id_map = AppDataMap::from([("owner", "AuroraOSS"), ("repo", "AuroraStore")]);
-        let proxy_url = format!("{} -> {}", GITLAB_API_URL, server.url());
+        let proxy_url = format!(
+            "{} -> {}",
+            GitLabProvider::get_api_url(GITLAB_URL),
+            server.url()
+        );
         let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
 
         let gitlab_provider = GitLabProvider::new();
@@ -323,4 +481,286 @@ mod tests {
 
         assert_eq!(releases, releases_saved)
     }
+
+    #[tokio::test]
+    async fn test_private_token() {
+        let body =
+            fs::read_to_string("tests/files/web/gitlab_api_release_AuroraStore.json").unwrap();
+        let project_body =
+            fs::read_to_string("tests/files/web/gitlab_api_project_AuroraStore.json").unwrap();
+        let mut server = Server::new_async().await;
+        let mocks = [
+            ("GET", "/AuroraOSS/AuroraStore", String::new()),
+            (
+                "GET",
+                "/api/v4/projects/AuroraOSS%2FAuroraStore/releases",
+                body,
+            ),
+            (
+                "GET",
+                "/api/v4/projects/AuroraOSS%2FAuroraStore",
+                project_body,
+            ),
+        ]
+        .map(|(method, path, body)| {
+            server
+                .mock(method, path)
+                .match_header("PRIVATE-TOKEN", "secret")
+                .with_status(200)
+                .with_body(body)
+                .create()
+        });
+
+        let id_map = AppDataMap::from([("owner", "AuroraOSS"), ("repo", "AuroraStore")]);
+        let proxy_url = format!("{} -> {}", GITLAB_URL, server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str()), (TOKEN, "secret")]);
+        let fin = FIn::new_with_frag(&id_map, &hub_data, None);
+
+        let gitlab_provider = GitLabProvider::new();
+        assert!(gitlab_provider
+            .check_app_available(&fin)
+            .await
+            .result
+            .unwrap());
+        assert!(!gitlab_provider
+            .get_releases(&fin)
+            .await
+            .result
+            .unwrap()
+            .is_empty());
+        for mock in mocks {
+            mock.assert();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        let body = fs::read("tests/files/web/gitlab_api_release_AuroraStore.json").unwrap();
+        let project_body = fs::read("tests/files/web/gitlab_api_project_AuroraStore.json").unwrap();
+        run_conformance(
+            GitLabProvider::new(),
+            Fixture {
+                app_data: vec![("owner", "AuroraOSS"), ("repo", "AuroraStore")],
+                hub_data: vec![],
+                responses: vec![
+                    (
+                        "/api/v4/projects/AuroraOSS%2FAuroraStore/releases",
+                        body.into(),
+                    ),
+                    (
+                        "/api/v4/projects/AuroraOSS%2FAuroraStore",
+                        project_body.into(),
+                    ),
+                ],
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_missing_app_data() {
+        let id_map = AppDataMap::from([("repo", "fdroidclient")]);
+        let hub_data = HubDataMap::new();
+        let fin = FIn::new_with_frag(&id_map, &hub_data, None);
+
+        let gitlab_provider = GitLabProvider::new();
+        let fout = gitlab_provider.check_app_available(&fin).await;
+        assert_eq!(
+            fout.result.unwrap_err().to_string(),
+            "MissingKeyError: app_data has no owner"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_project_id_cached() {
+        let body =
+            fs::read_to_string("tests/files/web/gitlab_api_release_AuroraStore.json").unwrap();
+        let project_body =
+            fs::read_to_string("tests/files/web/gitlab_api_project_AuroraStore.json").unwrap();
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/AuroraOSS%2FAuroraStore/releases")
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+        let project_mock = server
+            .mock("GET", "/AuroraOSS%2FAuroraStore")
+            .with_status(200)
+            .with_body(project_body)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let id_map = AppDataMap::from([("owner", "AuroraOSS"), ("repo", "AuroraStore")]);
+        let proxy_url = format!(
+            "{} -> {}",
+            GitLabProvider::get_api_url(GITLAB_URL),
+            server.url()
+        );
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+
+        let gitlab_provider = GitLabProvider::new();
+        let fout = gitlab_provider
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, None))
+            .await;
+        let releases = fout.result.unwrap();
+        let cached_map = fout.cached_map.unwrap();
+        let project_key =
+            GitLabProvider::get_project_cache_key(GITLAB_URL, "AuroraOSS", "AuroraStore");
+        assert!(cached_map.contains_key(&project_key));
+
+        // Both keys must be storable side by side in the on-disk cache
+        let cache_dir = tempfile::tempdir().unwrap();
+        let mut cache_manager = CacheManager::new();
+        cache_manager.set_local_cache_dir(cache_dir.path());
+        for (key, value) in cached_map.iter() {
+            cache_manager
+                .save(&GroupType::RepoInside, key, value.clone())
+                .await
+                .unwrap();
+        }
+
+        // Only the project response is cached, so the releases are fetched
+        // again but the project id is not
+        let project_cache = HashMap::from([(
+            project_key.clone(),
+            cached_map.get(&project_key).unwrap().clone(),
+        )]);
+        let fout = gitlab_provider
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, Some(project_cache)))
+            .await;
+        assert_eq!(fout.result.unwrap(), releases);
+        assert!(!fout.cached_map.unwrap().contains_key(&project_key));
+        project_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_tag_prefix() {
+        let body = r#"[
+  {"name": "cli 1.4.2", "tag_name": "cli-v1.4.2", "description": "", "assets": {"links": []}},
+  {"name": "app 2.1.0", "tag_name": "app-v2.1.0", "description": "", "assets": {"links": []}},
+  {"name": "app 2.0.0", "tag_name": "app-v2.0.0", "description": "", "assets": {"links": []}}
+]"#;
+        let id_map =
+            AppDataMap::from([("owner", "group"), ("repo", "mono"), (TAG_PREFIX, "app-v")]);
+        let hub_data = HubDataMap::new();
+        let cache_map = HashMap::from([(
+            format!(
+                "{}/group%2Fmono/releases",
+                GitLabProvider::get_api_url(GITLAB_URL)
+            ),
+            Bytes::from_static(body.as_bytes()),
+        )]);
+
+        let releases = GitLabProvider::new()
+            .get_releases(&FIn::new_with_frag(&id_map, &hub_data, Some(cache_map)))
+            .await
+            .result
+            .unwrap();
+        let versions = releases
+            .iter()
+            .map(|release| release.version_number.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, ["2.1.0", "2.0.0"]);
+        assert_eq!(
+            releases[0].extra.as_ref().unwrap()["tag_name"],
+            "app-v2.1.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_strict_parsing() {
+        let id_map = AppDataMap::from([("owner", "fdroid"), ("repo", "fdroidclient")]);
+        let cache_key = format!(
+            "{}/releases",
+            GitLabProvider::get_project_url(GITLAB_URL, "fdroid", "fdroidclient")
+        );
+        let gitlab_provider = &GitLabProvider::new();
+        for strict in [false, true] {
+            let hub_data =
+                HubDataMap::from([(STRICT_PARSING, if strict { "true" } else { "false" })]);
+            let get_releases = |body: &'static str| {
+                let cache_map = HashMap::from([(cache_key.clone(), Bytes::from(body))]);
+                let fin = FIn::new_with_frag(&id_map, &hub_data, Some(cache_map));
+                async move { gitlab_provider.get_releases(&fin).await }
+            };
+
+            let fout = get_releases(r#"{"message": "404 Project Not Found"}"#).await;
+            assert!(fout.result.is_err());
+            assert_eq!(is_parse_error(&fout), strict);
+            // No release has a version number
+            let fout =
+                get_releases(r#"[{"name": "nightly", "description": "", "assets": {}}]"#).await;
+            assert_eq!(is_parse_error(&fout), strict);
+            if !strict {
+                assert!(fout.result.unwrap().is_empty());
+            }
+            let fout = get_releases("[]").await;
+            assert!(fout.result.unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_self_hosted_subpath() {
+        let releases_body = r#"[{
+            "name": "1.0.0",
+            "tag_name": "v1.0.0",
+            "description": "",
+            "assets": {"links": [
+                {"name": "app.apk", "link_type": "package", "url": "/uploads/abc/app.apk"}
+            ]}
+        }]"#;
+        let mut server = Server::new_async().await;
+        let _m_available = server
+            .mock("GET", "/gitlab/group/app")
+            .with_status(200)
+            .create_async()
+            .await;
+        let _m_releases = server
+            .mock("GET", "/gitlab/api/v4/projects/group%2Fapp/releases")
+            .with_status(200)
+            .with_body(releases_body)
+            .create_async()
+            .await;
+        let _m_project = server
+            .mock("GET", "/gitlab/api/v4/projects/group%2Fapp")
+            .with_status(200)
+            .with_body(r#"{"id": 7}"#)
+            .create_async()
+            .await;
+
+        let id_map = AppDataMap::from([("owner", "group"), ("repo", "app")]);
+        let proxy_url = format!("https://git.example.com -> {}", server.url());
+        let hub_data = HubDataMap::from([
+            (KEY_REPO_URL, "https://git.example.com/gitlab/"),
+            (REVERSE_PROXY, proxy_url.as_str()),
+        ]);
+        let fin = FIn::new_with_frag(&id_map, &hub_data, None);
+        let gitlab_provider = GitLabProvider::new();
+        assert_eq!(
+            gitlab_provider.get_cache_request_key(&FunctionType::GetReleases, &fin.data_map),
+            vec![
+                "https://git.example.com/gitlab/api/v4/projects/group%2Fapp/releases",
+                "https://git.example.com/gitlab/api/v4/projects/group%2Fapp/project",
+            ]
+        );
+        assert!(gitlab_provider
+            .check_app_available(&fin)
+            .await
+            .result
+            .unwrap());
+        let fout = gitlab_provider.get_releases(&fin).await;
+        let releases = fout.result.unwrap();
+        assert_eq!(releases[0].version_number, "1.0.0");
+        assert_eq!(
+            releases[0].assets[0].download_url,
+            "https://git.example.com/gitlab/-/project/7/uploads/abc/app.apk"
+        );
+        let cache_keys =
+            gitlab_provider.get_cache_request_key(&FunctionType::GetReleases, &fin.data_map);
+        let cached_map = fout.cached_map.unwrap();
+        assert_eq!(cached_map.len(), 2);
+        assert!(cache_keys.iter().all(|key| cached_map.contains_key(key)));
+    }
 }
@@ -0,0 +1,359 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use quick_xml::escape::unescape;
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::utils::http::{get, http_status_is_ok};
+
+use super::super::data::release::*;
+use super::base_provider::*;
+
+pub const GOOGLE_PLAY_HUB_UUID: &str = "ae4c2304-7b6a-495d-a346-41189dd3c374";
+
+const GOOGLE_PLAY_URL: &str = "https://play.google.com";
+
+const CURRENT_VERSION_LABEL: &str = "current version";
+const WHATS_NEW_LABEL: &str = "what's new";
+const VARIES_WITH_DEVICE: &str = "Varies with device";
+const REGION_LOCKED_TEXT: &str = "available in your country";
+
+static TOKEN_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>|[^<]+").unwrap());
+static LINE_BREAK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^<br\b").unwrap());
+
+#[derive(Debug)]
+pub struct GooglePlayError {
+    pub message: String,
+}
+
+impl std::fmt::Display for GooglePlayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "GooglePlayError: {}", self.message)
+    }
+}
+
+impl std::error::Error for GooglePlayError {}
+
+enum Token<'a> {
+    Tag(&'a str),
+    Text(String),
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Listing {
+    version: Option<String>,
+    whats_new: Option<String>,
+}
+
+enum Fetched<T> {
+    Body(Bytes, FOut<T>),
+    NotFound,
+    Failed(FOut<T>),
+}
+
+/// Scrapes an app's Google Play listing, in English, for the "Current
+/// Version" and "What's new" fields. The release has no assets; Play
+/// doesn't hand out APKs.
+pub struct GooglePlayProvider;
+
+impl GooglePlayProvider {
+    pub fn new() -> Self {
+        GooglePlayProvider {}
+    }
+
+    pub fn get_listing_url(package_id: &str) -> String {
+        format!(
+            "{}/store/apps/details?id={}&hl=en",
+            GOOGLE_PLAY_URL, package_id
+        )
+    }
+
+    fn tokenize(html: &str) -> Vec<Token<'_>> {
+        TOKEN_REGEX
+            .find_iter(html)
+            .filter_map(|m| {
+                let token = m.as_str();
+                if token.starts_with('<') {
+                    return Some(Token::Tag(token));
+                }
+                let text = token.trim();
+                if text.is_empty() {
+                    return None;
+                }
+                // Entities outside XML's, like &nbsp;, stay as they are
+                let text = unescape(text).map_or_else(|_| text.to_string(), |t| t.into_owned());
+                Some(Token::Text(text))
+            })
+            .collect()
+    }
+
+    fn parse_listing(html: &str) -> Listing {
+        let tokens = Self::tokenize(html);
+        let mut listing = Listing::default();
+        for (i, token) in tokens.iter().enumerate() {
+            let Token::Text(label) = token else {
+                continue;
+            };
+            let label = label.to_lowercase().replace('\u{2019}', "'");
+            let rest = tokens[i + 1..].iter();
+            if label == CURRENT_VERSION_LABEL && listing.version.is_none() {
+                listing.version = rest
+                    .filter_map(|token| match token {
+                        Token::Text(text) => Some(text.clone()),
+                        Token::Tag(_) => None,
+                    })
+                    .next();
+            } else if label == WHATS_NEW_LABEL && listing.whats_new.is_none() {
+                // The text runs on through line breaks, up to the next tag
+                let mut lines = vec![];
+                for token in rest {
+                    match token {
+                        Token::Text(text) => lines.push(text.as_str()),
+                        Token::Tag(tag) if LINE_BREAK_REGEX.is_match(tag) => (),
+                        Token::Tag(_) if !lines.is_empty() => break,
+                        Token::Tag(_) => (),
+                    }
+                }
+                if !lines.is_empty() {
+                    listing.whats_new = Some(lines.join("\n"));
+                }
+            }
+        }
+        listing
+    }
+
+    async fn fetch_listing<T>(&self, fin: &FIn<'_>, listing_url: &str) -> Fetched<T> {
+        let fout = FOut::new_empty();
+        // Cached under the origin URL, whichever proxy fetched it
+        if let Some(body) = fin.get_cache(listing_url) {
+            return Fetched::Body(body.clone(), fout);
+        }
+        let url = self.replace_proxy_url(fin, listing_url);
+        let Ok(parsed_url) = url.parse() else {
+            return Fetched::Failed(fout.set_parse_error(&fin.data_map, ParseError::Url { url }));
+        };
+        let Ok(rsp) = get(parsed_url, &HashMap::new()).await else {
+            return Fetched::Failed(fout);
+        };
+        if rsp.status == 404 {
+            return Fetched::NotFound;
+        }
+        let policy = rsp.cache_policy();
+        match rsp.body {
+            Some(body) if http_status_is_ok(rsp.status) => Fetched::Body(
+                body.clone(),
+                fout.set_cache(listing_url, body)
+                    .set_cache_policy(listing_url, policy),
+            ),
+            _ => Fetched::Failed(fout),
+        }
+    }
+}
+
+impl BaseProviderExt for GooglePlayProvider {}
+
+#[async_trait]
+impl BaseProvider for GooglePlayProvider {
+    fn required_app_keys(&self) -> &'static [&'static str] {
+        &[ANDROID_APP_TYPE]
+    }
+
+    fn get_cache_request_key(
+        &self,
+        _function_type: &FunctionType,
+        data_map: &DataMap,
+    ) -> Vec<String> {
+        match data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => vec![Self::get_listing_url(package_id)],
+            Err(_) => vec![],
+        }
+    }
+
+    async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
+        let package_id = match fin.data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => package_id,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let listing_url = Self::get_listing_url(package_id);
+        match self.fetch_listing(fin, &listing_url).await {
+            Fetched::Body(body, fout) => match std::str::from_utf8(&body) {
+                Ok(html) => fout.set_data(!html.contains(REGION_LOCKED_TEXT)),
+                Err(e) => fout.set_data(true).set_parse_error(
+                    &fin.data_map,
+                    ParseError::Body {
+                        url: listing_url,
+                        reason: e.to_string(),
+                    },
+                ),
+            },
+            Fetched::NotFound => FOut::new(false),
+            Fetched::Failed(fout) => fout,
+        }
+    }
+
+    async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
+        let package_id = match fin.data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => package_id,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let listing_url = Self::get_listing_url(package_id);
+        let error = |message: &str| {
+            Box::new(GooglePlayError {
+                message: format!("{} {}", package_id, message),
+            })
+        };
+        let (body, fout) = match self.fetch_listing(fin, &listing_url).await {
+            Fetched::Body(body, fout) => (body, fout),
+            Fetched::NotFound => {
+                return FOut::new_empty().set_error(error("is not published on Google Play"))
+            }
+            Fetched::Failed(fout) => return fout,
+        };
+        let html = match std::str::from_utf8(&body) {
+            Ok(html) => html,
+            Err(e) => {
+                return fout.set_parse_error(
+                    &fin.data_map,
+                    ParseError::Body {
+                        url: listing_url,
+                        reason: e.to_string(),
+                    },
+                )
+            }
+        };
+        if html.contains(REGION_LOCKED_TEXT) {
+            return fout.set_error(error("is not available in this region"));
+        }
+        let listing = Self::parse_listing(html);
+        match listing.version {
+            Some(version) if version == VARIES_WITH_DEVICE => {
+                fout.set_error(error("has a version that varies with device"))
+            }
+            Some(version) => fout.set_data(vec![ReleaseData {
+                version_number: version,
+                changelog: listing.whats_new.unwrap_or_default(),
+                assets: vec![],
+                extra: None,
+            }]),
+            None => fout.set_parse_error(
+                &fin.data_map,
+                ParseError::Body {
+                    url: listing_url,
+                    reason: "no Current Version".to_string(),
+                },
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::conformance::{run_conformance, Fixture};
+    use super::*;
+    use mockito::Server;
+    use std::fs;
+
+    const PACKAGE_ID: &str = "com.example.notes";
+    const LISTING_PATH: &str = "/store/apps/details?id=com.example.notes&hl=en";
+
+    #[test]
+    fn test_parse_listing() {
+        let html = fs::read_to_string("tests/files/web/google_play_details.html").unwrap();
+        assert_eq!(
+            GooglePlayProvider::parse_listing(&html),
+            Listing {
+                version: Some("3.1.0".to_string()),
+                whats_new: Some("Sync fixes & a new widget.\nFaster startup.".to_string()),
+            }
+        );
+        assert_eq!(
+            GooglePlayProvider::parse_listing("<p>What\u{2019}s new</p><p>Fixes</p>"),
+            Listing {
+                version: None,
+                whats_new: Some("Fixes".to_string()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_releases() {
+        let body = fs::read_to_string("tests/files/web/google_play_details.html").unwrap();
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", LISTING_PATH)
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let app_data = AppDataMap::from([(ANDROID_APP_TYPE, PACKAGE_ID)]);
+        let proxy_url = format!("{} -> {}", GOOGLE_PLAY_URL, server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        let provider = GooglePlayProvider::new();
+        let releases = provider
+            .get_releases(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(
+            releases,
+            vec![ReleaseData {
+                version_number: "3.1.0".to_string(),
+                changelog: "Sync fixes & a new widget.\nFaster startup.".to_string(),
+                assets: vec![],
+                extra: None,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unavailable() {
+        let mut server = Server::new_async().await;
+        let _not_found = server
+            .mock("GET", LISTING_PATH)
+            .with_status(404)
+            .create_async()
+            .await;
+        let _region_locked = server
+            .mock("GET", "/store/apps/details?id=com.example.local&hl=en")
+            .with_status(200)
+            .with_body("<div>This item isn't available in your country.</div>")
+            .create_async()
+            .await;
+
+        let proxy_url = format!("{} -> {}", GOOGLE_PLAY_URL, server.url());
+        let hub_data = HubDataMap::from([(REVERSE_PROXY, proxy_url.as_str())]);
+        let provider = GooglePlayProvider::new();
+        for (package_id, message) in [
+            (
+                PACKAGE_ID,
+                "com.example.notes is not published on Google Play",
+            ),
+            (
+                "com.example.local",
+                "com.example.local is not available in this region",
+            ),
+        ] {
+            let app_data = AppDataMap::from([(ANDROID_APP_TYPE, package_id)]);
+            let fin = FIn::new_with_frag(&app_data, &hub_data, None);
+            assert!(!provider.check_app_available(&fin).await.result.unwrap());
+            let error = provider.get_releases(&fin).await.result.unwrap_err();
+            assert_eq!(error.to_string(), format!("GooglePlayError: {}", message));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        let body = fs::read("tests/files/web/google_play_details.html").unwrap();
+        run_conformance(
+            GooglePlayProvider::new(),
+            Fixture {
+                app_data: vec![(ANDROID_APP_TYPE, PACKAGE_ID)],
+                hub_data: vec![],
+                responses: vec![(LISTING_PATH, body.into())],
+            },
+        )
+        .await;
+    }
+}
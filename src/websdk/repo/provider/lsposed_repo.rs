@@ -19,19 +19,18 @@ impl LsposedRepoProvider {
         LsposedRepoProvider {}
     }
 
-    fn get_app_json(package_name: &str, body: &Bytes) -> Option<Value> {
-        if let Ok(json) = serde_json::from_slice::<Vec<Value>>(body) {
-            for i in json {
-                if let Some(name) = i.get("name") {
-                    if let Some(name_str) = name.as_str() {
-                        if name_str == package_name {
-                            return Some(i);
-                        }
+    fn get_app_json(package_name: &str, body: &Bytes) -> serde_json::Result<Option<Value>> {
+        let json = serde_json::from_slice::<Vec<Value>>(body)?;
+        for i in json {
+            if let Some(name) = i.get("name") {
+                if let Some(name_str) = name.as_str() {
+                    if name_str == package_name {
+                        return Ok(Some(i));
                     }
                 }
             }
         }
-        None
+        Ok(None)
     }
 }
 
@@ -39,6 +38,10 @@ impl BaseProviderExt for LsposedRepoProvider {}
 
 #[async_trait]
 impl BaseProvider for LsposedRepoProvider {
+    fn required_app_keys(&self) -> &'static [&'static str] {
+        &[ANDROID_APP_TYPE]
+    }
+
     fn get_cache_request_key(
         &self,
         _function_type: &FunctionType,
@@ -50,16 +53,21 @@ impl BaseProvider for LsposedRepoProvider {
     async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
         let url = self.replace_proxy_url(fin, LSPOSED_REPO_API_URL);
         let mut fout = FOut::new_empty();
-        let cache_body = fin.get_cache(&url);
+        // Cached under the origin URL, whichever proxy fetched it
+        let cache_body = fin.get_cache(LSPOSED_REPO_API_URL);
         let mut rsp_body = None;
         if cache_body.is_none() {
-            if let Ok(parsed_url) = url.parse() {
-                let map = HashMap::new();
-                if let Ok(rsp) = get(parsed_url, &map).await {
-                    if let Some(content) = rsp.body {
-                        fout = fout.set_cache(&url, content.clone());
-                        rsp_body = Some(content);
-                    }
+            let Ok(parsed_url) = url.parse() else {
+                return fout.set_parse_error(&fin.data_map, ParseError::Url { url });
+            };
+            let map = HashMap::new();
+            if let Ok(rsp) = get(parsed_url, &map).await {
+                let rsp_policy = rsp.cache_policy();
+                if let Some(content) = rsp.body {
+                    fout = fout
+                        .set_cache(LSPOSED_REPO_API_URL, content.clone())
+                        .set_cache_policy(LSPOSED_REPO_API_URL, rsp_policy);
+                    rsp_body = Some(content);
                 }
             }
         }
@@ -71,25 +79,39 @@ impl BaseProvider for LsposedRepoProvider {
         } else {
             return fout;
         }
-        let id_map = fin.data_map.app_data;
-        let package_id = id_map[ANDROID_APP_TYPE];
-        let json = LsposedRepoProvider::get_app_json(package_id, body);
-        fout.set_data(json.is_some())
+        let package_id = match fin.data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => package_id,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        match LsposedRepoProvider::get_app_json(package_id, body) {
+            Ok(json) => fout.set_data(json.is_some()),
+            Err(e) => fout.set_data(false).set_parse_error(
+                &fin.data_map,
+                ParseError::Body {
+                    url,
+                    reason: e.to_string(),
+                },
+            ),
+        }
     }
 
     async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
         let url = self.replace_proxy_url(fin, LSPOSED_REPO_API_URL);
         let mut fout = FOut::new_empty();
-        let cache_body = fin.get_cache(&url);
+        let cache_body = fin.get_cache(LSPOSED_REPO_API_URL);
         let mut rsp_body = None;
         if cache_body.is_none() {
-            if let Ok(parsed_url) = url.parse() {
-                let map = HashMap::new();
-                if let Ok(rsp) = get(parsed_url, &map).await {
-                    if let Some(content) = rsp.body {
-                        fout = fout.set_cache(&url, content.clone());
-                        rsp_body = Some(content);
-                    }
+            let Ok(parsed_url) = url.parse() else {
+                return fout.set_parse_error(&fin.data_map, ParseError::Url { url });
+            };
+            let map = HashMap::new();
+            if let Ok(rsp) = get(parsed_url, &map).await {
+                let rsp_policy = rsp.cache_policy();
+                if let Some(content) = rsp.body {
+                    fout = fout
+                        .set_cache(LSPOSED_REPO_API_URL, content.clone())
+                        .set_cache_policy(LSPOSED_REPO_API_URL, rsp_policy);
+                    rsp_body = Some(content);
                 }
             }
         }
@@ -101,9 +123,22 @@ impl BaseProvider for LsposedRepoProvider {
         } else {
             return fout;
         }
-        let id_map = fin.data_map.app_data;
-        let package_id = id_map[ANDROID_APP_TYPE];
-        let json = LsposedRepoProvider::get_app_json(package_id, body);
+        let package_id = match fin.data_map.get_app_value(ANDROID_APP_TYPE) {
+            Ok(package_id) => package_id,
+            Err(e) => return FOut::new_empty().set_error(Box::new(e)),
+        };
+        let json = match LsposedRepoProvider::get_app_json(package_id, body) {
+            Ok(json) => json,
+            Err(e) => {
+                return fout.set_parse_error(
+                    &fin.data_map,
+                    ParseError::Body {
+                        url,
+                        reason: e.to_string(),
+                    },
+                )
+            }
+        };
         if let Some(json) = json {
             if let Some(releases_block) = json.get("releases") {
                 if let Some(release_json) = releases_block.as_array() {
@@ -127,6 +162,16 @@ impl BaseProvider for LsposedRepoProvider {
                                             .get("downloadUrl")?
                                             .as_str()?
                                             .to_string(),
+                                        size: asset.get("size").and_then(|v| v.as_u64()),
+                                        extra: asset
+                                            .get("downloadCount")
+                                            .and_then(|v| v.as_u64())
+                                            .map(|count| {
+                                                HashMap::from([(
+                                                    DOWNLOAD_COUNT_KEY.to_string(),
+                                                    count.to_string(),
+                                                )])
+                                            }),
                                     })
                                             })
                                             .collect();
@@ -156,8 +201,18 @@ impl BaseProvider for LsposedRepoProvider {
                             }
                             None
                         })
-                        .collect();
+                        .collect::<Vec<_>>();
+                    let all_dropped = release_list.is_empty() && !release_json.is_empty();
                     fout = fout.set_data(release_list);
+                    if all_dropped {
+                        fout = fout.set_parse_error(
+                            &fin.data_map,
+                            ParseError::AllDropped {
+                                url,
+                                count: release_json.len(),
+                            },
+                        );
+                    }
                 }
             }
         }
@@ -167,6 +222,7 @@ impl BaseProvider for LsposedRepoProvider {
 
 #[cfg(test)]
 mod tests {
+    use super::super::conformance::{is_parse_error, run_conformance, Fixture};
     use super::*;
     use mockito::Server;
     use std::fs;
@@ -221,4 +277,61 @@ mod tests {
         let releases_saved = serde_json::from_str::<Vec<ReleaseData>>(&release_json).unwrap();
         assert_eq!(releases, releases_saved)
     }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        let body = fs::read("tests/files/web/lsposed_modules.json").unwrap();
+        run_conformance(
+            LsposedRepoProvider::new(),
+            Fixture {
+                app_data: vec![(ANDROID_APP_TYPE, "com.agoines.relaxhelp")],
+                hub_data: vec![],
+                responses: vec![("/modules.json", body.into())],
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_strict_parsing() {
+        let id_map = AppDataMap::from([(ANDROID_APP_TYPE, "com.agoines.relaxhelp")]);
+        let lsposed_provider = &LsposedRepoProvider::new();
+        for strict in [false, true] {
+            let hub_data =
+                HubDataMap::from([(STRICT_PARSING, if strict { "true" } else { "false" })]);
+            let fin = |body: &'static str| {
+                let cache_map =
+                    HashMap::from([(LSPOSED_REPO_API_URL.to_string(), Bytes::from(body))]);
+                FIn::new_with_frag(&id_map, &hub_data, Some(cache_map))
+            };
+
+            let fout = lsposed_provider
+                .check_app_available(&fin(r#"{"error": "rate limited"}"#))
+                .await;
+            assert_eq!(is_parse_error(&fout), strict);
+            if !strict {
+                assert!(!fout.result.unwrap());
+            }
+            let fout = lsposed_provider
+                .get_releases(&fin(r#"{"error": "rate limited"}"#))
+                .await;
+            assert_eq!(is_parse_error(&fout), strict);
+            // No release has a version number
+            let fout = lsposed_provider
+                .get_releases(&fin(
+                    r#"[{"name": "com.agoines.relaxhelp", "releases": [{"name": "nightly", "releaseAssets": []}]}]"#,
+                ))
+                .await;
+            assert_eq!(is_parse_error(&fout), strict);
+            if !strict {
+                assert!(fout.result.unwrap().is_empty());
+            }
+            let fout = lsposed_provider
+                .get_releases(&fin(
+                    r#"[{"name": "com.agoines.relaxhelp", "releases": []}]"#,
+                ))
+                .await;
+            assert!(fout.result.unwrap().is_empty());
+        }
+    }
 }
@@ -42,6 +42,8 @@ impl BaseProvider for OutsideProvider {
                 Err(e) => Err(Box::new(e)),
             },
             cached_map: None,
+            cache_policy_map: None,
+            validators_map: None,
         }
     }
 
@@ -63,6 +65,8 @@ impl BaseProvider for OutsideProvider {
                 Err(e) => Err(Box::new(e)),
             },
             cached_map: None,
+            cache_policy_map: None,
+            validators_map: None,
         }
     }
 
@@ -84,6 +88,8 @@ impl BaseProvider for OutsideProvider {
                 Err(e) => Err(Box::new(e)),
             },
             cached_map: None,
+            cache_policy_map: None,
+            validators_map: None,
         }
     }
 }
@@ -0,0 +1,335 @@
+use async_trait::async_trait;
+use regex::Regex;
+use std::error::Error;
+use std::time::Duration;
+
+use super::super::data::release::*;
+use super::base_provider::*;
+use crate::utils::exec::{self, get_exec_policy, ExecError};
+
+pub const SHELL_HUB_UUID: &str = "4de20609-cbbb-4c55-91f8-112e783f0a7c";
+
+const SHELL_CHECK_COMMAND_KEY: &str = "shell_check_command";
+const SHELL_VERSION_REGEX_KEY: &str = "shell_version_regex";
+const SHELL_TIMEOUT_KEY: &str = "shell_timeout";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug)]
+pub struct ShellError {
+    pub message: String,
+    pub stderr: String,
+}
+
+impl std::fmt::Display for ShellError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.stderr.is_empty() {
+            write!(f, "ShellError: {}", self.message)
+        } else {
+            write!(f, "ShellError: {} (stderr: {})", self.message, self.stderr)
+        }
+    }
+}
+
+impl std::error::Error for ShellError {}
+
+struct ShellOutput {
+    success: bool,
+    stdout: String,
+    stderr: String,
+}
+
+/// Runs version-check commands supplied by the app/hub config.
+///
+/// The command template is read from `android_custom_shell` (or
+/// `android_custom_shell_root`, which wraps it in `su -c`), first in
+/// app_data, then in hub_data. `%key` placeholders are replaced with the
+/// single-quoted app_data value of `key`.
+pub struct ShellProvider;
+
+impl ShellProvider {
+    pub fn new() -> Self {
+        ShellProvider {}
+    }
+
+    fn get_value<'a>(data_map: &DataMap<'a>, key: &str) -> Option<&'a str> {
+        data_map
+            .app_data
+            .get(key)
+            .or_else(|| data_map.hub_data.get(key))
+            .copied()
+    }
+
+    fn get_command_template<'a>(data_map: &DataMap<'a>) -> Option<(&'a str, bool)> {
+        if let Some(command) = Self::get_value(data_map, ANDROID_CUSTOM_SHELL_ROOT) {
+            Some((command, true))
+        } else {
+            Self::get_value(data_map, ANDROID_CUSTOM_SHELL).map(|command| (command, false))
+        }
+    }
+
+    fn quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+
+    fn render_command(template: &str, data_map: &DataMap) -> String {
+        // Longest keys first, so `%repo` doesn't eat the prefix of `%repo_name`
+        let mut keys = data_map.app_data.keys().collect::<Vec<_>>();
+        keys.sort_by_key(|key| std::cmp::Reverse(key.len()));
+        // Single pass over the template: inserted values are never scanned again
+        let mut command = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(pos) = rest.find('%') {
+            command.push_str(&rest[..pos]);
+            let after = &rest[pos + 1..];
+            match keys.iter().find(|key| after.starts_with(**key)) {
+                Some(key) => {
+                    command.push_str(&Self::quote(data_map.app_data[*key]));
+                    rest = &after[key.len()..];
+                }
+                None => {
+                    command.push('%');
+                    rest = after;
+                }
+            }
+        }
+        command.push_str(rest);
+        command
+    }
+
+    fn get_timeout(data_map: &DataMap) -> Duration {
+        let secs = Self::get_value(data_map, SHELL_TIMEOUT_KEY)
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        Duration::from_secs(secs)
+    }
+
+    async fn run(
+        command: &str,
+        root: bool,
+        timeout: Duration,
+    ) -> Result<ShellOutput, Box<dyn Error + Send + Sync>> {
+        let program = if root { "su" } else { "sh" };
+        let output = exec::run(&get_exec_policy(), program, &["-c", command], timeout)
+            .await
+            .map_err(|e| -> Box<dyn Error + Send + Sync> {
+                match e {
+                    ExecError::Policy(e) => Box::new(e),
+                    e => Box::new(ShellError {
+                        message: e.to_string(),
+                        stderr: String::new(),
+                    }),
+                }
+            })?;
+        Ok(ShellOutput {
+            success: output.success,
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+
+    fn parse_version(stdout: &str, regex: Option<&str>) -> Result<String, ShellError> {
+        let version = if let Some(regex) = regex {
+            let re = Regex::new(regex).map_err(|e| ShellError {
+                message: format!("invalid {}: {}", SHELL_VERSION_REGEX_KEY, e),
+                stderr: String::new(),
+            })?;
+            re.captures(stdout).and_then(|caps| {
+                caps.get(1)
+                    .or_else(|| caps.get(0))
+                    .map(|m| m.as_str().trim().to_string())
+            })
+        } else {
+            Some(stdout.trim().to_string())
+        };
+        version
+            .filter(|version| !version.is_empty())
+            .ok_or_else(|| ShellError {
+                message: "no version in output".to_string(),
+                stderr: String::new(),
+            })
+    }
+}
+
+impl BaseProviderExt for ShellProvider {}
+
+#[async_trait]
+impl BaseProvider for ShellProvider {
+    fn get_cache_request_key(
+        &self,
+        _function_type: &FunctionType,
+        _data_map: &DataMap,
+    ) -> Vec<String> {
+        vec![]
+    }
+
+    async fn check_app_available(&self, fin: &FIn) -> FOut<bool> {
+        let data_map = &fin.data_map;
+        let timeout = Self::get_timeout(data_map);
+        let (template, root) =
+            if let Some(command) = Self::get_value(data_map, SHELL_CHECK_COMMAND_KEY) {
+                (
+                    command,
+                    Self::get_value(data_map, ANDROID_CUSTOM_SHELL_ROOT).is_some(),
+                )
+            } else if let Some(command) = Self::get_command_template(data_map) {
+                command
+            } else {
+                return FOut::new_empty();
+            };
+        let command = Self::render_command(template, data_map);
+        match Self::run(&command, root, timeout).await {
+            Ok(output) => FOut::new(output.success),
+            Err(e) => FOut::new_empty().set_error(e),
+        }
+    }
+
+    async fn get_releases(&self, fin: &FIn) -> FOut<Vec<ReleaseData>> {
+        let data_map = &fin.data_map;
+        let (template, root) = match Self::get_command_template(data_map) {
+            Some(command) => command,
+            None => return FOut::new_empty(),
+        };
+        let command = Self::render_command(template, data_map);
+        let output = match Self::run(&command, root, Self::get_timeout(data_map)).await {
+            Ok(output) => output,
+            Err(e) => return FOut::new_empty().set_error(e),
+        };
+        if !output.success {
+            return FOut::new_empty().set_error(Box::new(ShellError {
+                message: "command exited with non-zero status".to_string(),
+                stderr: output.stderr,
+            }));
+        }
+        match Self::parse_version(
+            &output.stdout,
+            Self::get_value(data_map, SHELL_VERSION_REGEX_KEY),
+        ) {
+            Ok(version_number) => FOut::new(vec![ReleaseData {
+                version_number,
+                changelog: "".to_string(),
+                assets: vec![],
+                extra: None,
+            }]),
+            Err(e) => FOut::new_empty().set_error(Box::new(ShellError {
+                stderr: output.stderr,
+                ..e
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_get_releases() {
+        let app_data = AppDataMap::from([(ANDROID_CUSTOM_SHELL, "echo 1.2.3")]);
+        let hub_data = HubDataMap::new();
+        let provider = ShellProvider::new();
+        let releases = provider
+            .get_releases(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(releases.len(), 1);
+        assert_eq!(releases[0].version_number, "1.2.3");
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_release_with_regex() {
+        let app_data = AppDataMap::from([(
+            ANDROID_CUSTOM_SHELL,
+            "echo 'versionName=2.0.1-beta'; echo 'versionCode=42'",
+        )]);
+        let hub_data = HubDataMap::from([(SHELL_VERSION_REGEX_KEY, r"versionName=(\S+)")]);
+        let provider = ShellProvider::new();
+        let release = provider
+            .get_latest_release(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(release.version_number, "2.0.1-beta");
+    }
+
+    #[tokio::test]
+    async fn test_get_releases_error_with_stderr() {
+        let app_data = AppDataMap::from([(ANDROID_CUSTOM_SHELL, "echo broken >&2; exit 3")]);
+        let hub_data = HubDataMap::new();
+        let provider = ShellProvider::new();
+        let result = provider
+            .get_releases(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result;
+        assert!(result.unwrap_err().to_string().contains("broken"));
+    }
+
+    #[tokio::test]
+    async fn test_check_app_available() {
+        let hub_data = HubDataMap::new();
+        let provider = ShellProvider::new();
+
+        let app_data = AppDataMap::from([
+            (ANDROID_CUSTOM_SHELL, "echo 1.0"),
+            (SHELL_CHECK_COMMAND_KEY, "exit 0"),
+        ]);
+        let fout = provider
+            .check_app_available(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await;
+        assert!(fout.result.unwrap());
+
+        let app_data = AppDataMap::from([
+            (ANDROID_CUSTOM_SHELL, "echo 1.0"),
+            (SHELL_CHECK_COMMAND_KEY, "exit 1"),
+        ]);
+        let fout = provider
+            .check_app_available(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await;
+        assert!(!fout.result.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let app_data = AppDataMap::from([(ANDROID_CUSTOM_SHELL, "sleep 10; echo 1.0")]);
+        let hub_data = HubDataMap::from([(SHELL_TIMEOUT_KEY, "1")]);
+        let provider = ShellProvider::new();
+        let start = Instant::now();
+        let result = provider
+            .get_releases(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result;
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(result.unwrap_err().to_string().contains("timeout"));
+    }
+
+    #[tokio::test]
+    async fn test_render_command_quoting() {
+        let app_data = AppDataMap::from([
+            (ANDROID_CUSTOM_SHELL, "echo %package"),
+            ("package", "a'b\"; echo injected"),
+        ]);
+        let hub_data = HubDataMap::new();
+        let provider = ShellProvider::new();
+        let releases = provider
+            .get_releases(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(releases[0].version_number, "a'b\"; echo injected");
+
+        // A value naming another placeholder is not substituted again
+        let app_data = AppDataMap::from([
+            (ANDROID_CUSTOM_SHELL, "echo %package"),
+            ("package", "%x"),
+            ("x", "; echo INJECTED #"),
+        ]);
+        let releases = provider
+            .get_releases(&FIn::new_with_frag(&app_data, &hub_data, None))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(releases[0].version_number, "%x");
+    }
+}